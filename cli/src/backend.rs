@@ -0,0 +1,45 @@
+use crate::Error;
+
+/// Mirror of the vault's `AccountPosition`
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct Position {
+    pub shares: i128,
+    pub principal: i128,
+    pub value: i128,
+    pub earned: i128,
+}
+
+/// Mirror of the vault's `AccountEarnings`
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct Earnings {
+    pub realized: i128,
+    pub unrealized: i128,
+    pub total: i128,
+}
+
+/// The vault entrypoints the CLI uses. Accounts are strkeys, amounts are raw
+/// units as the contract sees them.
+pub trait VaultBackend {
+    /// Share decimals, the asset's plus the vault's `decimals_offset`
+    fn decimals(&self) -> Result<u32, Error>;
+    /// Decimals of the underlying asset, read from the token `query_asset`
+    /// returns
+    fn asset_decimals(&self) -> Result<u32, Error>;
+    fn total_assets(&self) -> Result<i128, Error>;
+    fn total_supply(&self) -> Result<i128, Error>;
+    fn convert_to_assets(&self, shares: i128) -> Result<i128, Error>;
+    fn idle_assets(&self) -> Result<i128, Error>;
+    fn depositor_count(&self) -> Result<u32, Error>;
+    fn position_of(&self, account: &str) -> Result<Position, Error>;
+    fn earnings_of(&self, account: &str) -> Result<Earnings, Error>;
+    fn preview_deposit(&self, assets: i128) -> Result<i128, Error>;
+    fn preview_withdraw(&self, assets: i128) -> Result<i128, Error>;
+    /// Non-zero balances of registry entries `start..start + limit`
+    fn depositors_snapshot_page(
+        &self,
+        start: u32,
+        limit: u32,
+    ) -> Result<Vec<(String, i128)>, Error>;
+    /// Submits `compound_with_min_out`, the only call that changes state
+    fn compound_with_min_out(&self, operator: &str, min_out: i128) -> Result<i128, Error>;
+}
@@ -0,0 +1,243 @@
+use std::io::Write;
+
+use serde_json::{Map, Value};
+
+use crate::{Error, VaultBackend};
+
+/// Registry entries read per `get_depositors_snapshot_page` call, the
+/// vault's own page cap
+pub const SNAPSHOT_PAGE_SIZE: u32 = 200;
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum PreviewKind {
+    Deposit,
+    Withdraw,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Format {
+    Csv,
+    Json,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Command {
+    /// Vault totals and share price
+    State,
+    /// Shares, cost basis and earnings of one account
+    Position { account: String },
+    /// Shares minted by a deposit or burned by a withdrawal of `assets`
+    Preview { kind: PreviewKind, assets: i128 },
+    /// Compound as `operator`, reverting below `min_out` assets
+    Compound { operator: String, min_out: i128 },
+    /// Every depositor with a non-zero share balance
+    Snapshot { format: Format },
+}
+
+fn usage(message: &str) -> Error {
+    Error::Usage(message.to_string())
+}
+
+fn parse_amount(value: Option<&str>, what: &str) -> Result<i128, Error> {
+    let value = value.ok_or_else(|| usage(&format!("missing {}", what)))?;
+    value
+        .parse()
+        .map_err(|_| usage(&format!("invalid {}: {}", what, value)))
+}
+
+impl Command {
+    /// Parse the command and its arguments, `operator` is used by `compound`
+    /// when no `--operator` is given
+    pub fn parse(args: &[String], operator: &str) -> Result<Self, Error> {
+        let args: Vec<&str> = args.iter().map(String::as_str).collect();
+        match args.as_slice() {
+            ["state"] => Ok(Command::State),
+            ["position", account] => Ok(Command::Position {
+                account: account.to_string(),
+            }),
+            ["preview", kind, assets] => {
+                let kind = match *kind {
+                    "deposit" => PreviewKind::Deposit,
+                    "withdraw" => PreviewKind::Withdraw,
+                    _ => return Err(usage("preview takes deposit or withdraw")),
+                };
+                Ok(Command::Preview {
+                    kind,
+                    assets: parse_amount(Some(assets), "assets")?,
+                })
+            }
+            ["compound", options @ ..] => {
+                let mut min_out = None;
+                let mut operator = operator.to_string();
+                let mut options = options.iter();
+                while let Some(option) = options.next() {
+                    match *option {
+                        "--min-out" => {
+                            min_out = Some(parse_amount(options.next().copied(), "--min-out")?)
+                        }
+                        "--operator" => {
+                            operator = options
+                                .next()
+                                .ok_or_else(|| usage("missing --operator"))?
+                                .to_string()
+                        }
+                        _ => return Err(usage(&format!("unknown option {}", option))),
+                    }
+                }
+                Ok(Command::Compound {
+                    operator,
+                    min_out: min_out.ok_or_else(|| usage("compound requires --min-out"))?,
+                })
+            }
+            ["snapshot"] => Ok(Command::Snapshot {
+                format: Format::Csv,
+            }),
+            ["snapshot", "--format", format] => Ok(Command::Snapshot {
+                format: match *format {
+                    "csv" => Format::Csv,
+                    "json" => Format::Json,
+                    _ => return Err(usage("snapshot format must be csv or json")),
+                },
+            }),
+            [] => Err(usage("missing command")),
+            [command, ..] => Err(usage(&format!("invalid arguments for {}", command))),
+        }
+    }
+
+    pub fn execute(&self, backend: &dyn VaultBackend, out: &mut dyn Write) -> Result<(), Error> {
+        match self {
+            Command::State => state(backend, out),
+            Command::Position { account } => position(backend, account, out),
+            Command::Preview { kind, assets } => {
+                let asset_decimals = backend.asset_decimals()?;
+                let share_decimals = backend.decimals()?;
+                let (label, shares) = match kind {
+                    PreviewKind::Deposit => ("shares_minted", backend.preview_deposit(*assets)?),
+                    PreviewKind::Withdraw => ("shares_burned", backend.preview_withdraw(*assets)?),
+                };
+                writeln!(
+                    out,
+                    "assets         {}",
+                    format_amount(*assets, asset_decimals)
+                )?;
+                writeln!(
+                    out,
+                    "{:<14} {}",
+                    label,
+                    format_amount(shares, share_decimals)
+                )?;
+                Ok(())
+            }
+            Command::Compound { operator, min_out } => {
+                let asset_decimals = backend.asset_decimals()?;
+                let received = backend.compound_with_min_out(operator, *min_out)?;
+                writeln!(
+                    out,
+                    "assets_received {}",
+                    format_amount(received, asset_decimals)
+                )?;
+                Ok(())
+            }
+            Command::Snapshot { format } => snapshot(backend, *format, out),
+        }
+    }
+}
+
+/// Render raw units with `decimals` places, e.g. `12.3400000`
+pub fn format_amount(value: i128, decimals: u32) -> String {
+    let sign = if value < 0 { "-" } else { "" };
+    let value = value.unsigned_abs();
+    if decimals == 0 {
+        return format!("{}{}", sign, value);
+    }
+    let scale = 10u128.pow(decimals);
+    format!(
+        "{}{}.{:0width$}",
+        sign,
+        value / scale,
+        value % scale,
+        width = decimals as usize
+    )
+}
+
+fn state(backend: &dyn VaultBackend, out: &mut dyn Write) -> Result<(), Error> {
+    let asset_decimals = backend.asset_decimals()?;
+    let share_decimals = backend.decimals()?;
+    let one_share = 10i128.pow(share_decimals);
+    writeln!(
+        out,
+        "total_assets   {}",
+        format_amount(backend.total_assets()?, asset_decimals)
+    )?;
+    writeln!(
+        out,
+        "total_supply   {}",
+        format_amount(backend.total_supply()?, share_decimals)
+    )?;
+    writeln!(
+        out,
+        "share_price    {}",
+        format_amount(backend.convert_to_assets(one_share)?, asset_decimals)
+    )?;
+    writeln!(
+        out,
+        "idle_assets    {}",
+        format_amount(backend.idle_assets()?, asset_decimals)
+    )?;
+    writeln!(out, "depositors     {}", backend.depositor_count()?)?;
+    Ok(())
+}
+
+fn position(backend: &dyn VaultBackend, account: &str, out: &mut dyn Write) -> Result<(), Error> {
+    let asset_decimals = backend.asset_decimals()?;
+    let position = backend.position_of(account)?;
+    let earnings = backend.earnings_of(account)?;
+    writeln!(
+        out,
+        "shares         {}",
+        format_amount(position.shares, backend.decimals()?)
+    )?;
+    for (label, value) in [
+        ("principal", position.principal),
+        ("value", position.value),
+        ("unrealized", earnings.unrealized),
+        ("realized", earnings.realized),
+        ("total_earned", earnings.total),
+    ] {
+        writeln!(
+            out,
+            "{:<14} {}",
+            label,
+            format_amount(value, asset_decimals)
+        )?;
+    }
+    Ok(())
+}
+
+fn snapshot(backend: &dyn VaultBackend, format: Format, out: &mut dyn Write) -> Result<(), Error> {
+    let count = backend.depositor_count()?;
+    let mut balances = Vec::new();
+    let mut start = 0;
+    while start < count {
+        balances.extend(backend.depositors_snapshot_page(start, SNAPSHOT_PAGE_SIZE)?);
+        start += SNAPSHOT_PAGE_SIZE;
+    }
+    match format {
+        Format::Csv => {
+            writeln!(out, "account,shares")?;
+            for (account, shares) in balances {
+                writeln!(out, "{},{}", account, shares)?;
+            }
+        }
+        Format::Json => {
+            // Balances as decimal strings, the way the stellar CLI prints
+            // `get_depositors_snapshot`
+            let object: Map<String, Value> = balances
+                .into_iter()
+                .map(|(account, shares)| (account, Value::String(shares.to_string())))
+                .collect();
+            writeln!(out, "{}", serde_json::to_string_pretty(&object)?)?;
+        }
+    }
+    Ok(())
+}
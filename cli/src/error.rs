@@ -0,0 +1,47 @@
+use std::{fmt, io};
+
+#[derive(Debug)]
+pub enum Error {
+    /// Bad command line, carries the message shown with the usage
+    Usage(String),
+    /// The vault rejected a call or the call could not be made
+    Invoke {
+        function: String,
+        message: String,
+    },
+    /// A call returned something other than the expected type
+    InvalidValue {
+        function: String,
+        value: String,
+    },
+    Io(io::Error),
+    Json(serde_json::Error),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Usage(message) => write!(f, "{}", message),
+            Error::Invoke { function, message } => write!(f, "{} failed: {}", function, message),
+            Error::InvalidValue { function, value } => {
+                write!(f, "{} returned an unexpected value: {}", function, value)
+            }
+            Error::Io(err) => write!(f, "{}", err),
+            Error::Json(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<io::Error> for Error {
+    fn from(err: io::Error) -> Self {
+        Error::Io(err)
+    }
+}
+
+impl From<serde_json::Error> for Error {
+    fn from(err: serde_json::Error) -> Self {
+        Error::Json(err)
+    }
+}
@@ -0,0 +1,21 @@
+//! Command line access to a deployed Blend Vault.
+//!
+//! Every command goes through [`VaultBackend`], the handful of vault
+//! entrypoints the CLI needs. [`RpcBackend`] calls them on a live network
+//! over Soroban RPC with the vault's own contract types; the tests run the
+//! same commands against a local Soroban sandbox.
+
+mod backend;
+mod commands;
+mod error;
+mod rpc;
+
+pub use backend::{Earnings, Position, VaultBackend};
+pub use commands::{format_amount, Command, Format, PreviewKind, SNAPSHOT_PAGE_SIZE};
+pub use error::Error;
+pub use rpc::{RpcBackend, DEFAULT_RPC_URL, MAINNET_PASSPHRASE};
+
+#[cfg(test)]
+mod sandbox;
+#[cfg(test)]
+mod test;
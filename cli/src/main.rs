@@ -0,0 +1,87 @@
+use std::{env, io, process::ExitCode};
+
+use blend_vault_cli::{Command, Error, RpcBackend, DEFAULT_RPC_URL, MAINNET_PASSPHRASE};
+
+const USAGE: &str = "usage: blend-vault-cli [OPTIONS] COMMAND
+
+Commands:
+  state                              vault totals, share price and depositor count
+  position ACCOUNT                   shares, cost basis and earnings of ACCOUNT
+  preview deposit|withdraw ASSETS    shares minted or burned for ASSETS raw units
+  compound --min-out ASSETS [--operator ACCOUNT]
+                                     harvest and reinvest BLND, reverting if the
+                                     swap returns less than ASSETS
+  snapshot [--format csv|json]       every depositor's share balance
+
+Options (environment variable in brackets):
+  --vault ID                  vault contract id [BLEND_VAULT_ID]
+  --rpc-url URL               Soroban RPC endpoint [BLEND_VAULT_RPC_URL]
+  --network-passphrase TEXT   network passphrase [BLEND_VAULT_NETWORK_PASSPHRASE]
+  --source KEY                G... account to simulate from, or the S... secret
+                              key compound signs with; its account is the
+                              default compound operator [BLEND_VAULT_SOURCE]
+
+Calls go straight to the RPC endpoint. Only compound submits a transaction,
+everything else is simulated.";
+
+fn option(value: Option<String>, name: &str) -> Result<String, Error> {
+    value.ok_or_else(|| Error::Usage(format!("missing value for {}", name)))
+}
+
+fn run() -> Result<(), Error> {
+    let mut rpc_url =
+        env::var("BLEND_VAULT_RPC_URL").unwrap_or_else(|_| DEFAULT_RPC_URL.to_string());
+    let mut network_passphrase = env::var("BLEND_VAULT_NETWORK_PASSPHRASE")
+        .unwrap_or_else(|_| MAINNET_PASSPHRASE.to_string());
+    let mut vault = env::var("BLEND_VAULT_ID").unwrap_or_default();
+    let mut source = env::var("BLEND_VAULT_SOURCE").unwrap_or_default();
+
+    let mut args = env::args().skip(1);
+    let mut command_args = Vec::new();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--vault" => vault = option(args.next(), "--vault")?,
+            "--rpc-url" => rpc_url = option(args.next(), "--rpc-url")?,
+            "--network-passphrase" => {
+                network_passphrase = option(args.next(), "--network-passphrase")?
+            }
+            "--source" => source = option(args.next(), "--source")?,
+            "-h" | "--help" => {
+                println!("{}", USAGE);
+                return Ok(());
+            }
+            _ => {
+                command_args.push(arg);
+                command_args.extend(args.by_ref());
+            }
+        }
+    }
+
+    if vault.is_empty() {
+        return Err(Error::Usage(
+            "no vault given, pass --vault or set BLEND_VAULT_ID".into(),
+        ));
+    }
+    if source.is_empty() {
+        return Err(Error::Usage(
+            "no source given, pass --source or set BLEND_VAULT_SOURCE".into(),
+        ));
+    }
+    let backend = RpcBackend::new(&rpc_url, &network_passphrase, &vault, &source)?;
+    let command = Command::parse(&command_args, &backend.source_account())?;
+    command.execute(&backend, &mut io::stdout().lock())
+}
+
+fn main() -> ExitCode {
+    match run() {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(Error::Usage(message)) => {
+            eprintln!("{}\n\n{}", message, USAGE);
+            ExitCode::from(2)
+        }
+        Err(err) => {
+            eprintln!("{}", err);
+            ExitCode::FAILURE
+        }
+    }
+}
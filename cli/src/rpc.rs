@@ -0,0 +1,465 @@
+use blend_vault::{AccountEarnings, AccountPosition, BlendVaultContractArgs};
+use ed25519_dalek::{Signer, SigningKey};
+use sha2::{Digest, Sha256};
+use soroban_sdk::{
+    xdr::{
+        DecoratedSignature, Hash, HostFunction, InvokeContractArgs, InvokeHostFunctionOp, Limits,
+        Memo, MuxedAccount, Operation, OperationBody, Preconditions, ScSymbol, ScVal,
+        SequenceNumber, Signature, SignatureHint, SorobanCredentials, Transaction,
+        TransactionEnvelope, TransactionExt, TransactionSignaturePayload,
+        TransactionSignaturePayloadTaggedTransaction, TransactionV1Envelope, Uint256, WriteXdr,
+    },
+    Address, Env, IntoVal, Map, TryFromVal, Val, Vec as SorobanVec,
+};
+use stellar_rpc_client::Client;
+use stellar_strkey::{ed25519, Strkey};
+use tokio::runtime::Runtime;
+
+use crate::{Earnings, Error, Position, VaultBackend};
+
+pub const DEFAULT_RPC_URL: &str = "https://rpc.lightsail.network/";
+pub const MAINNET_PASSPHRASE: &str = "Public Global Stellar Network ; September 2015";
+
+/// Inclusion fee in stroops, the resource fee from simulation is added on top
+const BASE_FEE: u32 = 100;
+
+/// Talks to a deployed vault over Soroban RPC. Arguments are built with the
+/// vault's generated `BlendVaultContractArgs` and results decoded into its
+/// contract types. Read-only calls are only simulated, `compound_with_min_out`
+/// is signed with the source secret key and submitted.
+pub struct RpcBackend {
+    client: Client,
+    runtime: Runtime,
+    env: Env,
+    network_passphrase: String,
+    vault: Address,
+    source: ed25519::PublicKey,
+    /// Only needed to submit, reads work from a public key alone
+    signing_key: Option<SigningKey>,
+}
+
+impl RpcBackend {
+    /// `source` is a `G...` account to simulate from or an `S...` secret key,
+    /// which is also required to compound
+    pub fn new(
+        rpc_url: &str,
+        network_passphrase: &str,
+        vault: &str,
+        source: &str,
+    ) -> Result<Self, Error> {
+        let env = Env::default();
+        let vault = match Strkey::from_string(vault) {
+            Ok(Strkey::Contract(_)) => Address::from_str(&env, vault),
+            _ => {
+                return Err(Error::Usage(format!(
+                    "invalid vault contract id: {}",
+                    vault
+                )))
+            }
+        };
+        let (source, signing_key) = match Strkey::from_string(source) {
+            Ok(Strkey::PublicKeyEd25519(public_key)) => (public_key, None),
+            Ok(Strkey::PrivateKeyEd25519(secret_key)) => {
+                let signing_key = SigningKey::from_bytes(&secret_key.0);
+                let public_key = ed25519::PublicKey(signing_key.verifying_key().to_bytes());
+                (public_key, Some(signing_key))
+            }
+            _ => {
+                return Err(Error::Usage(
+                    "source must be a G... account or an S... secret key".into(),
+                ))
+            }
+        };
+        let client = Client::new(rpc_url).map_err(|err| Error::Usage(err.to_string()))?;
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()?;
+        Ok(Self {
+            client,
+            runtime,
+            env,
+            network_passphrase: network_passphrase.to_string(),
+            vault,
+            source,
+            signing_key,
+        })
+    }
+
+    /// Strkey of the source account, the default compound operator
+    pub fn source_account(&self) -> String {
+        self.source.to_string()
+    }
+
+    /// A single `InvokeHostFunction` transaction calling `function` on
+    /// `contract`, not yet simulated
+    pub(crate) fn transaction(
+        &self,
+        contract: &Address,
+        function: &str,
+        args: SorobanVec<Val>,
+        seq_num: i64,
+    ) -> Result<Transaction, Error> {
+        let invalid = |value: String| Error::InvalidValue {
+            function: function.to_string(),
+            value,
+        };
+        let ScVal::Address(contract_address) = ScVal::from(contract) else {
+            return Err(invalid(strkey(contract)));
+        };
+        let args = args
+            .iter()
+            .map(|arg| ScVal::try_from_val(&self.env, &arg))
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|err| invalid(format!("{:?}", err)))?;
+        let host_function = HostFunction::InvokeContract(InvokeContractArgs {
+            contract_address,
+            function_name: ScSymbol(
+                function
+                    .try_into()
+                    .map_err(|_| invalid(function.to_string()))?,
+            ),
+            args: args.try_into().map_err(|_| invalid("args".to_string()))?,
+        });
+        let operation = Operation {
+            source_account: None,
+            body: OperationBody::InvokeHostFunction(InvokeHostFunctionOp {
+                host_function,
+                auth: Default::default(),
+            }),
+        };
+        Ok(Transaction {
+            source_account: MuxedAccount::Ed25519(Uint256(self.source.0)),
+            fee: BASE_FEE,
+            seq_num: SequenceNumber(seq_num),
+            cond: Preconditions::None,
+            memo: Memo::None,
+            operations: vec![operation]
+                .try_into()
+                .map_err(|_| invalid("operations".to_string()))?,
+            ext: TransactionExt::V0,
+        })
+    }
+
+    fn rpc_error(function: &str, err: impl std::fmt::Display) -> Error {
+        Error::Invoke {
+            function: function.to_string(),
+            message: err.to_string(),
+        }
+    }
+
+    /// Simulate `function` and return its result, nothing is submitted
+    fn view(
+        &self,
+        contract: &Address,
+        function: &str,
+        args: SorobanVec<Val>,
+    ) -> Result<ScVal, Error> {
+        // Simulation does not check the sequence number
+        let tx = self.transaction(contract, function, args, 0)?;
+        let envelope = envelope(tx, Default::default());
+        let simulation = self
+            .runtime
+            .block_on(self.client.simulate_transaction_envelope(&envelope, None))
+            .map_err(|err| Self::rpc_error(function, err))?;
+        if let Some(message) = simulation.error {
+            return Err(Self::rpc_error(function, message));
+        }
+        let results = simulation
+            .results()
+            .map_err(|err| Self::rpc_error(function, err))?;
+        results
+            .into_iter()
+            .next()
+            .map(|result| result.xdr)
+            .ok_or_else(|| Self::rpc_error(function, "simulation returned no result"))
+    }
+
+    /// Simulate `function`, attach the resources and auth it reported, then
+    /// sign with the source key, submit and wait for the result
+    fn send(
+        &self,
+        contract: &Address,
+        function: &str,
+        args: SorobanVec<Val>,
+    ) -> Result<ScVal, Error> {
+        let signing_key = self.signing_key.as_ref().ok_or_else(|| {
+            Error::Usage(format!("{} needs an S... secret key as source", function))
+        })?;
+        let account = self
+            .runtime
+            .block_on(self.client.get_account(&self.source.to_string()))
+            .map_err(|err| Self::rpc_error(function, err))?;
+        let mut tx = self.transaction(contract, function, args, account.seq_num.0 + 1)?;
+
+        let simulation = self
+            .runtime
+            .block_on(
+                self.client
+                    .simulate_transaction_envelope(&envelope(tx.clone(), Default::default()), None),
+            )
+            .map_err(|err| Self::rpc_error(function, err))?;
+        if let Some(message) = simulation.error {
+            return Err(Self::rpc_error(function, message));
+        }
+        let auth = simulation
+            .results()
+            .map_err(|err| Self::rpc_error(function, err))?
+            .into_iter()
+            .next()
+            .map(|result| result.auth)
+            .unwrap_or_default();
+        // Only the source account signs, so every authorization has to come
+        // from it rather than a separately signed entry
+        if auth
+            .iter()
+            .any(|entry| matches!(entry.credentials, SorobanCredentials::Address(_)))
+        {
+            return Err(Self::rpc_error(
+                function,
+                "every authorizing account must be the source account",
+            ));
+        }
+        let transaction_data = simulation
+            .transaction_data()
+            .map_err(|err| Self::rpc_error(function, err))?;
+        let mut operations = tx.operations.to_vec();
+        if let OperationBody::InvokeHostFunction(op) = &mut operations[0].body {
+            op.auth = auth
+                .try_into()
+                .map_err(|_| Self::rpc_error(function, "too many auth entries"))?;
+        }
+        tx.operations = operations
+            .try_into()
+            .map_err(|_| Self::rpc_error(function, "operations"))?;
+        let resource_fee = u32::try_from(simulation.min_resource_fee).unwrap_or(u32::MAX);
+        tx.fee = BASE_FEE.saturating_add(resource_fee);
+        tx.ext = TransactionExt::V1(transaction_data);
+
+        let signature = self.sign(&tx, signing_key, function)?;
+        let response = self
+            .runtime
+            .block_on(
+                self.client
+                    .send_transaction_polling(&envelope(tx, vec![signature])),
+            )
+            .map_err(|err| Self::rpc_error(function, err))?;
+        response
+            .return_value()
+            .map_err(|err| Self::rpc_error(function, err))
+    }
+
+    fn sign(
+        &self,
+        tx: &Transaction,
+        signing_key: &SigningKey,
+        function: &str,
+    ) -> Result<DecoratedSignature, Error> {
+        let network_id = Hash(Sha256::digest(self.network_passphrase.as_bytes()).into());
+        let payload = TransactionSignaturePayload {
+            network_id,
+            tagged_transaction: TransactionSignaturePayloadTaggedTransaction::Tx(tx.clone()),
+        }
+        .to_xdr(Limits::none())
+        .map_err(|err| Self::rpc_error(function, err))?;
+        let signature = signing_key.sign(&Sha256::digest(payload));
+        let hint: [u8; 4] = self.source.0[28..].try_into().unwrap();
+        Ok(DecoratedSignature {
+            hint: SignatureHint(hint),
+            signature: Signature(
+                signature
+                    .to_bytes()
+                    .to_vec()
+                    .try_into()
+                    .map_err(|_| Self::rpc_error(function, "invalid signature"))?,
+            ),
+        })
+    }
+
+    fn vault_view(&self, function: &str, args: SorobanVec<Val>) -> Result<ScVal, Error> {
+        self.view(&self.vault, function, args)
+    }
+
+    pub(crate) fn address(&self, account: &str) -> Result<Address, Error> {
+        match Strkey::from_string(account) {
+            Ok(Strkey::PublicKeyEd25519(_) | Strkey::Contract(_)) => {
+                Ok(Address::from_str(&self.env, account))
+            }
+            _ => Err(Error::Usage(format!("invalid account: {}", account))),
+        }
+    }
+
+    pub(crate) fn args(&self, args: impl IntoVal<Env, SorobanVec<Val>>) -> SorobanVec<Val> {
+        args.into_val(&self.env)
+    }
+}
+
+fn envelope(tx: Transaction, signatures: Vec<DecoratedSignature>) -> TransactionEnvelope {
+    TransactionEnvelope::Tx(TransactionV1Envelope {
+        tx,
+        signatures: signatures.try_into().unwrap_or_default(),
+    })
+}
+
+/// Decode a returned `ScVal` into the contract type `T`
+pub(crate) fn decode<T: TryFromVal<Env, Val>>(
+    env: &Env,
+    function: &str,
+    value: &ScVal,
+) -> Result<T, Error> {
+    Val::try_from_val(env, value)
+        .ok()
+        .and_then(|val| T::try_from_val(env, &val).ok())
+        .ok_or_else(|| Error::InvalidValue {
+            function: function.to_string(),
+            value: format!("{:?}", value),
+        })
+}
+
+/// The strkey of `address`, `G...` or `C...`
+pub(crate) fn strkey(address: &Address) -> String {
+    let string = address.to_string();
+    let mut bytes = vec![0u8; string.len() as usize];
+    string.copy_into_slice(&mut bytes);
+    String::from_utf8(bytes).unwrap()
+}
+
+pub(crate) fn position(value: AccountPosition) -> Position {
+    Position {
+        shares: value.shares,
+        principal: value.principal,
+        value: value.value,
+        earned: value.earned,
+    }
+}
+
+pub(crate) fn earnings(value: AccountEarnings) -> Earnings {
+    Earnings {
+        realized: value.realized,
+        unrealized: value.unrealized,
+        total: value.total,
+    }
+}
+
+pub(crate) fn snapshot(value: Map<Address, i128>) -> Vec<(String, i128)> {
+    value
+        .iter()
+        .map(|(account, balance)| (strkey(&account), balance))
+        .collect()
+}
+
+impl VaultBackend for RpcBackend {
+    fn decimals(&self) -> Result<u32, Error> {
+        let value = self.vault_view("decimals", self.args(BlendVaultContractArgs::decimals()))?;
+        decode(&self.env, "decimals", &value)
+    }
+
+    fn asset_decimals(&self) -> Result<u32, Error> {
+        let value = self.vault_view(
+            "query_asset",
+            self.args(BlendVaultContractArgs::query_asset()),
+        )?;
+        let asset: Address = decode(&self.env, "query_asset", &value)?;
+        let value = self.view(&asset, "decimals", self.args(()))?;
+        decode(&self.env, "decimals", &value)
+    }
+
+    fn total_assets(&self) -> Result<i128, Error> {
+        let value = self.vault_view(
+            "total_assets",
+            self.args(BlendVaultContractArgs::total_assets()),
+        )?;
+        decode(&self.env, "total_assets", &value)
+    }
+
+    fn total_supply(&self) -> Result<i128, Error> {
+        let value = self.vault_view(
+            "total_supply",
+            self.args(BlendVaultContractArgs::total_supply()),
+        )?;
+        decode(&self.env, "total_supply", &value)
+    }
+
+    fn convert_to_assets(&self, shares: i128) -> Result<i128, Error> {
+        let value = self.vault_view(
+            "convert_to_assets",
+            self.args(BlendVaultContractArgs::convert_to_assets(&shares)),
+        )?;
+        decode(&self.env, "convert_to_assets", &value)
+    }
+
+    fn idle_assets(&self) -> Result<i128, Error> {
+        let value = self.vault_view(
+            "idle_assets",
+            self.args(BlendVaultContractArgs::idle_assets()),
+        )?;
+        decode(&self.env, "idle_assets", &value)
+    }
+
+    fn depositor_count(&self) -> Result<u32, Error> {
+        let value = self.vault_view(
+            "get_depositor_count",
+            self.args(BlendVaultContractArgs::get_depositor_count()),
+        )?;
+        decode(&self.env, "get_depositor_count", &value)
+    }
+
+    fn position_of(&self, account: &str) -> Result<Position, Error> {
+        let account = self.address(account)?;
+        let value = self.vault_view(
+            "position_of",
+            self.args(BlendVaultContractArgs::position_of(&account)),
+        )?;
+        decode(&self.env, "position_of", &value).map(position)
+    }
+
+    fn earnings_of(&self, account: &str) -> Result<Earnings, Error> {
+        let account = self.address(account)?;
+        let value = self.vault_view(
+            "earnings_of",
+            self.args(BlendVaultContractArgs::earnings_of(&account)),
+        )?;
+        decode(&self.env, "earnings_of", &value).map(earnings)
+    }
+
+    fn preview_deposit(&self, assets: i128) -> Result<i128, Error> {
+        let value = self.vault_view(
+            "preview_deposit",
+            self.args(BlendVaultContractArgs::preview_deposit(&assets)),
+        )?;
+        decode(&self.env, "preview_deposit", &value)
+    }
+
+    fn preview_withdraw(&self, assets: i128) -> Result<i128, Error> {
+        let value = self.vault_view(
+            "preview_withdraw",
+            self.args(BlendVaultContractArgs::preview_withdraw(&assets)),
+        )?;
+        decode(&self.env, "preview_withdraw", &value)
+    }
+
+    fn depositors_snapshot_page(
+        &self,
+        start: u32,
+        limit: u32,
+    ) -> Result<Vec<(String, i128)>, Error> {
+        let value = self.vault_view(
+            "get_depositors_snapshot_page",
+            self.args(BlendVaultContractArgs::get_depositors_snapshot_page(
+                &start, &limit,
+            )),
+        )?;
+        decode(&self.env, "get_depositors_snapshot_page", &value).map(snapshot)
+    }
+
+    fn compound_with_min_out(&self, operator: &str, min_out: i128) -> Result<i128, Error> {
+        let operator = self.address(operator)?;
+        let value = self.send(
+            &self.vault,
+            "compound_with_min_out",
+            self.args(BlendVaultContractArgs::compound_with_min_out(
+                &operator, &min_out,
+            )),
+        )?;
+        decode(&self.env, "compound_with_min_out", &value)
+    }
+}
@@ -0,0 +1,176 @@
+//! A `VaultBackend` over a local Soroban sandbox, the vault deployed next to
+//! the mock Blend and Comet pools, so the commands run end to end without RPC
+
+#![allow(clippy::inconsistent_digit_grouping)]
+
+use blend_vault::{
+    mocks::{MockCometPool, RealisticMockBlendPool, RealisticMockBlendPoolClient},
+    BlendVaultContract, BlendVaultContractClient,
+};
+use soroban_sdk::{
+    testutils::Address as _,
+    token::{StellarAssetClient, TokenClient},
+    Address, Env, String as SorobanString,
+};
+
+use crate::{
+    rpc::{earnings, position, snapshot},
+    Earnings, Error, Position, VaultBackend,
+};
+
+pub struct SandboxBackend<'a> {
+    env: Env,
+    usdc_token: Address,
+    comet_pool: Address,
+    vault: Address,
+    vault_client: BlendVaultContractClient<'a>,
+}
+
+impl<'a> SandboxBackend<'a> {
+    pub fn new() -> Self {
+        Self::with_decimals_offset(0)
+    }
+
+    /// A vault whose shares carry `decimals_offset` more decimals than USDC
+    pub fn with_decimals_offset(decimals_offset: u32) -> Self {
+        let env = Env::default();
+        env.mock_all_auths();
+
+        let admin = Address::generate(&env);
+        let usdc_token = env
+            .register_stellar_asset_contract_v2(admin.clone())
+            .address();
+        let blnd_token = env
+            .register_stellar_asset_contract_v2(admin.clone())
+            .address();
+
+        let blend_pool = env.register(RealisticMockBlendPool, ());
+        RealisticMockBlendPoolClient::new(&env, &blend_pool).set_reward_token(&blnd_token);
+        StellarAssetClient::new(&env, &blnd_token).mint(&blend_pool, &1_000_000_0000000);
+
+        let comet_pool = env.register(MockCometPool, ());
+
+        let vault = env.register(BlendVaultContract, ());
+        let vault_client = BlendVaultContractClient::new(&env, &vault);
+        vault_client.initialize(
+            &usdc_token,
+            &decimals_offset,
+            &blend_pool,
+            &blnd_token,
+            &comet_pool,
+            &admin,
+            &Some(SorobanString::from_str(&env, "BLEND VAULT")),
+            &Some(SorobanString::from_str(&env, "BV")),
+        );
+
+        Self {
+            env,
+            usdc_token,
+            comet_pool,
+            vault,
+            vault_client,
+        }
+    }
+
+    /// Fund a fresh account with `assets` USDC and deposit all of it
+    pub fn deposit(&self, assets: i128) -> Address {
+        let account = Address::generate(&self.env);
+        StellarAssetClient::new(&self.env, &self.usdc_token).mint(&account, &assets);
+        TokenClient::new(&self.env, &self.usdc_token).approve(&account, &self.vault, &assets, &200);
+        self.vault_client
+            .deposit(&assets, &account, &account, &account);
+        account
+    }
+
+    /// Give the Comet pool USDC to pay out BLND swaps with
+    pub fn fund_comet(&self, assets: i128) {
+        StellarAssetClient::new(&self.env, &self.usdc_token).mint(&self.comet_pool, &assets);
+    }
+
+    fn address(&self, strkey: &str) -> Address {
+        Address::from_str(&self.env, strkey)
+    }
+}
+
+impl VaultBackend for SandboxBackend<'_> {
+    fn decimals(&self) -> Result<u32, Error> {
+        Ok(self.vault_client.decimals())
+    }
+
+    fn asset_decimals(&self) -> Result<u32, Error> {
+        Ok(TokenClient::new(&self.env, &self.vault_client.query_asset()).decimals())
+    }
+
+    fn total_assets(&self) -> Result<i128, Error> {
+        Ok(self.vault_client.total_assets())
+    }
+
+    fn total_supply(&self) -> Result<i128, Error> {
+        Ok(self.vault_client.total_supply())
+    }
+
+    fn convert_to_assets(&self, shares: i128) -> Result<i128, Error> {
+        Ok(self.vault_client.convert_to_assets(&shares))
+    }
+
+    fn idle_assets(&self) -> Result<i128, Error> {
+        Ok(self.vault_client.idle_assets())
+    }
+
+    fn depositor_count(&self) -> Result<u32, Error> {
+        Ok(self.vault_client.get_depositor_count())
+    }
+
+    fn position_of(&self, account: &str) -> Result<Position, Error> {
+        Ok(position(
+            self.vault_client.position_of(&self.address(account)),
+        ))
+    }
+
+    fn earnings_of(&self, account: &str) -> Result<Earnings, Error> {
+        Ok(earnings(
+            self.vault_client.earnings_of(&self.address(account)),
+        ))
+    }
+
+    fn preview_deposit(&self, assets: i128) -> Result<i128, Error> {
+        Ok(self.vault_client.preview_deposit(&assets))
+    }
+
+    fn preview_withdraw(&self, assets: i128) -> Result<i128, Error> {
+        Ok(self.vault_client.preview_withdraw(&assets))
+    }
+
+    fn depositors_snapshot_page(
+        &self,
+        start: u32,
+        limit: u32,
+    ) -> Result<Vec<(String, i128)>, Error> {
+        Ok(snapshot(
+            self.vault_client
+                .get_depositors_snapshot_page(&start, &limit),
+        ))
+    }
+
+    fn compound_with_min_out(&self, operator: &str, min_out: i128) -> Result<i128, Error> {
+        let operator = self.address(operator);
+        match self
+            .vault_client
+            .try_compound_with_min_out(&operator, &min_out)
+        {
+            Ok(Ok(assets)) => Ok(assets),
+            Ok(Err(err)) => Err(Error::InvalidValue {
+                function: "compound_with_min_out".to_string(),
+                value: format!("{:?}", err),
+            }),
+            Err(Ok(err)) => Err(Error::Invoke {
+                function: "compound_with_min_out".to_string(),
+                message: format!("{:?}", err),
+            }),
+            Err(Err(err)) => Err(Error::Invoke {
+                function: "compound_with_min_out".to_string(),
+                message: format!("{:?}", err),
+            }),
+        }
+    }
+}
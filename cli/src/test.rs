@@ -0,0 +1,393 @@
+// Amounts are written as whole units and 7 decimals, e.g. 1000_0000000
+#![allow(clippy::inconsistent_digit_grouping)]
+
+use blend_vault::{AccountEarnings, AccountPosition, BlendVaultContractArgs};
+use serde_json::{json, Value};
+use soroban_sdk::{
+    map,
+    testutils::Address as _,
+    xdr::{
+        AccountId, HostFunction, OperationBody, PublicKey, ScAddress, ScSymbol, ScVal,
+        SequenceNumber, Uint256,
+    },
+    Address, Env, IntoVal, Map, TryFromVal, Val,
+};
+use stellar_strkey::{ed25519, Contract};
+
+use super::*;
+use crate::rpc::{decode, earnings, position, snapshot, strkey};
+use crate::sandbox::SandboxBackend;
+
+fn args(line: &str) -> Vec<String> {
+    line.split_whitespace().map(str::to_string).collect()
+}
+
+fn run(backend: &dyn VaultBackend, line: &str) -> Result<String, Error> {
+    let mut out = Vec::new();
+    Command::parse(&args(line), "GOPERATOR")?.execute(backend, &mut out)?;
+    Ok(String::from_utf8(out).unwrap())
+}
+
+// ===== Arguments =====
+
+#[test]
+fn test_parses_commands() {
+    assert_eq!(Command::parse(&args("state"), "G").unwrap(), Command::State);
+    assert_eq!(
+        Command::parse(&args("position GABC"), "G").unwrap(),
+        Command::Position {
+            account: "GABC".to_string()
+        }
+    );
+    assert_eq!(
+        Command::parse(&args("preview withdraw 25"), "G").unwrap(),
+        Command::Preview {
+            kind: PreviewKind::Withdraw,
+            assets: 25
+        }
+    );
+    assert_eq!(
+        Command::parse(&args("compound --min-out 90"), "GSOURCE").unwrap(),
+        Command::Compound {
+            operator: "GSOURCE".to_string(),
+            min_out: 90
+        }
+    );
+    assert_eq!(
+        Command::parse(&args("compound --operator GKEEPER --min-out 0"), "GSOURCE").unwrap(),
+        Command::Compound {
+            operator: "GKEEPER".to_string(),
+            min_out: 0
+        }
+    );
+    assert_eq!(
+        Command::parse(&args("snapshot"), "G").unwrap(),
+        Command::Snapshot {
+            format: Format::Csv
+        }
+    );
+    assert_eq!(
+        Command::parse(&args("snapshot --format json"), "G").unwrap(),
+        Command::Snapshot {
+            format: Format::Json
+        }
+    );
+}
+
+#[test]
+fn test_rejects_bad_arguments() {
+    for line in [
+        "",
+        "status",
+        "position",
+        "preview mint 10",
+        "preview deposit ten",
+        "compound",
+        "compound --min-out",
+        "compound --min-out 1 --slippage 2",
+        "snapshot --format xml",
+    ] {
+        assert!(
+            matches!(Command::parse(&args(line), "G"), Err(Error::Usage(_))),
+            "{:?} should be rejected",
+            line
+        );
+    }
+}
+
+#[test]
+fn test_formats_amounts() {
+    assert_eq!(format_amount(12_3400000, 7), "12.3400000");
+    assert_eq!(format_amount(5, 7), "0.0000005");
+    assert_eq!(format_amount(-1_5000000, 7), "-1.5000000");
+    assert_eq!(format_amount(42, 0), "42");
+}
+
+// ===== RPC =====
+
+fn to_scval<T: IntoVal<Env, Val>>(env: &Env, value: T) -> ScVal {
+    ScVal::try_from_val(env, &value.into_val(env)).unwrap()
+}
+
+#[test]
+fn test_decodes_contract_types() {
+    let env = Env::default();
+    assert_eq!(
+        decode::<i128>(&env, "total_assets", &to_scval(&env, 10_000_000_000i128)).unwrap(),
+        10_000_000_000
+    );
+    assert_eq!(
+        decode::<u32>(&env, "decimals", &to_scval(&env, 7u32)).unwrap(),
+        7
+    );
+    assert!(matches!(
+        decode::<i128>(&env, "total_assets", &ScVal::Void),
+        Err(Error::InvalidValue { .. })
+    ));
+
+    let value = to_scval(
+        &env,
+        AccountPosition {
+            shares: 100,
+            principal: 90,
+            value: 110,
+            earned: 20,
+        },
+    );
+    assert_eq!(
+        position(decode(&env, "position_of", &value).unwrap()),
+        Position {
+            shares: 100,
+            principal: 90,
+            value: 110,
+            earned: 20
+        }
+    );
+    let value = to_scval(
+        &env,
+        AccountEarnings {
+            realized: 5,
+            unrealized: -3,
+            total: 2,
+        },
+    );
+    assert_eq!(
+        earnings(decode(&env, "earnings_of", &value).unwrap()),
+        Earnings {
+            realized: 5,
+            unrealized: -3,
+            total: 2
+        }
+    );
+
+    let alice = Address::generate(&env);
+    let value = to_scval(&env, map![&env, (alice.clone(), 7i128)]);
+    assert_eq!(
+        snapshot(decode(&env, "get_depositors_snapshot_page", &value).unwrap()),
+        vec![(strkey(&alice), 7)]
+    );
+    assert!(
+        decode::<Map<Address, i128>>(&env, "get_depositors_snapshot_page", &ScVal::Void).is_err()
+    );
+}
+
+#[test]
+fn test_builds_invocations_from_contract_args() {
+    let vault = Contract([1; 32]).to_string();
+    let secret = ed25519::PrivateKey([7; 32]).to_string();
+    let backend = RpcBackend::new(DEFAULT_RPC_URL, MAINNET_PASSPHRASE, &vault, &secret).unwrap();
+    let operator = backend.source_account();
+    let operator_key = ed25519::PublicKey::from_string(&operator).unwrap();
+
+    let tx = backend
+        .transaction(
+            &backend.address(&vault).unwrap(),
+            "compound_with_min_out",
+            backend.args(BlendVaultContractArgs::compound_with_min_out(
+                &backend.address(&operator).unwrap(),
+                &95,
+            )),
+            8,
+        )
+        .unwrap();
+    assert_eq!(tx.seq_num, SequenceNumber(8));
+    let operations = tx.operations.to_vec();
+    let OperationBody::InvokeHostFunction(op) = &operations[0].body else {
+        panic!("expected an InvokeHostFunction operation");
+    };
+    let HostFunction::InvokeContract(invoke) = &op.host_function else {
+        panic!("expected a contract invocation");
+    };
+    assert_eq!(
+        invoke.function_name,
+        ScSymbol("compound_with_min_out".try_into().unwrap())
+    );
+    assert_eq!(
+        invoke.args.to_vec(),
+        vec![
+            ScVal::Address(ScAddress::Account(AccountId(
+                PublicKey::PublicKeyTypeEd25519(Uint256(operator_key.0))
+            ))),
+            ScVal::from(95i128),
+        ]
+    );
+}
+
+#[test]
+fn test_rejects_bad_keys() {
+    let vault = Contract([1; 32]).to_string();
+    let account = ed25519::PublicKey([7; 32]).to_string();
+    for (vault, source) in [
+        (vault.as_str(), "alice"),
+        (account.as_str(), account.as_str()),
+    ] {
+        assert!(matches!(
+            RpcBackend::new(DEFAULT_RPC_URL, MAINNET_PASSPHRASE, vault, source),
+            Err(Error::Usage(_))
+        ));
+    }
+
+    // Compounding has to sign, which a public key alone cannot
+    let backend = RpcBackend::new(DEFAULT_RPC_URL, MAINNET_PASSPHRASE, &vault, &account).unwrap();
+    assert!(matches!(
+        backend.compound_with_min_out(&account, 0),
+        Err(Error::Usage(_))
+    ));
+}
+
+// ===== Sandbox =====
+
+#[test]
+fn test_state_reports_totals_and_share_price() {
+    let sandbox = SandboxBackend::new();
+    sandbox.deposit(1000_0000000);
+
+    assert_eq!(
+        run(&sandbox, "state").unwrap(),
+        "total_assets   1000.0000000\n\
+         total_supply   1000.0000000\n\
+         share_price    1.0000000\n\
+         idle_assets    0.0000000\n\
+         depositors     1\n"
+    );
+}
+
+#[test]
+fn test_formats_assets_and_shares_with_their_own_decimals() {
+    // Shares carry two more decimals than USDC, a deposit mints 100 raw
+    // shares per raw asset
+    let sandbox = SandboxBackend::with_decimals_offset(2);
+    let account = strkey(&sandbox.deposit(1000_0000000));
+
+    assert_eq!(
+        run(&sandbox, "state").unwrap(),
+        "total_assets   1000.0000000\n\
+         total_supply   1000.000000000\n\
+         share_price    1.0000000\n\
+         idle_assets    0.0000000\n\
+         depositors     1\n"
+    );
+    assert_eq!(
+        run(&sandbox, "preview deposit 5000000000").unwrap(),
+        "assets         500.0000000\nshares_minted  500.000000000\n"
+    );
+    assert_eq!(
+        run(&sandbox, &format!("position {}", account)).unwrap(),
+        "shares         1000.000000000\n\
+         principal      1000.0000000\n\
+         value          1000.0000000\n\
+         unrealized     0.0000000\n\
+         realized       0.0000000\n\
+         total_earned   0.0000000\n"
+    );
+
+    sandbox.fund_comet(1000_0000000);
+    assert_eq!(
+        run(
+            &sandbox,
+            &format!("compound --operator {} --min-out 0", account)
+        )
+        .unwrap(),
+        "assets_received 1000.0000000\n"
+    );
+}
+
+#[test]
+fn test_position_reports_earnings_after_compound() {
+    let sandbox = SandboxBackend::new();
+    let account = sandbox.deposit(1000_0000000);
+    // The mock pool pays 1000 BLND per claim and Comet swaps it 1:1
+    sandbox.fund_comet(1000_0000000);
+    run(
+        &sandbox,
+        &format!("compound --operator {} --min-out 0", strkey(&account)),
+    )
+    .unwrap();
+
+    assert_eq!(
+        run(&sandbox, &format!("position {}", strkey(&account))).unwrap(),
+        "shares         1000.0000000\n\
+         principal      1000.0000000\n\
+         value          2000.0000000\n\
+         unrealized     1000.0000000\n\
+         realized       0.0000000\n\
+         total_earned   1000.0000000\n"
+    );
+}
+
+#[test]
+fn test_preview_deposit_and_withdraw() {
+    let sandbox = SandboxBackend::new();
+    sandbox.deposit(1000_0000000);
+
+    assert_eq!(
+        run(&sandbox, "preview deposit 5000000000").unwrap(),
+        "assets         500.0000000\nshares_minted  500.0000000\n"
+    );
+    assert_eq!(
+        run(&sandbox, "preview withdraw 2500000000").unwrap(),
+        "assets         250.0000000\nshares_burned  250.0000000\n"
+    );
+}
+
+#[test]
+fn test_compound_with_min_out() {
+    let sandbox = SandboxBackend::new();
+    let account = strkey(&sandbox.deposit(1000_0000000));
+    sandbox.fund_comet(2000_0000000);
+
+    let err = run(
+        &sandbox,
+        &format!("compound --operator {} --min-out 10000000001", account),
+    )
+    .unwrap_err();
+    match err {
+        Error::Invoke { function, .. } => assert_eq!(function, "compound_with_min_out"),
+        err => panic!("unexpected error {:?}", err),
+    }
+    assert_eq!(sandbox.total_assets().unwrap(), 1000_0000000);
+
+    assert_eq!(
+        run(
+            &sandbox,
+            &format!("compound --operator {} --min-out 10000000000", account)
+        )
+        .unwrap(),
+        "assets_received 1000.0000000\n"
+    );
+    assert_eq!(sandbox.total_assets().unwrap(), 2000_0000000);
+}
+
+#[test]
+fn test_snapshot_csv_and_json() {
+    let sandbox = SandboxBackend::new();
+    let alice = strkey(&sandbox.deposit(300_0000000));
+    let bob = strkey(&sandbox.deposit(700_0000000));
+
+    let csv = run(&sandbox, "snapshot --format csv").unwrap();
+    let mut lines: Vec<&str> = csv.lines().collect();
+    assert_eq!(lines.remove(0), "account,shares");
+    lines.sort();
+    let mut expected = vec![
+        format!("{},3000000000", alice),
+        format!("{},7000000000", bob),
+    ];
+    expected.sort();
+    assert_eq!(lines, expected);
+
+    let json: Value =
+        serde_json::from_str(&run(&sandbox, "snapshot --format json").unwrap()).unwrap();
+    assert_eq!(json, json!({alice: "3000000000", bob: "7000000000"}));
+}
+
+#[test]
+fn test_snapshot_pages_through_the_registry() {
+    let sandbox = SandboxBackend::new();
+    let count = SNAPSHOT_PAGE_SIZE as usize + 5;
+    for _ in 0..count {
+        sandbox.deposit(1_0000000);
+    }
+
+    let csv = run(&sandbox, "snapshot").unwrap();
+    assert_eq!(csv.lines().count(), count + 1);
+}
@@ -0,0 +1,99 @@
+use soroban_sdk::{vec, Address, Env, Vec};
+
+use crate::strategy::{self, WEIGHT_SCALAR};
+
+fn target(total: i128, weight: u32) -> i128 {
+    total * weight as i128 / WEIGHT_SCALAR as i128
+}
+
+/// Index of the strategy furthest below its target weight once `amount` is
+/// added, among those whose debt limit leaves room for `amount`. `None` when
+/// every weighted strategy is at its limit.
+pub(crate) fn deposit_target(e: &Env, asset: &Address, amount: i128) -> Option<u32> {
+    let strategies = strategy::strategies(e);
+    let balances = strategy::balances(e, asset);
+    let mut total = amount;
+    for balance in balances.iter() {
+        total += balance;
+    }
+    let mut best = None;
+    let mut best_deficit = i128::MIN;
+    for (index, params) in strategies.iter().enumerate() {
+        if params.weight == 0 || params.max_debt - params.current_debt < amount {
+            continue;
+        }
+        let deficit = target(total, params.weight) - balances.get_unchecked(index as u32);
+        if deficit > best_deficit {
+            best_deficit = deficit;
+            best = Some(index as u32);
+        }
+    }
+    best
+}
+
+/// Split `amount` into per-strategy withdrawals, as `(index, amount)` pairs.
+/// Strategies are visited from most to least overweight and a single strategy
+/// with enough liquidity is preferred.
+pub(crate) fn withdrawal_plan(e: &Env, asset: &Address, amount: i128) -> Vec<(u32, i128)> {
+    let strategies = strategy::strategies(e);
+    if strategies.len() == 1 {
+        return vec![e, (0, amount)];
+    }
+    let balances = strategy::balances(e, asset);
+    let mut total = 0i128;
+    for balance in balances.iter() {
+        total += balance;
+    }
+    let total_after = (total - amount).max(0);
+
+    // Withdrawable amount and overweight-ness of every strategy
+    let mut available: Vec<i128> = Vec::new(e);
+    let mut excess: Vec<i128> = Vec::new(e);
+    for (index, params) in strategies.iter().enumerate() {
+        let balance = balances.get_unchecked(index as u32);
+        available.push_back(balance.min(strategy::liquidity(e, &params, asset)));
+        excess.push_back(balance - target(total_after, params.weight));
+    }
+
+    // Visit order: most overweight first
+    let mut order: Vec<u32> = Vec::new(e);
+    let mut used: Vec<bool> = vec![e];
+    for _ in 0..strategies.len() {
+        used.push_back(false);
+    }
+    for _ in 0..strategies.len() {
+        let mut pick = 0u32;
+        let mut pick_excess = i128::MIN;
+        for index in 0..strategies.len() {
+            if !used.get_unchecked(index) && excess.get_unchecked(index) > pick_excess {
+                pick = index;
+                pick_excess = excess.get_unchecked(index);
+            }
+        }
+        used.set(pick, true);
+        order.push_back(pick);
+    }
+
+    for index in order.iter() {
+        if available.get_unchecked(index) >= amount {
+            return vec![e, (index, amount)];
+        }
+    }
+
+    let mut plan = Vec::new(e);
+    let mut remaining = amount;
+    for index in order.iter() {
+        if remaining == 0 {
+            break;
+        }
+        let take = remaining.min(available.get_unchecked(index));
+        if take > 0 {
+            plan.push_back((index, take));
+            remaining -= take;
+        }
+    }
+    if remaining > 0 {
+        panic!("Insufficient strategy liquidity");
+    }
+    plan
+}
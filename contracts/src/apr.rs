@@ -0,0 +1,181 @@
+use soroban_sdk::{contracttype, Address, Env, Vec};
+
+use crate::{
+    strategy::{self, BlendReserve, StrategyKind},
+    BlendPoolClient, CometPoolClient, Reserve, ReserveConfig, BLEND_RATE_SCALAR,
+};
+
+/// Scalar of every rate in `AprBreakdown`, matching Blend's 7 decimals
+pub(crate) const APR_SCALAR: i128 = 10_000_000;
+const SECONDS_PER_YEAR: i128 = 365 * 24 * 60 * 60;
+/// Blend emissions per second carry 7 decimals on top of the BLND amount
+const EPS_SCALAR: i128 = 10_000_000;
+
+/// Projected yearly rates for supplying the vault asset to Blend, all scaled
+/// by `APR_SCALAR`
+#[contracttype]
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct AprBreakdown {
+    /// Share of supplied assets that is borrowed
+    pub utilization: i128,
+    /// Rate paid by borrowers, from the reserve's interest rate curve
+    pub borrow_apr: i128,
+    /// Share of borrower interest taken by the backstop
+    pub backstop_rate: i128,
+    /// Interest rate earned by suppliers
+    pub supply_apr: i128,
+    /// BLND supply emissions valued at the Comet spot price
+    pub emission_apr: i128,
+    /// `supply_apr` plus `emission_apr`
+    pub total_apr: i128,
+}
+
+fn mul_ceil(a: i128, b: i128) -> i128 {
+    (a * b + APR_SCALAR - 1) / APR_SCALAR
+}
+
+fn div_ceil(a: i128, b: i128) -> i128 {
+    (a * APR_SCALAR + b - 1) / b
+}
+
+/// Share of supplied assets that is borrowed
+pub(crate) fn utilization(reserve: &Reserve) -> i128 {
+    let supplied = reserve.data.b_supply * reserve.data.b_rate / BLEND_RATE_SCALAR;
+    if supplied == 0 {
+        return 0;
+    }
+    let borrowed = reserve.data.d_supply * reserve.data.d_rate / BLEND_RATE_SCALAR;
+    borrowed * APR_SCALAR / supplied
+}
+
+/// Borrow rate at `util`, following Blend's three-segment interest rate curve
+/// scaled by the reserve's rate modifier
+pub(crate) fn borrow_apr(config: &ReserveConfig, util: i128, ir_mod: i128) -> i128 {
+    let target_util = config.util as i128;
+    let max_util = config.max_util as i128;
+    let r_base = config.r_base as i128;
+    let r_one = config.r_one as i128;
+    let r_two = config.r_two as i128;
+    if util <= target_util {
+        let util_scalar = div_ceil(util, target_util);
+        let base_rate = mul_ceil(util_scalar, r_one) + r_base;
+        mul_ceil(base_rate, ir_mod)
+    } else if util <= max_util {
+        let util_scalar = div_ceil(util - target_util, max_util - target_util);
+        let base_rate = mul_ceil(util_scalar, r_two) + r_one + r_base;
+        mul_ceil(base_rate, ir_mod)
+    } else {
+        let util_scalar = div_ceil(util - max_util, APR_SCALAR - max_util);
+        let extra_rate = mul_ceil(util_scalar, config.r_three as i128);
+        extra_rate + mul_ceil(ir_mod, r_two + r_one + r_base)
+    }
+}
+
+/// Interest rates of `reserve` when the backstop takes `backstop_rate` of
+/// borrower interest, as a breakdown without emissions
+pub(crate) fn interest(reserve: &Reserve, backstop_rate: i128) -> AprBreakdown {
+    let utilization = utilization(reserve);
+    let borrow_apr = borrow_apr(&reserve.config, utilization, reserve.data.ir_mod);
+    let supply_apr =
+        borrow_apr * utilization / APR_SCALAR * (APR_SCALAR - backstop_rate) / APR_SCALAR;
+    AprBreakdown {
+        utilization,
+        borrow_apr,
+        backstop_rate,
+        supply_apr,
+        emission_apr: 0,
+        total_apr: supply_apr,
+    }
+}
+
+/// Underlying units one BLND unit buys at the Comet spot price, scaled by
+/// `APR_SCALAR`. Zero when the Comet pool does not trade the asset.
+pub(crate) fn blnd_price(e: &Env, comet_pool: &Address, blnd: &Address, asset: &Address) -> i128 {
+    let comet = CometPoolClient::new(e, comet_pool);
+    let (Ok(Ok(asset_balance)), Ok(Ok(blnd_balance))) =
+        (comet.try_get_balance(asset), comet.try_get_balance(blnd))
+    else {
+        return 0;
+    };
+    let (Ok(Ok(asset_weight)), Ok(Ok(blnd_weight))) = (
+        comet.try_get_normalized_weight(asset),
+        comet.try_get_normalized_weight(blnd),
+    ) else {
+        return 0;
+    };
+    if blnd_balance == 0 || asset_weight == 0 {
+        return 0;
+    }
+    asset_balance * blnd_weight * APR_SCALAR / (blnd_balance * asset_weight)
+}
+
+/// Rates for supplying `asset` to one pool, with BLND supply emissions
+/// valued at `blnd_price`
+pub(crate) fn pool_apr(
+    e: &Env,
+    pool: &Address,
+    blend_reserve: &BlendReserve,
+    asset: &Address,
+    blnd_price: i128,
+) -> AprBreakdown {
+    let pool_client = BlendPoolClient::new(e, pool);
+    let reserve = pool_client.get_reserve(asset);
+    let backstop_rate = pool_client.get_config().bstop_rate as i128;
+    let mut breakdown = interest(&reserve, backstop_rate);
+
+    let supplied = reserve.data.b_supply * reserve.data.b_rate / BLEND_RATE_SCALAR;
+    if let Some(emissions) = pool_client.get_reserve_emissions(&blend_reserve.reward_token_id) {
+        if emissions.expiration > e.ledger().timestamp() && supplied > 0 {
+            let blnd_per_year = emissions.eps as i128 * SECONDS_PER_YEAR / EPS_SCALAR;
+            let value_per_year = blnd_per_year * blnd_price / APR_SCALAR;
+            breakdown.emission_apr = value_per_year * APR_SCALAR / supplied;
+        }
+    }
+    breakdown.total_apr = breakdown.supply_apr + breakdown.emission_apr;
+    breakdown
+}
+
+/// Rates across every Blend pool, weighted by the vault's position in each
+/// pool or by the target weights while the vault holds nothing in Blend
+pub(crate) fn projected(e: &Env, asset: &Address, blnd_price: i128) -> AprBreakdown {
+    let mut pools: Vec<(Address, BlendReserve)> = Vec::new(e);
+    let mut weights: Vec<i128> = Vec::new(e);
+    let mut targets: Vec<i128> = Vec::new(e);
+    for params in strategy::strategies(e).iter() {
+        if let StrategyKind::Blend(reserve) = &params.kind {
+            weights.push_back(strategy::assets(e, &params, asset));
+            targets.push_back(params.weight as i128);
+            pools.push_back((params.strategy.clone(), reserve.clone()));
+        }
+    }
+    if weights.iter().all(|balance| balance <= 0) {
+        weights = targets;
+    }
+    let mut total_weight = 0i128;
+    let mut sum = AprBreakdown::default();
+    for ((pool, reserve), weight) in pools.iter().zip(weights.iter()) {
+        if weight <= 0 {
+            continue;
+        }
+        let rates = pool_apr(e, &pool, &reserve, asset, blnd_price);
+        sum.utilization += rates.utilization * weight;
+        sum.borrow_apr += rates.borrow_apr * weight;
+        sum.backstop_rate += rates.backstop_rate * weight;
+        sum.supply_apr += rates.supply_apr * weight;
+        sum.emission_apr += rates.emission_apr * weight;
+        total_weight += weight;
+    }
+    if total_weight == 0 {
+        return sum;
+    }
+    let supply_apr = sum.supply_apr / total_weight;
+    let emission_apr = sum.emission_apr / total_weight;
+    AprBreakdown {
+        utilization: sum.utilization / total_weight,
+        borrow_apr: sum.borrow_apr / total_weight,
+        backstop_rate: sum.backstop_rate / total_weight,
+        supply_apr,
+        emission_apr,
+        total_apr: supply_apr + emission_apr,
+    }
+}
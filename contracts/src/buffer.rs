@@ -0,0 +1,56 @@
+use soroban_sdk::Env;
+
+use crate::DataKey;
+
+/// Idle ratios are expressed in basis points of total assets
+pub(crate) const RATIO_SCALAR: u32 = 10_000;
+
+/// Target share of total assets kept as raw asset in the vault, 0 disables the buffer
+pub(crate) fn ratio(e: &Env) -> u32 {
+    e.storage().instance().get(&DataKey::IdleRatio).unwrap_or(0)
+}
+
+pub(crate) fn set_ratio(e: &Env, ratio: u32) {
+    if ratio > RATIO_SCALAR {
+        panic!("Invalid idle ratio");
+    }
+    e.storage().instance().set(&DataKey::IdleRatio, &ratio);
+}
+
+/// Asset the vault holds itself, as booked by its own flows: deposits and
+/// withdrawals from strategies add to it, supplies and pay-outs take from it.
+/// Tokens sent to the vault outside those flows are not counted, so a
+/// donation cannot move the share price.
+pub(crate) fn idle(e: &Env) -> i128 {
+    e.storage()
+        .instance()
+        .get(&DataKey::IdleAssets)
+        .unwrap_or(0)
+}
+
+pub(crate) fn add(e: &Env, amount: i128) {
+    e.storage()
+        .instance()
+        .set(&DataKey::IdleAssets, &(idle(e) + amount));
+}
+
+pub(crate) fn sub(e: &Env, amount: i128) {
+    let idle = idle(e);
+    if amount > idle {
+        panic!("Insufficient idle assets");
+    }
+    e.storage()
+        .instance()
+        .set(&DataKey::IdleAssets, &(idle - amount));
+}
+
+/// Buffer size for the given total assets
+pub(crate) fn target(total_assets: i128, ratio: u32) -> i128 {
+    total_assets * ratio as i128 / RATIO_SCALAR as i128
+}
+
+/// Deposits stay idle until the buffer grows past this, at which point the
+/// excess over the target is supplied in one go
+pub(crate) fn ceiling(target: i128) -> i128 {
+    target * 2
+}
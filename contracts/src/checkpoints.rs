@@ -0,0 +1,175 @@
+use soroban_sdk::{contracttype, Address, Env};
+
+use crate::{ttl::DAY_IN_LEDGERS, DataKey};
+
+/// How many ledgers back `balance_at` and `total_supply_at` can look
+pub(crate) const MAX_LOOKBACK: u32 = 30 * DAY_IN_LEDGERS;
+/// TTL a checkpoint gets once a newer one supersedes it. Any lookup within
+/// `MAX_LOOKBACK` only reads checkpoints superseded less than that long ago.
+pub(crate) const SUPERSEDED_TTL: u32 = 2 * MAX_LOOKBACK;
+
+/// Value of a share balance or of total supply as of the end of `ledger`
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Checkpoint {
+    pub ledger: u32,
+    pub value: i128,
+}
+
+/// Checkpoints `start..end` of a series may still be read, `end` counts every
+/// checkpoint ever recorded. Older ones can no longer answer a lookup within
+/// `MAX_LOOKBACK` and are left to expire.
+#[contracttype]
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub(crate) struct CheckpointRange {
+    pub start: u32,
+    pub end: u32,
+}
+
+enum Series<'a> {
+    Account(&'a Address),
+    Supply,
+}
+
+impl Series<'_> {
+    fn range_key(&self) -> DataKey {
+        match self {
+            Series::Account(account) => DataKey::BalanceCheckpoints((*account).clone()),
+            Series::Supply => DataKey::SupplyCheckpoints,
+        }
+    }
+
+    fn entry_key(&self, index: u32) -> DataKey {
+        match self {
+            Series::Account(account) => DataKey::BalanceCheckpoint((*account).clone(), index),
+            Series::Supply => DataKey::SupplyCheckpoint(index),
+        }
+    }
+}
+
+fn range(e: &Env, series: &Series) -> CheckpointRange {
+    e.storage()
+        .persistent()
+        .get(&series.range_key())
+        .unwrap_or_default()
+}
+
+fn get(e: &Env, series: &Series, index: u32) -> Checkpoint {
+    e.storage()
+        .persistent()
+        .get(&series.entry_key(index))
+        .expect("Checkpoint missing")
+}
+
+/// The checkpoint a lookup at `MAX_LOOKBACK` ledgers ago resolves to, the
+/// first one any lookup can still need. `last_ledger` is the ledger of the
+/// newest checkpoint: when it is that old, every lookup resolves to it.
+/// Otherwise all of `range` was superseded recently enough to be live.
+fn first_needed(e: &Env, series: &Series, range: &CheckpointRange, last_ledger: u32) -> u32 {
+    let cutoff = e.ledger().sequence().saturating_sub(MAX_LOOKBACK);
+    if last_ledger <= cutoff {
+        return range.end - 1;
+    }
+    let mut low = range.start;
+    let mut high = range.end;
+    while low < high {
+        let mid = low + (high - low) / 2;
+        if get(e, series, mid).ledger > cutoff {
+            high = mid;
+        } else {
+            low = mid + 1;
+        }
+    }
+    if low > range.start {
+        low - 1
+    } else {
+        range.start
+    }
+}
+
+/// Record `value` for the current ledger, several changes within one ledger
+/// collapse into a single checkpoint holding the final value
+fn push(e: &Env, series: &Series, value: i128) {
+    let ledger = e.ledger().sequence();
+    let mut range = range(e, series);
+    if range.end > 0 {
+        let last_key = series.entry_key(range.end - 1);
+        let last = get(e, series, range.end - 1);
+        if last.ledger == ledger {
+            e.storage()
+                .persistent()
+                .set(&last_key, &Checkpoint { ledger, value });
+            return;
+        }
+        if last.value == value {
+            return;
+        }
+        e.storage()
+            .persistent()
+            .extend_ttl(&last_key, SUPERSEDED_TTL, SUPERSEDED_TTL);
+        range.start = first_needed(e, series, &range, last.ledger);
+    }
+    e.storage()
+        .persistent()
+        .set(&series.entry_key(range.end), &Checkpoint { ledger, value });
+    range.end += 1;
+    e.storage().persistent().set(&series.range_key(), &range);
+}
+
+/// Binary search for the last checkpoint at or before `ledger`, which must be
+/// within `MAX_LOOKBACK` of the current ledger. Unless the newest checkpoint
+/// answers the lookup, it is less than `MAX_LOOKBACK` old and the search
+/// only reads checkpoints `first_needed` kept.
+fn upper_lookup(e: &Env, series: &Series, ledger: u32) -> i128 {
+    let range = range(e, series);
+    if range.end == 0 {
+        return 0;
+    }
+    let last = get(e, series, range.end - 1);
+    if last.ledger <= ledger {
+        return last.value;
+    }
+    let mut low = range.start;
+    let mut high = range.end - 1;
+    while low < high {
+        let mid = low + (high - low) / 2;
+        if get(e, series, mid).ledger > ledger {
+            high = mid;
+        } else {
+            low = mid + 1;
+        }
+    }
+    if low == 0 {
+        0
+    } else {
+        get(e, series, low - 1).value
+    }
+}
+
+pub(crate) fn range_of(e: &Env, account: &Address) -> CheckpointRange {
+    range(e, &Series::Account(account))
+}
+
+pub(crate) fn supply_range(e: &Env) -> CheckpointRange {
+    range(e, &Series::Supply)
+}
+
+pub(crate) fn push_balance(e: &Env, account: &Address, balance: i128) {
+    push(e, &Series::Account(account), balance);
+}
+
+pub(crate) fn push_supply(e: &Env, total_supply: i128) {
+    push(e, &Series::Supply, total_supply);
+}
+
+pub(crate) fn balance_at(e: &Env, account: &Address, ledger: u32) -> i128 {
+    upper_lookup(e, &Series::Account(account), ledger)
+}
+
+pub(crate) fn total_supply_at(e: &Env, ledger: u32) -> i128 {
+    upper_lookup(e, &Series::Supply, ledger)
+}
+
+pub(crate) fn balance_checkpoint_count(e: &Env, account: &Address) -> u32 {
+    range_of(e, account).end
+}
@@ -0,0 +1,89 @@
+use soroban_sdk::{contracttype, Address, Env};
+
+use crate::{ttl, DataKey};
+
+/// Assets an account has put into the shares it holds and the profit it has
+/// taken out, tracked at weighted-average cost
+#[contracttype]
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct CostBasis {
+    /// Net assets deposited for the shares currently held
+    pub principal: i128,
+    /// Assets received on exits minus the principal of the shares exited
+    pub realized: i128,
+}
+
+/// An account's shares, what it paid for them and what they are worth now
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct AccountPosition {
+    pub shares: i128,
+    pub principal: i128,
+    pub value: i128,
+    /// `value` minus `principal`
+    pub earned: i128,
+}
+
+/// Yield an account has earned, taken out and still held
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct AccountEarnings {
+    pub realized: i128,
+    pub unrealized: i128,
+    pub total: i128,
+}
+
+pub(crate) fn get(e: &Env, account: &Address) -> CostBasis {
+    e.storage()
+        .persistent()
+        .get(&DataKey::CostBasis(account.clone()))
+        .unwrap_or_default()
+}
+
+fn set(e: &Env, account: &Address, basis: &CostBasis) {
+    let key = DataKey::CostBasis(account.clone());
+    if *basis == CostBasis::default() {
+        e.storage().persistent().remove(&key);
+    } else {
+        e.storage().persistent().set(&key, basis);
+        ttl::extend_persistent(e, &key);
+    }
+}
+
+/// Principal carried by `shares` out of a `balance` with `principal` in total
+fn principal_of(principal: i128, shares: i128, balance: i128) -> i128 {
+    if shares >= balance {
+        return principal;
+    }
+    principal * shares / balance
+}
+
+/// `account` received shares for `assets`
+pub(crate) fn record_deposit(e: &Env, account: &Address, assets: i128) {
+    let mut basis = get(e, account);
+    basis.principal += assets;
+    set(e, account, &basis);
+}
+
+/// `account` gave up `shares` out of its `balance` for `assets`
+pub(crate) fn record_exit(e: &Env, account: &Address, shares: i128, balance: i128, assets: i128) {
+    let mut basis = get(e, account);
+    let principal = principal_of(basis.principal, shares, balance);
+    basis.principal -= principal;
+    basis.realized += assets - principal;
+    set(e, account, &basis);
+}
+
+/// `from` moved `shares` out of its `balance` to `to`, along with their principal
+pub(crate) fn record_transfer(e: &Env, from: &Address, to: &Address, shares: i128, balance: i128) {
+    if from == to {
+        return;
+    }
+    let mut from_basis = get(e, from);
+    let principal = principal_of(from_basis.principal, shares, balance);
+    from_basis.principal -= principal;
+    set(e, from, &from_basis);
+    let mut to_basis = get(e, to);
+    to_basis.principal += principal;
+    set(e, to, &to_basis);
+}
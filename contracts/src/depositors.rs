@@ -0,0 +1,74 @@
+use soroban_sdk::{Address, Env, Map, Vec};
+use stellar_tokens::fungible::Base;
+
+use crate::DataKey;
+
+/// Upper bound on entries returned by a single page, keeps reads within budget.
+pub(crate) const MAX_PAGE_SIZE: u32 = 200;
+
+/// Number of addresses ever registered as depositors
+pub(crate) fn count(e: &Env) -> u32 {
+    e.storage()
+        .instance()
+        .get(&DataKey::DepositorCount)
+        .unwrap_or(0)
+}
+
+/// Register an address once, membership (holding the registry index) and the
+/// indexed entry live in persistent storage so instance storage only holds the counter
+pub(crate) fn add(e: &Env, address: &Address) {
+    let flag = DataKey::IsDepositor(address.clone());
+    if e.storage().persistent().has(&flag) {
+        return;
+    }
+    let index = count(e);
+    e.storage().persistent().set(&flag, &index);
+    e.storage()
+        .persistent()
+        .set(&DataKey::Depositor(index), address);
+    e.storage()
+        .instance()
+        .set(&DataKey::DepositorCount, &(index + 1));
+}
+
+/// Addresses in registration order from `start`, at most `limit` entries
+pub(crate) fn page(e: &Env, start: u32, limit: u32) -> Vec<Address> {
+    let end = start.saturating_add(limit).min(count(e));
+    let mut depositors = Vec::new(e);
+    for index in start..end {
+        if let Some(depositor) = e
+            .storage()
+            .persistent()
+            .get::<_, Address>(&DataKey::Depositor(index))
+        {
+            depositors.push_back(depositor);
+        }
+    }
+    depositors
+}
+
+/// Non-zero share balances for a page of depositors
+pub(crate) fn snapshot_page(e: &Env, start: u32, limit: u32) -> Map<Address, i128> {
+    let mut snapshot = Map::new(e);
+    for depositor in page(e, start, limit).iter() {
+        let balance = Base::balance(e, &depositor);
+        if balance > 0 {
+            snapshot.set(depositor, balance);
+        }
+    }
+    snapshot
+}
+
+/// Move the legacy instance-storage vector into the indexed registry.
+/// Returns the number of legacy entries processed, zero once migrated.
+pub(crate) fn migrate_legacy(e: &Env) -> u32 {
+    let legacy: Vec<Address> = match e.storage().instance().get(&DataKey::Depositors) {
+        Some(legacy) => legacy,
+        None => return 0,
+    };
+    for depositor in legacy.iter() {
+        add(e, &depositor);
+    }
+    e.storage().instance().remove(&DataKey::Depositors);
+    legacy.len()
+}
@@ -0,0 +1,190 @@
+use soroban_sdk::{contracttype, Env, Vec};
+use stellar_contract_utils::math::fixed_point::{muldiv, Rounding};
+
+use crate::{ttl::DAY_IN_LEDGERS, DataKey};
+
+/// Minimum seconds between two share price checkpoints
+pub(crate) const HISTORY_INTERVAL: u64 = 60 * 60;
+/// Ledgers a checkpoint stays in the history after it was written
+pub(crate) const HISTORY_MAX_AGE: u32 = 30 * DAY_IN_LEDGERS;
+/// TTL given to every checkpoint on write. At twice the maximum age, every
+/// checkpoint the history still retains is live, even when nothing has
+/// touched the vault for a while.
+pub(crate) const HISTORY_TTL: u32 = 2 * HISTORY_MAX_AGE;
+/// Slots in the ring buffer, one checkpoint per interval over `HISTORY_MAX_AGE`
+pub(crate) const HISTORY_CAPACITY: u32 = 30 * 24;
+/// Upper bound on checkpoints returned by `share_price_history`
+pub(crate) const MAX_HISTORY_PAGE: u32 = 100;
+/// Scalar of `realized_apy`, matching Blend's 7 decimal rates
+pub(crate) const APY_SCALAR: i128 = 10_000_000;
+const PRICE_SCALAR: i128 = 1_000_000_000_000_000_000;
+const SECONDS_PER_YEAR: i128 = 365 * 24 * 60 * 60;
+
+/// Vault totals at `timestamp`, the share price is `total_assets / total_supply`
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct SharePriceCheckpoint {
+    pub ledger: u32,
+    pub timestamp: u64,
+    pub total_assets: i128,
+    pub total_supply: i128,
+}
+
+/// Checkpoints `start..end` are retained, `end` counts every checkpoint ever
+/// recorded. All retained checkpoints were written at most `HISTORY_MAX_AGE`
+/// ledgers before `last_ledger`, the ledger of the newest one.
+#[contracttype]
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+struct HistoryBounds {
+    start: u32,
+    end: u32,
+    last_ledger: u32,
+}
+
+fn bounds(e: &Env) -> HistoryBounds {
+    e.storage()
+        .instance()
+        .get(&DataKey::SharePriceHistory)
+        .unwrap_or_default()
+}
+
+fn get(e: &Env, index: u32) -> SharePriceCheckpoint {
+    e.storage()
+        .persistent()
+        .get(&DataKey::SharePriceCheckpoint(index % HISTORY_CAPACITY))
+        .expect("Share price checkpoint missing")
+}
+
+/// Whether every retained checkpoint has aged out. Nothing older than the
+/// newest checkpoint is read in that case, it may already be archived.
+fn is_stale(e: &Env, bounds: &HistoryBounds) -> bool {
+    bounds.end == 0 || e.ledger().sequence() - bounds.last_ledger >= HISTORY_MAX_AGE
+}
+
+/// Index of the oldest checkpoint written within `HISTORY_MAX_AGE` ledgers.
+/// Retained checkpoints are at most `HISTORY_TTL` ledgers old unless the
+/// history is stale, so the binary search only reads live entries.
+fn first_live(e: &Env, bounds: &HistoryBounds) -> u32 {
+    if is_stale(e, bounds) {
+        return bounds.end;
+    }
+    let cutoff = e.ledger().sequence().saturating_sub(HISTORY_MAX_AGE);
+    let mut low = bounds.start;
+    let mut high = bounds.end;
+    while low < high {
+        let mid = low + (high - low) / 2;
+        if get(e, mid).ledger < cutoff {
+            low = mid + 1;
+        } else {
+            high = mid;
+        }
+    }
+    low
+}
+
+/// Storage key of the newest checkpoint, if the history is not stale
+pub(crate) fn latest_key(e: &Env) -> Option<DataKey> {
+    let bounds = bounds(e);
+    if is_stale(e, &bounds) {
+        None
+    } else {
+        Some(DataKey::SharePriceCheckpoint(
+            (bounds.end - 1) % HISTORY_CAPACITY,
+        ))
+    }
+}
+
+/// Whether `HISTORY_INTERVAL` has passed since the last checkpoint
+pub(crate) fn is_due(e: &Env) -> bool {
+    let bounds = bounds(e);
+    is_stale(e, &bounds)
+        || e.ledger().timestamp() >= get(e, bounds.end - 1).timestamp + HISTORY_INTERVAL
+}
+
+pub(crate) fn push(e: &Env, total_assets: i128, total_supply: i128) {
+    let mut bounds = bounds(e);
+    bounds.start = first_live(e, &bounds).max((bounds.end + 1).saturating_sub(HISTORY_CAPACITY));
+    let key = DataKey::SharePriceCheckpoint(bounds.end % HISTORY_CAPACITY);
+    e.storage().persistent().set(
+        &key,
+        &SharePriceCheckpoint {
+            ledger: e.ledger().sequence(),
+            timestamp: e.ledger().timestamp(),
+            total_assets,
+            total_supply,
+        },
+    );
+    e.storage()
+        .persistent()
+        .extend_ttl(&key, HISTORY_TTL, HISTORY_TTL);
+    bounds.end += 1;
+    bounds.last_ledger = e.ledger().sequence();
+    e.storage()
+        .instance()
+        .set(&DataKey::SharePriceHistory, &bounds);
+}
+
+/// Up to `n` of the most recent checkpoints, newest first
+pub(crate) fn latest(e: &Env, n: u32) -> Vec<SharePriceCheckpoint> {
+    let bounds = bounds(e);
+    let oldest = first_live(e, &bounds);
+    let mut checkpoints = Vec::new(e);
+    let mut index = bounds.end;
+    while index > oldest && checkpoints.len() < n.min(MAX_HISTORY_PAGE) {
+        index -= 1;
+        checkpoints.push_back(get(e, index));
+    }
+    checkpoints
+}
+
+/// Binary search the retained checkpoints for the last one at or before `timestamp`
+fn at_or_before(e: &Env, timestamp: u64) -> Option<SharePriceCheckpoint> {
+    let bounds = bounds(e);
+    let oldest = first_live(e, &bounds);
+    let mut low = oldest;
+    let mut high = bounds.end;
+    while low < high {
+        let mid = low + (high - low) / 2;
+        if get(e, mid).timestamp > timestamp {
+            high = mid;
+        } else {
+            low = mid + 1;
+        }
+    }
+    if low == oldest {
+        None
+    } else {
+        Some(get(e, low - 1))
+    }
+}
+
+/// Share price including the vault's virtual shares and assets, so it matches
+/// `convert_to_assets`
+fn price(e: &Env, checkpoint: &SharePriceCheckpoint, virtual_shares: i128) -> i128 {
+    muldiv(
+        e,
+        PRICE_SCALAR,
+        checkpoint.total_assets + 1,
+        checkpoint.total_supply + virtual_shares,
+        Rounding::Floor,
+    )
+}
+
+/// Share price growth from the last checkpoint at least `window` seconds
+/// before `current` up to `current`, annualized without compounding and
+/// scaled by `APY_SCALAR`. `None` until the history covers the window.
+pub(crate) fn realized_apy(
+    e: &Env,
+    current: &SharePriceCheckpoint,
+    window: u64,
+    virtual_shares: i128,
+) -> Option<i128> {
+    let start = at_or_before(e, current.timestamp.checked_sub(window)?)?;
+    let elapsed = (current.timestamp - start.timestamp) as i128;
+    if elapsed == 0 {
+        return None;
+    }
+    let start_price = price(e, &start, virtual_shares);
+    let end_price = price(e, current, virtual_shares);
+    Some((end_price - start_price) * APY_SCALAR * SECONDS_PER_YEAR / (start_price * elapsed))
+}
@@ -0,0 +1,177 @@
+use soroban_sdk::{contracttype, Address, Env};
+
+use crate::{strategy::BlendReserve, BlendPoolClient, DataKey, BLEND_RATE_SCALAR};
+
+/// Leverage is expressed in basis points, this value means no looping
+pub(crate) const LEVERAGE_SCALAR: u32 = 10_000;
+/// Scalar of Blend's `c_factor` and `l_factor`
+pub(crate) const FACTOR_SCALAR: i128 = 10_000_000;
+/// Health factor (in `LEVERAGE_SCALAR` units) the vault never borrows or
+/// withdraws below
+pub(crate) const MIN_HEALTH_FACTOR: i128 = 11_000;
+/// Health factor unwinding rounds may dip to, just above Blend's own limit
+pub(crate) const UNWIND_HEALTH_FACTOR: i128 = 10_100;
+/// Upper bound on borrow/supply rounds per deposit
+pub(crate) const MAX_LOOPS: u32 = 8;
+
+/// When `deleverage` may be called and what it pays the caller
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct DeleverageConfig {
+    /// Health factor in basis points below which anyone may deleverage
+    pub threshold: u32,
+    /// Share of the repaid debt paid to the caller, in basis points
+    pub bounty: u32,
+}
+
+impl Default for DeleverageConfig {
+    fn default() -> Self {
+        DeleverageConfig {
+            threshold: 10_500,
+            bounty: 10,
+        }
+    }
+}
+
+/// The vault's collateral and liabilities in one pool, in underlying units
+#[derive(Clone, Debug)]
+pub(crate) struct PoolPosition {
+    pub collateral: i128,
+    pub liabilities: i128,
+    pub c_factor: i128,
+    pub l_factor: i128,
+}
+
+impl PoolPosition {
+    pub fn equity(&self) -> i128 {
+        self.collateral - self.liabilities
+    }
+
+    /// Collateral value counted towards borrowing, after both risk factors
+    fn weighted(&self, collateral: i128) -> i128 {
+        collateral * self.c_factor / FACTOR_SCALAR * self.l_factor / FACTOR_SCALAR
+    }
+
+    /// Additional liabilities the position can take on at `MIN_HEALTH_FACTOR`
+    pub fn borrow_capacity(&self) -> i128 {
+        let max_liabilities =
+            self.weighted(self.collateral) * LEVERAGE_SCALAR as i128 / MIN_HEALTH_FACTOR;
+        (max_liabilities - self.liabilities).max(0)
+    }
+
+    /// Collateral that can be withdrawn while staying at `health_floor`
+    pub fn withdraw_capacity(&self, health_floor: i128) -> i128 {
+        if self.liabilities == 0 {
+            return self.collateral;
+        }
+        let weight = self.weighted(FACTOR_SCALAR);
+        if weight == 0 {
+            return 0;
+        }
+        let required = self.liabilities * health_floor * FACTOR_SCALAR
+            / (weight * LEVERAGE_SCALAR as i128)
+            + 1;
+        (self.collateral - required).max(0)
+    }
+
+    /// Weighted collateral over liabilities in basis points, `i128::MAX`
+    /// without liabilities
+    pub fn health_factor(&self) -> i128 {
+        if self.liabilities == 0 {
+            return i128::MAX;
+        }
+        self.weighted(self.collateral) * LEVERAGE_SCALAR as i128 / self.liabilities
+    }
+
+    /// Debt to repay out of collateral to bring the health factor up to `target`
+    pub fn repay_for(&self, target: i128) -> i128 {
+        let weight = self.weighted(LEVERAGE_SCALAR as i128);
+        let shortfall = target * self.liabilities - weight * self.collateral;
+        if shortfall <= 0 {
+            return 0;
+        }
+        let repay = (shortfall + target - weight - 1) / (target - weight);
+        repay.min(self.liabilities)
+    }
+
+    /// Highest leverage that keeps the position at `MIN_HEALTH_FACTOR`
+    pub fn max_leverage(&self) -> u32 {
+        let weight = self.weighted(LEVERAGE_SCALAR as i128);
+        if weight >= MIN_HEALTH_FACTOR {
+            return u32::MAX;
+        }
+        (MIN_HEALTH_FACTOR * LEVERAGE_SCALAR as i128 / (MIN_HEALTH_FACTOR - weight)) as u32
+    }
+}
+
+pub(crate) fn position(
+    e: &Env,
+    pool: &Address,
+    reserve: &BlendReserve,
+    asset: &Address,
+) -> PoolPosition {
+    let pool_client = BlendPoolClient::new(e, pool);
+    let positions = pool_client.get_positions(&e.current_contract_address());
+    let b_tokens = positions.collateral.get(reserve.reserve_index).unwrap_or(0);
+    let d_tokens = positions
+        .liabilities
+        .get(reserve.reserve_index)
+        .unwrap_or(0);
+    let reserve = pool_client.get_reserve(asset);
+    let collateral = b_tokens
+        .checked_mul(reserve.data.b_rate)
+        .unwrap_or_else(|| panic!("Blend collateral overflow"))
+        / BLEND_RATE_SCALAR;
+    let debt = d_tokens
+        .checked_mul(reserve.data.d_rate)
+        .unwrap_or_else(|| panic!("Blend liabilities overflow"));
+    // Round liabilities up so equity is never overstated
+    let liabilities = (debt + BLEND_RATE_SCALAR - 1) / BLEND_RATE_SCALAR;
+    PoolPosition {
+        collateral,
+        liabilities,
+        c_factor: reserve.config.c_factor as i128,
+        l_factor: reserve.config.l_factor as i128,
+    }
+}
+
+/// Target ratio of collateral to equity in every pool
+pub(crate) fn target(e: &Env) -> u32 {
+    e.storage()
+        .instance()
+        .get(&DataKey::TargetLeverage)
+        .unwrap_or(LEVERAGE_SCALAR)
+}
+
+pub(crate) fn set_target(e: &Env, leverage: u32) {
+    if leverage < LEVERAGE_SCALAR {
+        panic!("Leverage must be at least 1x");
+    }
+    e.storage()
+        .instance()
+        .set(&DataKey::TargetLeverage, &leverage);
+}
+
+/// Liabilities a position with `equity` should carry at `leverage`
+pub(crate) fn target_liabilities(equity: i128, leverage: u32) -> i128 {
+    equity * (leverage - LEVERAGE_SCALAR) as i128 / LEVERAGE_SCALAR as i128
+}
+
+pub(crate) fn deleverage_config(e: &Env) -> DeleverageConfig {
+    e.storage()
+        .instance()
+        .get(&DataKey::DeleverageConfig)
+        .unwrap_or_default()
+}
+
+pub(crate) fn set_deleverage_config(e: &Env, config: &DeleverageConfig) {
+    if config.threshold as i128 <= LEVERAGE_SCALAR as i128
+        || config.threshold as i128 > MIN_HEALTH_FACTOR
+        || config.bounty > 100
+    {
+        panic!("Invalid deleverage config");
+    }
+    e.storage()
+        .instance()
+        .set(&DataKey::DeleverageConfig, config);
+}
@@ -2,16 +2,43 @@
 
 use soroban_sdk::{
     auth::{ContractContext, InvokerContractAuthEntry, SubContractInvocation},
-    contract, contractclient, contractevent, contractimpl, contracttype, panic_with_error, token,
-    vec, Address, Env, IntoVal, Map, String, Symbol, Vec,
+    contract, contractclient, contracterror, contractevent, contractimpl, contracttype,
+    panic_with_error, token, vec, Address, Env, IntoVal, Map, MuxedAddress, String, Symbol, Vec,
 };
+use stellar_access::ownable::{self as ownable, Ownable};
 use stellar_contract_utils::math::fixed_point::{muldiv, Rounding};
-use stellar_macros::default_impl;
+use stellar_macros::{default_impl, only_owner};
 use stellar_tokens::{
     fungible::{Base, FungibleToken},
     vault::{FungibleVault, Vault, VaultTokenError},
 };
 
+mod allocation;
+mod apr;
+mod buffer;
+mod checkpoints;
+mod cost_basis;
+mod depositors;
+mod history;
+mod leverage;
+mod metadata;
+mod oracle;
+mod points;
+mod queue;
+mod strategy;
+mod ttl;
+
+pub use apr::AprBreakdown;
+pub use checkpoints::Checkpoint;
+pub use cost_basis::{AccountEarnings, AccountPosition};
+pub use history::SharePriceCheckpoint;
+pub use leverage::DeleverageConfig;
+pub use oracle::{Asset, PriceData, PriceObservation};
+pub use points::{AccountPoints, PointsEpoch};
+pub use queue::{PendingRedeem, RedeemRequest};
+pub use strategy::{BlendReserve, Strategy, StrategyClient, StrategyKind, StrategyParams};
+pub use ttl::TtlConfig;
+
 #[contract]
 pub struct BlendVaultContract;
 
@@ -21,15 +48,22 @@ pub struct BlendVaultContract;
 pub struct InitializedEvent {
     pub asset: Address,
     pub blend_pool: Address,
-    pub usdc_reserve_index: u32,
+    pub reserve_index: u32,
+    pub reward_token_id: u32,
 }
 
+// Events for operations that move the share price or supply carry the vault
+// totals after the operation and the ledger timestamp, so indexers can chart
+// the share price without querying the contract
 #[contractevent]
 pub struct DepositEvent {
     pub operator: Address,
     pub receiver: Address,
     pub assets: i128,
     pub shares: i128,
+    pub total_assets: i128,
+    pub total_supply: i128,
+    pub timestamp: u64,
 }
 
 #[contractevent]
@@ -38,6 +72,9 @@ pub struct MintEvent {
     pub receiver: Address,
     pub assets: i128,
     pub shares: i128,
+    pub total_assets: i128,
+    pub total_supply: i128,
+    pub timestamp: u64,
 }
 
 #[contractevent]
@@ -47,6 +84,9 @@ pub struct WithdrawEvent {
     pub owner: Address,
     pub assets: i128,
     pub shares: i128,
+    pub total_assets: i128,
+    pub total_supply: i128,
+    pub timestamp: u64,
 }
 
 #[contractevent]
@@ -56,12 +96,170 @@ pub struct RedeemEvent {
     pub owner: Address,
     pub assets: i128,
     pub shares: i128,
+    pub total_assets: i128,
+    pub total_supply: i128,
+    pub timestamp: u64,
+}
+
+#[contractevent]
+pub struct RedeemRequestedEvent {
+    pub id: u32,
+    pub owner: Address,
+    pub receiver: Address,
+    pub assets: i128,
+    pub shares: i128,
+    pub total_assets: i128,
+    pub total_supply: i128,
+    pub timestamp: u64,
+}
+
+#[contractevent]
+pub struct RedeemFulfilledEvent {
+    pub id: u32,
+    pub owner: Address,
+    pub receiver: Address,
+    pub assets: i128,
+    pub shares: i128,
+    pub total_assets: i128,
+    pub total_supply: i128,
+    pub timestamp: u64,
 }
 
 #[contractevent]
 pub struct CompoundEvent {
     pub blnd_claimed: i128,
-    pub usdc_received: i128,
+    pub assets_received: i128,
+    pub total_assets: i128,
+    pub total_supply: i128,
+    pub timestamp: u64,
+}
+
+#[contractevent]
+pub struct ZapDepositEvent {
+    pub from: Address,
+    pub receiver: Address,
+    pub token_in: Address,
+    pub amount_in: i128,
+    pub assets: i128,
+    pub shares: i128,
+    pub total_assets: i128,
+    pub total_supply: i128,
+    pub timestamp: u64,
+}
+
+#[contractevent]
+pub struct ZapRedeemEvent {
+    pub owner: Address,
+    pub receiver: Address,
+    pub shares: i128,
+    pub assets: i128,
+    pub token_out: Address,
+    pub amount_out: i128,
+    pub total_assets: i128,
+    pub total_supply: i128,
+    pub timestamp: u64,
+}
+
+#[contractevent]
+pub struct RebalanceEvent {
+    pub from: Address,
+    pub to: Address,
+    pub assets: i128,
+}
+
+#[contractevent]
+pub struct BufferRebalancedEvent {
+    pub idle_before: i128,
+    pub idle_after: i128,
+    pub target: i128,
+}
+
+#[contractevent]
+pub struct MetadataUpdatedEvent {
+    pub name: String,
+    pub symbol: String,
+}
+
+#[contractevent]
+pub struct DeleverageEvent {
+    pub caller: Address,
+    pub repaid: i128,
+    pub bounty: i128,
+}
+
+#[contractevent]
+pub struct PointsEpochResetEvent {
+    pub epoch: u32,
+    pub closed_total_points: i128,
+}
+
+// Owner configuration changes share the `config_changed` topic, followed by
+// the setting that changed
+#[contractevent(topics = ["config_changed", "ttl_config"])]
+pub struct TtlConfigChangedEvent {
+    pub config: TtlConfig,
+}
+
+#[contractevent(topics = ["config_changed", "weights"])]
+pub struct WeightsChangedEvent {
+    pub weights: Vec<u32>,
+}
+
+#[contractevent(topics = ["config_changed", "leverage"])]
+pub struct LeverageChangedEvent {
+    pub previous: u32,
+    pub target_leverage: u32,
+}
+
+#[contractevent(topics = ["config_changed", "deleverage_config"])]
+pub struct DeleverageConfigChangedEvent {
+    pub config: DeleverageConfig,
+}
+
+#[contractevent(topics = ["config_changed", "idle_ratio"])]
+pub struct IdleRatioChangedEvent {
+    pub previous: u32,
+    pub ratio: u32,
+}
+
+#[contractevent(topics = ["config_changed", "strategy_added"])]
+pub struct StrategyAddedEvent {
+    pub strategy: Address,
+    pub kind: StrategyKind,
+    pub max_debt: i128,
+}
+
+#[contractevent(topics = ["config_changed", "max_debt"])]
+pub struct MaxDebtChangedEvent {
+    pub strategy: Address,
+    pub previous: i128,
+    pub max_debt: i128,
+}
+
+#[contractevent(topics = ["config_changed", "strategy_removed"])]
+pub struct StrategyRemovedEvent {
+    pub strategy: Address,
+}
+
+// ERRORS
+#[contracterror]
+#[derive(Copy, Clone, Debug, Eq, PartialEq, PartialOrd, Ord)]
+#[repr(u32)]
+pub enum BlendVaultError {
+    /// Fewer shares would be minted than the caller's lower bound.
+    MinSharesNotMet = 1000,
+    /// More assets would be pulled than the caller's upper bound.
+    MaxAssetsExceeded = 1001,
+    /// More shares would be burned than the caller's upper bound.
+    MaxSharesExceeded = 1002,
+    /// Fewer assets would be paid out than the caller's lower bound.
+    MinAssetsNotMet = 1003,
+    /// Historical lookups must target a ledger that has already closed.
+    FutureLedger = 1004,
+    /// Every pool is at or above the deleverage threshold.
+    HealthFactorAboveThreshold = 1005,
+    /// Historical lookups can reach at most `MAX_LOOKBACK` ledgers back.
+    SnapshotTooOld = 1006,
 }
 
 // STORAGE KEYS
@@ -69,12 +267,36 @@ pub struct CompoundEvent {
 #[derive(Clone, Debug, Eq, PartialEq)]
 pub enum DataKey {
     Initialized,
-    BlendPool,
-    USDCReserveIndex,
+    Strategies,
+    IdleRatio,
+    IdleAssets,
+    TargetLeverage,
+    DeleverageConfig,
     BLNDToken,
-    BLNDReserveIndex,
     CometPool,
+    /// Legacy instance-storage depositor list, see `migrate_depositors`
     Depositors,
+    DepositorCount,
+    IsDepositor(Address),
+    Depositor(u32),
+    BalanceCheckpoints(Address),
+    BalanceCheckpoint(Address, u32),
+    SupplyCheckpoints,
+    SupplyCheckpoint(u32),
+    PointsEpoch,
+    TotalPoints,
+    Points(Address),
+    TtlConfig,
+    RedeemQueueHead,
+    RedeemQueueTail,
+    RedeemRequest(u32),
+    PendingRedeem(Address),
+    QueuedRedeem,
+    PriceObservations,
+    PriceObservation(u32),
+    SharePriceHistory,
+    SharePriceCheckpoint(u32),
+    CostBasis(Address),
 }
 
 // BLEND TYPES
@@ -132,10 +354,39 @@ pub struct Reserve {
     pub scalar: i128,
 }
 
+#[contracttype]
+#[derive(Clone, Debug)]
+pub struct PoolConfig {
+    pub oracle: Address,
+    pub min_collateral: i128,
+    pub bstop_rate: u32,
+    pub status: u32,
+    pub max_positions: u32,
+}
+
+#[contracttype]
+#[derive(Clone, Debug)]
+pub struct ReserveEmissionData {
+    pub expiration: u64,
+    pub eps: u64,
+    pub index: i128,
+    pub last_time: u64,
+}
+
 const REQUEST_TYPE_SUPPLY_COLLATERAL: u32 = 2;
 const REQUEST_TYPE_WITHDRAW_COLLATERAL: u32 = 3;
+const REQUEST_TYPE_BORROW: u32 = 4;
+const REQUEST_TYPE_REPAY: u32 = 5;
 pub(crate) const BLEND_RATE_SCALAR: i128 = 1_000_000_000_000;
 
+fn blend_request(request_type: u32, asset: &Address, amount: i128) -> Request {
+    Request {
+        request_type,
+        address: asset.clone(),
+        amount,
+    }
+}
+
 #[contractclient(name = "BlendPoolClient")]
 pub trait BlendPoolInterface {
     fn submit(
@@ -155,6 +406,8 @@ pub trait BlendPoolInterface {
     fn get_positions(env: Env, address: Address) -> Positions;
     fn claim(env: Env, from: Address, reserve_token_ids: Vec<u32>, to: Address) -> i128;
     fn get_reserve(env: Env, asset: Address) -> Reserve;
+    fn get_config(env: Env) -> PoolConfig;
+    fn get_reserve_emissions(env: Env, reserve_token_index: u32) -> Option<ReserveEmissionData>;
 }
 
 #[contractclient(name = "CometPoolClient")]
@@ -176,50 +429,78 @@ pub trait CometPoolInterface {
         max_price: i128,
         user: Address,
     ) -> (i128, i128);
+    fn get_balance(env: Env, token: Address) -> i128;
+    fn get_normalized_weight(env: Env, token: Address) -> i128;
 }
 
 #[contractimpl]
 impl BlendVaultContract {
     /// Initialize the vault after deployment, this function can only be called once.
+    /// The reserve index and the claimable reward token id of the asset are
+    /// read from `blend_pool`.
     /// ### Arguments
-    /// * `asset` - The underlying asset address (USDC)
+    /// * `asset` - The underlying asset address
     /// * `decimals_offset` - The decimal offset for share token
     /// * `blend_pool` - The Blend pool contract address
-    /// * `usdc_reserve_index` - The reserve index for USDC in the Blend pool
     /// * `blnd_token` - The BLND token address for claiming rewards
-    /// * `blnd_reserve_index` - The reserve index for BLND in the Blend pool
-    /// * `comet_pool` - The Comet pool contract address for BLND-USDC swaps
+    /// * `comet_pool` - The Comet pool contract address for swapping BLND into the asset
+    /// * `owner` - Admin allowed to change vault configuration
+    /// * `name` - Share token name, derived from the asset symbol when `None`
+    ///   (e.g. "Blend Vault USDC")
+    /// * `symbol` - Share token symbol, derived from the asset symbol when `None`
+    ///   (e.g. "bvUSDC")
     pub fn initialize(
         e: &Env,
         asset: Address,
         decimals_offset: u32,
         blend_pool: Address,
-        usdc_reserve_index: u32,
         blnd_token: Address,
-        blnd_reserve_index: u32,
         comet_pool: Address,
+        owner: Address,
+        name: Option<String>,
+        symbol: Option<String>,
     ) {
         if e.storage().instance().has(&DataKey::Initialized) {
             panic!("Contract is already initialized");
         }
-        e.storage().instance().set(&DataKey::BlendPool, &blend_pool);
-        e.storage().instance().set(&DataKey::USDCReserveIndex, &usdc_reserve_index);
+        ownable::set_owner(e, &owner);
+        let primary = strategy::discover(e, &blend_pool, &asset);
+        strategy::set_strategies(
+            e,
+            &vec![
+                e,
+                StrategyParams {
+                    strategy: blend_pool.clone(),
+                    kind: StrategyKind::Blend(primary.clone()),
+                    weight: strategy::WEIGHT_SCALAR,
+                    max_debt: i128::MAX,
+                    current_debt: 0,
+                },
+            ],
+        );
         e.storage().instance().set(&DataKey::BLNDToken, &blnd_token);
-        e.storage().instance().set(&DataKey::BLNDReserveIndex, &blnd_reserve_index);
         e.storage().instance().set(&DataKey::CometPool, &comet_pool);
         Vault::set_asset(e, asset.clone());
         Vault::set_decimals_offset(e, decimals_offset);
-        Base::set_metadata(
-            e,
-            Self::decimals(e),
-            String::from_str(e, "BLEND VAULT"),
-            String::from_str(e, "BV"),
-        );
+        let (name, symbol) = match (name, symbol) {
+            (Some(name), Some(symbol)) => (name, symbol),
+            (name, symbol) => {
+                let (derived_name, derived_symbol) = metadata::derive(e, &asset);
+                (
+                    name.unwrap_or(derived_name),
+                    symbol.unwrap_or(derived_symbol),
+                )
+            }
+        };
+        metadata::validate(&name, &symbol);
+        Base::set_metadata(e, Self::decimals(e), name, symbol);
         e.storage().instance().set(&DataKey::Initialized, &true);
+        ttl::extend_instance(e);
         InitializedEvent {
             asset,
             blend_pool,
-            usdc_reserve_index,
+            reserve_index: primary.reserve_index,
+            reward_token_id: primary.reward_token_id,
         }
         .publish(e);
     }
@@ -228,18 +509,12 @@ impl BlendVaultContract {
         e.storage().instance().has(&DataKey::Initialized)
     }
 
-    fn get_blend_pool(e: &Env) -> Address {
-        e.storage()
-            .instance()
-            .get(&DataKey::BlendPool)
-            .expect("Blend pool not initialized")
-    }
-
-    fn get_usdc_reserve_index(e: &Env) -> u32 {
-        e.storage()
-            .instance()
-            .get(&DataKey::USDCReserveIndex)
-            .expect("USDC reserve index not initialized")
+    /// Correct the share token name and symbol, decimals are unchanged
+    #[only_owner]
+    pub fn set_metadata(e: &Env, name: String, symbol: String) {
+        metadata::validate(&name, &symbol);
+        Base::set_metadata(e, Self::decimals(e), name.clone(), symbol.clone());
+        MetadataUpdatedEvent { name, symbol }.publish(e);
     }
 
     fn get_blnd_token(e: &Env) -> Address {
@@ -249,13 +524,6 @@ impl BlendVaultContract {
             .expect("BLND token not initialized")
     }
 
-    fn get_blnd_reserve_index(e: &Env) -> u32 {
-        e.storage()
-            .instance()
-            .get(&DataKey::BLNDReserveIndex)
-            .expect("BLND reserve index not initialized")
-    }
-
     fn get_comet_pool(e: &Env) -> Address {
         e.storage()
             .instance()
@@ -273,72 +541,71 @@ impl BlendVaultContract {
         }
     }
 
-    /// Add an address to the depositors list if not already present
-    /// Used only for collecting snapshots of users
-    fn add_depositor(e: &Env, address: &Address) {
-        let mut depositors: Vec<Address> = e
-            .storage()
-            .instance()
-            .get(&DataKey::Depositors)
-            .unwrap_or(Vec::new(e));
-        let mut found = false;
-        for depositor in depositors.iter() {
-            if depositor == *address {
-                found = true;
-                break;
-            }
-        }
-        if !found {
-            depositors.push_back(address.clone());
-            e.storage()
-                .instance()
-                .set(&DataKey::Depositors, &depositors);
+    /// Pull `amount` of `asset` from `from` into the vault. Spends the vault's
+    /// allowance when `from` approved it, otherwise makes a direct `transfer`
+    /// authorized by `from` as part of the deposit, so Stellar Asset Contract
+    /// holders (e.g. native XLM) need no separate approval transaction.
+    fn pull_assets(e: &Env, asset: &Address, from: &Address, amount: i128) {
+        let vault_address = e.current_contract_address();
+        let token_client = token::TokenClient::new(e, asset);
+        if token_client.allowance(from, &vault_address) < amount {
+            token_client.transfer(from, &vault_address, &amount);
+            return;
         }
+        Self::authorize_invocations(
+            e,
+            vec![
+                e,
+                InvokerContractAuthEntry::Contract(SubContractInvocation {
+                    context: ContractContext {
+                        contract: asset.clone(),
+                        fn_name: Symbol::new(e, "transfer_from"),
+                        args: (
+                            vault_address.clone(),
+                            from.clone(),
+                            vault_address.clone(),
+                            amount,
+                        )
+                            .into_val(e),
+                    },
+                    sub_invocations: vec![e],
+                }),
+            ],
+        );
+        token_client.transfer_from(&vault_address, from, &vault_address, &amount);
     }
 
-    pub fn compound(e: &Env, operator: Address) -> i128 {
-        operator.require_auth();
+    /// Swap `amount_in` of `token_in` held by the vault for `token_out` on the
+    /// configured Comet pool, returning the amount received. Comet reverts when
+    /// the swap would return less than `min_amount_out`.
+    fn swap_on_comet(
+        e: &Env,
+        token_in: &Address,
+        amount_in: i128,
+        token_out: &Address,
+        min_amount_out: i128,
+    ) -> i128 {
         let vault_address = e.current_contract_address();
-        let pool_address = Self::get_blend_pool(e);
-        let blnd_token = Self::get_blnd_token(e);
-        let blnd_index = Self::get_blnd_reserve_index(e);
         let comet_pool = Self::get_comet_pool(e);
-        let usdc_token = Vault::query_asset(e);
-
-        // Step 1: Claim BLND from Blend pool
-        let pool_client = BlendPoolClient::new(e, &pool_address);
-        let mut reserve_ids: Vec<u32> = Vec::new(e);
-        reserve_ids.push_back(blnd_index);
-        let blnd_claimed = pool_client.claim(&vault_address, &reserve_ids, &vault_address);
-        if blnd_claimed <= 0 {
-            return 0;
-        }
-
-        // Step 2: Swap BLND for USDC on Comet
         let comet_client = CometPoolClient::new(e, &comet_pool);
-        let blnd_token_client = token::TokenClient::new(e, &blnd_token);
+        let token_in_client = token::TokenClient::new(e, token_in);
         let expiration_ledger = e.ledger().sequence() + 100000; // ~5.7 days
-        blnd_token_client.approve(
-            &vault_address,
-            &comet_pool,
-            &blnd_claimed,
-            &expiration_ledger,
-        );
+        token_in_client.approve(&vault_address, &comet_pool, &amount_in, &expiration_ledger);
         // The Comet pool internally calls `pull_underlying`, which in turn calls
-        // `approve` and `transfer_from` on the BLND token with the vault address
+        // `approve` and `transfer_from` on `token_in` with the vault address
         // as the authorizer. Pre-authorize those nested calls so Comet can pull
-        // the claimed BLND without tripping InvalidAction on mainnet.
+        // the tokens without tripping InvalidAction on mainnet.
         let comet_allowance_ledger = ((e.ledger().sequence() / 100000) + 1) * 100000; // matches Comet rounding
         e.authorize_as_current_contract(vec![
             e,
             InvokerContractAuthEntry::Contract(SubContractInvocation {
                 context: ContractContext {
-                    contract: blnd_token.clone(),
+                    contract: token_in.clone(),
                     fn_name: Symbol::new(e, "approve"),
                     args: (
                         vault_address.clone(),
                         comet_pool.clone(),
-                        blnd_claimed,
+                        amount_in,
                         comet_allowance_ledger,
                     )
                         .into_val(e),
@@ -347,13 +614,13 @@ impl BlendVaultContract {
             }),
             InvokerContractAuthEntry::Contract(SubContractInvocation {
                 context: ContractContext {
-                    contract: blnd_token.clone(),
+                    contract: token_in.clone(),
                     fn_name: Symbol::new(e, "transfer_from"),
                     args: (
                         comet_pool.clone(),
                         vault_address.clone(),
                         comet_pool.clone(),
-                        blnd_claimed,
+                        amount_in,
                     )
                         .into_val(e),
                 },
@@ -361,174 +628,1282 @@ impl BlendVaultContract {
             }),
         ]);
 
-        let (usdc_received, _) = comet_client.swap_exact_amount_in(
-            &blnd_token,
-            &blnd_claimed,
-            &usdc_token,
-            &0,         // min_amount_out - set to 0 (no slippage protection)
+        let (amount_out, _) = comet_client.swap_exact_amount_in(
+            token_in,
+            &amount_in,
+            token_out,
+            &min_amount_out,
             &i128::MAX, // max_price - accept any price
             &vault_address,
         );
+        amount_out
+    }
 
-        // If no USDC received, return early
-        if usdc_received <= 0 {
-            return 0;
+    /// Book freshly deposited assets as idle and put them to work unless they
+    /// fit in the idle buffer
+    fn supply_deposit(e: &Env, asset: &Address, assets: i128) {
+        buffer::add(e, assets);
+        let ratio = buffer::ratio(e);
+        if ratio == 0 {
+            Self::supply_assets(e, asset, assets);
+            return;
+        }
+        let idle = buffer::idle(e);
+        let target = buffer::target(Self::total_assets(e), ratio);
+        if idle > buffer::ceiling(target) {
+            Self::supply_assets(e, asset, idle - target);
         }
+    }
 
-        // Step 3: Deposit USDC back into Blend pool
-        let mut requests: Vec<Request> = Vec::new(e);
-        requests.push_back(Request {
-            request_type: REQUEST_TYPE_SUPPLY_COLLATERAL,
-            address: usdc_token.clone(),
-            amount: usdc_received,
-        });
-        if usdc_received > 0 {
+    /// Send `amount` to `to`, from the idle buffer when it can cover the whole
+    /// amount and from the strategies otherwise
+    fn pay_out(e: &Env, asset: &Address, amount: i128, to: &Address) {
+        if amount > 0 && buffer::idle(e) >= amount {
+            buffer::sub(e, amount);
+            token::TokenClient::new(e, asset).transfer(&e.current_contract_address(), to, &amount);
+            return;
+        }
+        Self::withdraw_assets(e, asset, amount, to);
+    }
+
+    /// Whether `pay_out` can send `amount` right now without reverting
+    fn can_pay_out(e: &Env, asset: &Address, amount: i128) -> bool {
+        buffer::idle(e) >= amount || strategy::withdrawable(e, asset) >= amount
+    }
+
+    /// Lend assets held by the vault to the most underweight strategy with
+    /// room under its debt limit. The assets stay idle when no strategy has
+    /// room for them.
+    fn supply_assets(e: &Env, asset: &Address, amount: i128) {
+        if amount <= 0 {
+            return;
+        }
+        if let Some(index) = allocation::deposit_target(e, asset, amount) {
+            Self::deposit_to_strategy(e, index, asset, amount);
+        }
+    }
+
+    /// Withdraw assets straight to `to`, drawing on the most overweight
+    /// strategies that have liquidity
+    fn withdraw_assets(e: &Env, asset: &Address, amount: i128, to: &Address) {
+        if amount <= 0 {
+            return;
+        }
+        let mut withdrawn = 0i128;
+        for (index, strategy_amount) in allocation::withdrawal_plan(e, asset, amount).iter() {
+            withdrawn += Self::withdraw_from_strategy(e, index, asset, strategy_amount, to);
+        }
+        if withdrawn < amount {
+            panic!("Insufficient strategy liquidity");
+        }
+    }
+
+    /// Lend `amount` of idle assets to the strategy at `index` and add it to
+    /// the strategy's debt
+    fn deposit_to_strategy(e: &Env, index: u32, asset: &Address, amount: i128) {
+        buffer::sub(e, amount);
+        let mut params = strategy::strategies(e).get_unchecked(index);
+        match &params.kind {
+            StrategyKind::Blend(reserve) => {
+                Self::supply_to_pool(e, &params.strategy, reserve, asset, amount);
+            }
+            StrategyKind::Contract => {
+                token::TokenClient::new(e, asset).transfer(
+                    &e.current_contract_address(),
+                    &params.strategy,
+                    &amount,
+                );
+                StrategyClient::new(e, &params.strategy).deposit(&amount);
+            }
+        }
+        params.current_debt += amount;
+        strategy::update(e, index, params);
+    }
+
+    /// Withdraw `amount` from the strategy at `index` to `to`, reducing its
+    /// debt by what it pays out. What the vault withdraws to itself is booked
+    /// as idle. Returns the amount sent to `to`.
+    fn withdraw_from_strategy(
+        e: &Env,
+        index: u32,
+        asset: &Address,
+        amount: i128,
+        to: &Address,
+    ) -> i128 {
+        let mut params = strategy::strategies(e).get_unchecked(index);
+        let sent = match &params.kind {
+            StrategyKind::Blend(reserve) => {
+                Self::withdraw_from_pool(e, &params.strategy, reserve, asset, amount, to);
+                amount
+            }
+            StrategyKind::Contract => {
+                StrategyClient::new(e, &params.strategy).withdraw(&amount, to)
+            }
+        };
+        params.current_debt = (params.current_debt - sent).max(0);
+        strategy::update(e, index, params);
+        if *to == e.current_contract_address() {
+            buffer::add(e, sent);
+        }
+        sent
+    }
+
+    /// Submit `requests` to a Blend pool on behalf of the vault, approving the
+    /// pool to pull `pull` of the asset for supplies and repayments
+    fn submit_to_pool(
+        e: &Env,
+        pool: &Address,
+        asset: &Address,
+        requests: &Vec<Request>,
+        pull: i128,
+        to: &Address,
+    ) {
+        let vault_address = e.current_contract_address();
+        if pull > 0 {
             let expiration_ledger = e.ledger().sequence() + 1000;
-            let usdc_token_client = token::TokenClient::new(e, &usdc_token);
             Self::authorize_invocations(
                 e,
                 vec![
                     e,
                     InvokerContractAuthEntry::Contract(SubContractInvocation {
                         context: ContractContext {
-                            contract: usdc_token.clone(),
+                            contract: asset.clone(),
                             fn_name: Symbol::new(e, "approve"),
-                            args: (
-                                vault_address.clone(),
-                                pool_address.clone(),
-                                usdc_received,
-                                expiration_ledger,
-                            )
+                            args: (vault_address.clone(), pool.clone(), pull, expiration_ledger)
                                 .into_val(e),
                         },
                         sub_invocations: vec![e],
                     }),
                 ],
             );
-            usdc_token_client.approve(
+            token::TokenClient::new(e, asset).approve(
                 &vault_address,
-                &pool_address,
-                &usdc_received,
+                pool,
+                &pull,
                 &expiration_ledger,
             );
         }
-        pool_client.submit_with_allowance(
+        BlendPoolClient::new(e, pool).submit_with_allowance(
             &vault_address,
             &vault_address,
-            &vault_address,
-            &requests,
+            to,
+            requests,
         );
-        CompoundEvent {
-            blnd_claimed,
-            usdc_received,
-        }
-        .publish(e);
-        usdc_received
     }
 
-    /// Get a snapshot of all depositors and their current token balances (hacky)
-    /// Returns a Map of Address -> Balance (in vault share tokens)
-    /// Useful for calculating points for future token distributions or incentive programs based on vault participation.
-    /// Nb: There are scalability limits and it will fail with too many depositors, use events instead
-    pub fn get_depositors_snapshot(e: &Env) -> Map<Address, i128> {
-        let depositors: Vec<Address> = e
-            .storage()
-            .instance()
-            .get(&DataKey::Depositors)
-            .unwrap_or(Vec::new(e));
-        let mut snapshot = Map::new(e);
-        for depositor in depositors.iter() {
-            let balance = Base::balance(e, &depositor);
-            // Only include depositors with non-zero balances
-            if balance > 0 {
-                snapshot.set(depositor.clone(), balance);
+    /// Supply `amount` to a pool. In leveraged mode the supply is looped:
+    /// borrow against the new collateral and re-supply until the pool reaches
+    /// the target leverage or the health factor guard.
+    fn supply_to_pool(
+        e: &Env,
+        pool: &Address,
+        reserve: &BlendReserve,
+        asset: &Address,
+        amount: i128,
+    ) {
+        let vault_address = e.current_contract_address();
+        let leverage = leverage::target(e);
+        let mut supply = amount;
+        if leverage > leverage::LEVERAGE_SCALAR {
+            for _ in 0..leverage::MAX_LOOPS {
+                let mut position = leverage::position(e, pool, reserve, asset);
+                position.collateral += supply;
+                let borrow = (leverage::target_liabilities(position.equity(), leverage)
+                    - position.liabilities)
+                    .min(position.borrow_capacity());
+                if borrow <= 0 {
+                    break;
+                }
+                let requests = vec![
+                    e,
+                    blend_request(REQUEST_TYPE_SUPPLY_COLLATERAL, asset, supply),
+                    blend_request(REQUEST_TYPE_BORROW, asset, borrow),
+                ];
+                Self::submit_to_pool(e, pool, asset, &requests, supply, &vault_address);
+                supply = borrow;
             }
         }
-        snapshot
+        let requests = vec![
+            e,
+            blend_request(REQUEST_TYPE_SUPPLY_COLLATERAL, asset, supply),
+        ];
+        Self::submit_to_pool(e, pool, asset, &requests, supply, &vault_address);
     }
 
-    fn convert_assets_to_shares(e: &Env, assets: i128, rounding: Rounding) -> i128 {
-        if assets < 0 {
-            panic_with_error!(e, VaultTokenError::VaultInvalidAssetsAmount);
+    /// Withdraw `amount` of equity from a pool to `to`. A leveraged position is
+    /// unwound proportionally: the same share of liabilities is repaid, using
+    /// collateral withdrawn in earlier rounds.
+    fn withdraw_from_pool(
+        e: &Env,
+        pool: &Address,
+        reserve: &BlendReserve,
+        asset: &Address,
+        amount: i128,
+        to: &Address,
+    ) {
+        let position = leverage::position(e, pool, reserve, asset);
+        if position.liabilities == 0 {
+            let requests = vec![
+                e,
+                blend_request(REQUEST_TYPE_WITHDRAW_COLLATERAL, asset, amount),
+            ];
+            Self::submit_to_pool(e, pool, asset, &requests, 0, to);
+            return;
         }
-        if assets == 0 {
-            return 0;
+
+        let vault_address = e.current_contract_address();
+        let equity = position.equity();
+        let repay_total = if amount >= equity {
+            position.liabilities
+        } else {
+            muldiv(e, position.liabilities, amount, equity, Rounding::Ceil)
+        };
+        let held = Self::unwind_pool(e, pool, reserve, asset, repay_total, amount + repay_total);
+        if *to != vault_address {
+            token::TokenClient::new(e, asset).transfer(&vault_address, to, &held);
         }
-        let pow = 10_i128
-            .checked_pow(Vault::get_decimals_offset(e))
-            .unwrap_or_else(|| panic_with_error!(e, VaultTokenError::MathOverflow));
-        let effective_supply = Base::total_supply(e)
-            .checked_add(pow)
-            .unwrap_or_else(|| panic_with_error!(e, VaultTokenError::MathOverflow));
-        let effective_assets = Self::total_assets(e)
-            .checked_add(1)
-            .unwrap_or_else(|| panic_with_error!(e, VaultTokenError::MathOverflow));
-        muldiv(e, assets, effective_supply, effective_assets, rounding)
     }
 
-    fn convert_shares_to_assets(e: &Env, shares: i128, rounding: Rounding) -> i128 {
-        if shares < 0 {
-            panic_with_error!(e, VaultTokenError::VaultInvalidSharesAmount);
+    /// Withdraw `collateral_total` from a pool to the vault while repaying
+    /// `repay_total` of its debt out of the withdrawn collateral. Blend pulls
+    /// repayments before paying out, so each round repays with what earlier
+    /// rounds withdrew. Returns the collateral left over in the vault.
+    fn unwind_pool(
+        e: &Env,
+        pool: &Address,
+        reserve: &BlendReserve,
+        asset: &Address,
+        repay_total: i128,
+        collateral_total: i128,
+    ) -> i128 {
+        let vault_address = e.current_contract_address();
+        let mut held = 0i128;
+        let mut repaid = 0i128;
+        let mut withdrawn = 0i128;
+        for _ in 0..leverage::MAX_LOOPS * 2 {
+            if repaid >= repay_total && withdrawn >= collateral_total {
+                break;
+            }
+            let mut position = leverage::position(e, pool, reserve, asset);
+            let repay = held.min(repay_total - repaid);
+            position.liabilities -= repay;
+            let take = (collateral_total - withdrawn)
+                .min(position.withdraw_capacity(leverage::UNWIND_HEALTH_FACTOR));
+            if repay <= 0 && take <= 0 {
+                break;
+            }
+            let mut requests: Vec<Request> = Vec::new(e);
+            if repay > 0 {
+                requests.push_back(blend_request(REQUEST_TYPE_REPAY, asset, repay));
+            }
+            if take > 0 {
+                requests.push_back(blend_request(REQUEST_TYPE_WITHDRAW_COLLATERAL, asset, take));
+            }
+            Self::submit_to_pool(e, pool, asset, &requests, repay, &vault_address);
+            held += take - repay;
+            repaid += repay;
+            withdrawn += take;
         }
-        if shares == 0 {
-            return 0;
+        if repaid < repay_total || withdrawn < collateral_total {
+            panic!("Unable to deleverage Blend position");
         }
-        let pow = 10_i128
-            .checked_pow(Vault::get_decimals_offset(e))
-            .unwrap_or_else(|| panic_with_error!(e, VaultTokenError::MathOverflow));
-        let effective_supply = Base::total_supply(e)
-            .checked_add(pow)
-            .unwrap_or_else(|| panic_with_error!(e, VaultTokenError::MathOverflow));
-        let effective_assets = Self::total_assets(e)
-            .checked_add(1)
-            .unwrap_or_else(|| panic_with_error!(e, VaultTokenError::MathOverflow));
-        muldiv(e, shares, effective_assets, effective_supply, rounding)
+        held
     }
-}
 
-#[default_impl]
-#[contractimpl]
-impl FungibleToken for BlendVaultContract {
-    type ContractType = Vault;
-    fn decimals(e: &Env) -> u32 {
-        Vault::decimals(e)
+    fn mint_shares(e: &Env, to: &Address, shares: i128) {
+        Self::before_shares_update(e, None, Some(to));
+        Base::mint(e, to, shares);
+        Self::after_shares_update(e, None, Some(to));
     }
-}
 
-#[contractimpl]
-impl FungibleVault for BlendVaultContract {
-    fn query_asset(e: &Env) -> Address {
-        Vault::query_asset(e)
+    fn burn_shares(e: &Env, from: &Address, shares: i128) {
+        Self::before_shares_update(e, Some(from), None);
+        Base::burn(e, from, shares);
+        Self::after_shares_update(e, Some(from), None);
     }
 
-    /// Override total_assets to query the actual balance in Blend pool
-    fn total_assets(e: &Env) -> i128 {
-        let pool_address = Self::get_blend_pool(e);
-        let usdc_index = Self::get_usdc_reserve_index(e);
-        let vault_address = e.current_contract_address();
-        let asset = Vault::query_asset(e);
-        let pool_client = BlendPoolClient::new(e, &pool_address);
-        let positions = pool_client.get_positions(&vault_address);
-        let collateral_b_tokens = positions.collateral.get(usdc_index).unwrap_or(0);
-        if collateral_b_tokens == 0 {
-            return 0;
+    /// Runs before every share balance change (mint, burn and transfers)
+    fn before_shares_update(e: &Env, from: Option<&Address>, to: Option<&Address>) {
+        if let Some(from) = from {
+            points::accrue_account(e, from);
+        }
+        if let Some(to) = to {
+            points::accrue_account(e, to);
+        }
+        if from.is_none() || to.is_none() {
+            points::accrue_total(e);
         }
-        let reserve = pool_client.get_reserve(&asset);
-        let pool_assets = collateral_b_tokens
-            .checked_mul(reserve.data.b_rate)
-            .unwrap_or_else(|| panic!("Blend collateral overflow"));
-        pool_assets / BLEND_RATE_SCALAR
     }
 
-    fn convert_to_shares(e: &Env, assets: i128) -> i128 {
-        Self::convert_assets_to_shares(e, assets, Rounding::Floor)
+    /// Runs after every share balance change (mint, burn and transfers)
+    fn after_shares_update(e: &Env, from: Option<&Address>, to: Option<&Address>) {
+        if let Some(from) = from {
+            checkpoints::push_balance(e, from, Base::balance(e, from));
+        }
+        if let Some(to) = to {
+            checkpoints::push_balance(e, to, Base::balance(e, to));
+        }
+        if from.is_none() || to.is_none() {
+            checkpoints::push_supply(e, Base::total_supply(e));
+            oracle::observe(e, Self::share_price(e));
+        }
+        Self::record_history(e);
+        ttl::extend_instance(e);
+        ttl::extend_shared(e);
+        if let Some(from) = from {
+            ttl::extend_account(e, from);
+        }
+        if let Some(to) = to {
+            ttl::extend_account(e, to);
+        }
     }
 
-    fn convert_to_assets(e: &Env, shares: i128) -> i128 {
-        Self::convert_shares_to_assets(e, shares, Rounding::Floor)
+    /// Add an address to the depositors registry if not already present
+    /// Used only for collecting snapshots of users
+    fn add_depositor(e: &Env, address: &Address) {
+        depositors::add(e, address);
     }
 
-    fn max_deposit(e: &Env, receiver: Address) -> i128 {
+    pub fn compound(e: &Env, operator: Address) -> i128 {
+        Self::compound_rewards(e, operator, 0)
+    }
+
+    /// Claim and harvest rewards, swap the BLND for at least `min_out` of the
+    /// asset and put the proceeds to work
+    fn compound_rewards(e: &Env, operator: Address, min_out: i128) -> i128 {
+        operator.require_auth();
+        ttl::extend_instance(e);
+        let vault_address = e.current_contract_address();
+        let blnd_token = Self::get_blnd_token(e);
+        let asset = Vault::query_asset(e);
+
+        // Step 1: Claim BLND from every Blend pool and harvest every strategy
+        let mut blnd_claimed = 0i128;
+        for params in strategy::strategies(e).iter() {
+            blnd_claimed += match &params.kind {
+                StrategyKind::Blend(reserve) => {
+                    let reserve_ids = vec![e, reserve.reward_token_id];
+                    BlendPoolClient::new(e, &params.strategy).claim(
+                        &vault_address,
+                        &reserve_ids,
+                        &vault_address,
+                    )
+                }
+                StrategyKind::Contract => StrategyClient::new(e, &params.strategy).harvest(),
+            };
+        }
+        if blnd_claimed <= 0 {
+            return 0;
+        }
+
+        // Step 2: Swap BLND for the asset on Comet
+        let assets_received = Self::swap_on_comet(e, &blnd_token, blnd_claimed, &asset, min_out);
+
+        // If nothing was received, return early
+        if assets_received <= 0 {
+            return 0;
+        }
+
+        // Step 3: Deposit the proceeds back into the strategies
+        buffer::add(e, assets_received);
+        Self::supply_assets(e, &asset, assets_received);
+        oracle::observe(e, Self::share_price(e));
+        Self::record_history(e);
+        CompoundEvent {
+            blnd_claimed,
+            assets_received,
+            total_assets: Self::total_assets(e),
+            total_supply: Base::total_supply(e),
+            timestamp: e.ledger().timestamp(),
+        }
+        .publish(e);
+        assets_received
+    }
+
+    /// Deposit any token the Comet pool trades by swapping it to the asset in
+    /// the same transaction. `amount_in` of `token_in` is pulled from `from`,
+    /// swapped on Comet, supplied like a regular deposit and shares are minted
+    /// to `receiver`. Reverts with `MinSharesNotMet` when the swap or the share
+    /// price leaves `receiver` with fewer than `min_shares`.
+    pub fn zap_deposit(
+        e: &Env,
+        token_in: Address,
+        amount_in: i128,
+        min_shares: i128,
+        receiver: Address,
+        from: Address,
+    ) -> i128 {
+        from.require_auth();
+        if amount_in <= 0 {
+            panic!("Zap amount must be positive");
+        }
+        let asset = Vault::query_asset(e);
+        // Price shares against the vault before the swap proceeds land in it
+        let total_assets = Self::total_assets(e);
+        Self::pull_assets(e, &token_in, &from, amount_in);
+        let assets = if token_in == asset {
+            amount_in
+        } else {
+            Self::swap_on_comet(e, &token_in, amount_in, &asset, 0)
+        };
+        let shares = Self::shares_for_assets(e, assets, total_assets, Rounding::Floor);
+        if shares <= 0 || shares < min_shares {
+            panic_with_error!(e, BlendVaultError::MinSharesNotMet);
+        }
+        Self::supply_deposit(e, &asset, assets);
+        Self::mint_shares(e, &receiver, shares);
+        cost_basis::record_deposit(e, &receiver, assets);
+        Self::add_depositor(e, &receiver);
+        ZapDepositEvent {
+            from,
+            receiver,
+            token_in,
+            amount_in,
+            assets,
+            shares,
+            total_assets: Self::total_assets(e),
+            total_supply: Base::total_supply(e),
+            timestamp: e.ledger().timestamp(),
+        }
+        .publish(e);
+        shares
+    }
+
+    /// Redeem shares straight into any token the Comet pool trades. Burns
+    /// `shares` from `owner`, withdraws their value to the vault, swaps it on
+    /// Comet and sends `token_out` to `receiver`. Reverts with `MinAssetsNotMet`
+    /// when `receiver` would get less than `min_out` of `token_out`.
+    pub fn zap_redeem(
+        e: &Env,
+        shares: i128,
+        token_out: Address,
+        min_out: i128,
+        receiver: Address,
+        owner: Address,
+    ) -> i128 {
+        owner.require_auth();
+        if shares <= 0 {
+            panic!("Zap amount must be positive");
+        }
+        let vault_address = e.current_contract_address();
+        let asset = Vault::query_asset(e);
+        let assets = Self::convert_shares_to_assets(e, shares, Rounding::Floor);
+        if buffer::idle(e) < assets {
+            Self::withdraw_assets(e, &asset, assets, &vault_address);
+        }
+        buffer::sub(e, assets);
+        let amount_out = if token_out == asset {
+            assets
+        } else {
+            Self::swap_on_comet(e, &asset, assets, &token_out, 0)
+        };
+        if amount_out <= 0 || amount_out < min_out {
+            panic_with_error!(e, BlendVaultError::MinAssetsNotMet);
+        }
+        token::TokenClient::new(e, &token_out).transfer(&vault_address, &receiver, &amount_out);
+        // Burn once the assets have left so the observed share price is final
+        cost_basis::record_exit(e, &owner, shares, Base::balance(e, &owner), assets);
+        Self::burn_shares(e, &owner, shares);
+        ZapRedeemEvent {
+            owner,
+            receiver,
+            shares,
+            assets,
+            token_out,
+            amount_out,
+            total_assets: Self::total_assets(e),
+            total_supply: Base::total_supply(e),
+            timestamp: e.ledger().timestamp(),
+        }
+        .publish(e);
+        amount_out
+    }
+
+    /// Deposit with a lower bound on the shares minted to `receiver`.
+    /// Reverts with `MinSharesNotMet` if the share price moved against the caller.
+    pub fn deposit_with_min_shares(
+        e: &Env,
+        assets: i128,
+        min_shares: i128,
+        receiver: Address,
+        from: Address,
+        operator: Address,
+    ) -> i128 {
+        let shares = <Self as FungibleVault>::deposit(e, assets, receiver, from, operator);
+        if shares < min_shares {
+            panic_with_error!(e, BlendVaultError::MinSharesNotMet);
+        }
+        shares
+    }
+
+    /// Mint with an upper bound on the assets pulled from `from`.
+    /// Reverts with `MaxAssetsExceeded` if the share price moved against the caller.
+    pub fn mint_with_max_assets(
+        e: &Env,
+        shares: i128,
+        max_assets: i128,
+        receiver: Address,
+        from: Address,
+        operator: Address,
+    ) -> i128 {
+        let assets = <Self as FungibleVault>::mint(e, shares, receiver, from, operator);
+        if assets > max_assets {
+            panic_with_error!(e, BlendVaultError::MaxAssetsExceeded);
+        }
+        assets
+    }
+
+    /// Withdraw with an upper bound on the shares burned from `owner`.
+    /// Reverts with `MaxSharesExceeded` if the share price moved against the caller.
+    pub fn withdraw_with_max_shares(
+        e: &Env,
+        assets: i128,
+        max_shares: i128,
+        receiver: Address,
+        owner: Address,
+        operator: Address,
+    ) -> i128 {
+        let shares = <Self as FungibleVault>::withdraw(e, assets, receiver, owner, operator);
+        if shares > max_shares {
+            panic_with_error!(e, BlendVaultError::MaxSharesExceeded);
+        }
+        shares
+    }
+
+    /// Redeem with a lower bound on the assets paid to `receiver`.
+    /// Reverts with `MinAssetsNotMet` if the share price moved against the caller.
+    pub fn redeem_with_min_assets(
+        e: &Env,
+        shares: i128,
+        min_assets: i128,
+        receiver: Address,
+        owner: Address,
+        operator: Address,
+    ) -> i128 {
+        let assets = <Self as FungibleVault>::redeem(e, shares, receiver, owner, operator);
+        if assets < min_assets {
+            panic_with_error!(e, BlendVaultError::MinAssetsNotMet);
+        }
+        assets
+    }
+
+    /// Compound with a lower bound on the assets the BLND swap returns. The
+    /// bound is passed to Comet, which reverts the swap if the price moved
+    /// against the vault.
+    pub fn compound_with_min_out(e: &Env, operator: Address, min_out: i128) -> i128 {
+        Self::compound_rewards(e, operator, min_out)
+    }
+
+    /// Get a snapshot of all depositors and their current token balances
+    /// Returns a Map of Address -> Balance (in vault share tokens)
+    /// Useful for calculating points for future token distributions or incentive programs based on vault participation.
+    /// Nb: Reads every registry entry, use `get_depositors_snapshot_page` once the list grows
+    pub fn get_depositors_snapshot(e: &Env) -> Map<Address, i128> {
+        depositors::snapshot_page(e, 0, depositors::count(e))
+    }
+
+    /// Same as `get_depositors_snapshot` for registry entries `start..start + limit`
+    /// `limit` is capped at 200 entries per call
+    pub fn get_depositors_snapshot_page(e: &Env, start: u32, limit: u32) -> Map<Address, i128> {
+        depositors::snapshot_page(e, start, limit.min(depositors::MAX_PAGE_SIZE))
+    }
+
+    /// Depositor addresses in registration order, `limit` is capped at 200 entries per call
+    pub fn get_depositors(e: &Env, start: u32, limit: u32) -> Vec<Address> {
+        depositors::page(e, start, limit.min(depositors::MAX_PAGE_SIZE))
+    }
+
+    /// Total number of addresses in the depositors registry
+    pub fn get_depositor_count(e: &Env) -> u32 {
+        depositors::count(e)
+    }
+
+    /// One-shot migration of the legacy instance-storage depositors vector into
+    /// the paginated registry. Safe for anyone to call, returns 0 once done.
+    pub fn migrate_depositors(e: &Env) -> u32 {
+        depositors::migrate_legacy(e)
+    }
+
+    /// Share balance of `account` at the end of `ledger`, which must already
+    /// have closed and be at most `MAX_LOOKBACK` ledgers old
+    pub fn balance_at(e: &Env, account: Address, ledger: u32) -> i128 {
+        Self::require_past_ledger(e, ledger);
+        checkpoints::balance_at(e, &account, ledger)
+    }
+
+    /// Total share supply at the end of `ledger`, which must already have
+    /// closed and be at most `MAX_LOOKBACK` ledgers old
+    pub fn total_supply_at(e: &Env, ledger: u32) -> i128 {
+        Self::require_past_ledger(e, ledger);
+        checkpoints::total_supply_at(e, ledger)
+    }
+
+    /// SEP-40: the asset prices are denominated in, the underlying
+    pub fn base(e: &Env) -> Asset {
+        Asset::Stellar(Vault::query_asset(e))
+    }
+
+    /// SEP-40: the only priced asset is the share token itself
+    pub fn assets(e: &Env) -> Vec<Asset> {
+        vec![e, Asset::Stellar(e.current_contract_address())]
+    }
+
+    /// SEP-40: seconds between price rounds. Prices use the share token's
+    /// `decimals()`.
+    pub fn resolution(_e: &Env) -> u32 {
+        oracle::RESOLUTION as u32
+    }
+
+    /// SEP-40: share price for the round containing `timestamp`, averaged
+    /// over the preceding `TWAP_WINDOW` of observed share prices
+    pub fn price(e: &Env, asset: Asset, timestamp: u64) -> Option<PriceData> {
+        if !Self::is_share_asset(e, &asset) {
+            return None;
+        }
+        oracle::price(e, timestamp)
+    }
+
+    /// SEP-40: share price for the most recent round
+    pub fn lastprice(e: &Env, asset: Asset) -> Option<PriceData> {
+        Self::price(e, asset, e.ledger().timestamp())
+    }
+
+    /// SEP-40: share prices for the last `records` rounds, newest first
+    pub fn prices(e: &Env, asset: Asset, records: u32) -> Option<Vec<PriceData>> {
+        if !Self::is_share_asset(e, &asset) {
+            return None;
+        }
+        oracle::prices(e, records)
+    }
+
+    fn is_share_asset(e: &Env, asset: &Asset) -> bool {
+        *asset == Asset::Stellar(e.current_contract_address())
+    }
+
+    /// Projected yearly rates for the assets the vault supplies to Blend: the
+    /// reserve's interest rate curve net of the backstop take rate, plus BLND
+    /// supply emissions valued at the Comet spot price. Averaged across pools
+    /// by the vault's position in each, ignoring leverage.
+    pub fn projected_apr(e: &Env) -> AprBreakdown {
+        let asset = Vault::query_asset(e);
+        let blnd_price = apr::blnd_price(
+            e,
+            &Self::get_comet_pool(e),
+            &Self::get_blnd_token(e),
+            &asset,
+        );
+        apr::projected(e, &asset, blnd_price)
+    }
+
+    /// Up to `n` share price checkpoints (max 100), newest first. A checkpoint
+    /// is written on the first interaction after each `HISTORY_INTERVAL` and
+    /// kept for `HISTORY_MAX_AGE` ledgers.
+    pub fn share_price_history(e: &Env, n: u32) -> Vec<SharePriceCheckpoint> {
+        history::latest(e, n)
+    }
+
+    /// Share price growth over at least the last `window_seconds`, annualized
+    /// without compounding with 7 decimals (`1_000_000` is 10%). Measured from
+    /// the last checkpoint at or before the start of the window to the current
+    /// share price, `None` while the history is shorter than the window.
+    pub fn realized_apy(e: &Env, window_seconds: u64) -> Option<i128> {
+        let current = SharePriceCheckpoint {
+            ledger: e.ledger().sequence(),
+            timestamp: e.ledger().timestamp(),
+            total_assets: Self::total_assets(e),
+            total_supply: Base::total_supply(e),
+        };
+        let virtual_shares = 10_i128.pow(Vault::get_decimals_offset(e));
+        history::realized_apy(e, &current, window_seconds, virtual_shares)
+    }
+
+    /// Number of balance checkpoints recorded for `account`
+    pub fn num_checkpoints(e: &Env, account: Address) -> u32 {
+        checkpoints::balance_checkpoint_count(e, &account)
+    }
+
+    /// Shares held by `account`, the net assets it deposited for them at
+    /// weighted-average cost and what they are worth now
+    pub fn position_of(e: &Env, account: Address) -> AccountPosition {
+        let shares = Base::balance(e, &account);
+        let principal = cost_basis::get(e, &account).principal;
+        let value = Self::convert_shares_to_assets(e, shares, Rounding::Floor);
+        AccountPosition {
+            shares,
+            principal,
+            value,
+            earned: value - principal,
+        }
+    }
+
+    /// Yield `account` has taken out through withdrawals, redemptions and
+    /// queued redemptions, plus the yield still held in its shares
+    pub fn earnings_of(e: &Env, account: Address) -> AccountEarnings {
+        let realized = cost_basis::get(e, &account).realized;
+        let unrealized = Self::position_of(e, account).earned;
+        AccountEarnings {
+            realized,
+            unrealized,
+            total: realized + unrealized,
+        }
+    }
+
+    /// Share-seconds accrued by `account` in the current points epoch
+    pub fn points_of(e: &Env, account: Address) -> i128 {
+        points::points_of(e, &account)
+    }
+
+    /// Share-seconds accrued by all holders in the current points epoch
+    pub fn total_points(e: &Env) -> i128 {
+        points::total_points(e)
+    }
+
+    /// Current points epoch number and its start timestamp
+    pub fn points_epoch(e: &Env) -> PointsEpoch {
+        points::epoch(e)
+    }
+
+    /// Start a new points epoch, every account restarts from zero
+    /// Returns the new epoch number
+    #[only_owner]
+    pub fn reset_epoch(e: &Env) -> u32 {
+        let (next, closed_total_points) = points::reset_epoch(e);
+        PointsEpochResetEvent {
+            epoch: next.epoch,
+            closed_total_points,
+        }
+        .publish(e);
+        next.epoch
+    }
+
+    /// Extend the TTL of the contract instance and shared persistent entries
+    /// Anyone can call this to keep the vault live between user interactions
+    pub fn bump(e: &Env) {
+        ttl::extend_instance(e);
+        ttl::extend_shared(e);
+    }
+
+    /// Extend the TTL of every persistent entry the vault keeps for `account`
+    pub fn bump_account(e: &Env, account: Address) {
+        ttl::extend_instance(e);
+        ttl::extend_account(e, &account);
+    }
+
+    pub fn get_ttl_config(e: &Env) -> TtlConfig {
+        ttl::config(e)
+    }
+
+    /// Update the TTL thresholds and extension targets (in ledgers)
+    #[only_owner]
+    pub fn set_ttl_config(e: &Env, config: TtlConfig) {
+        ttl::set_config(e, &config);
+        ttl::extend_instance(e);
+        TtlConfigChangedEvent { config }.publish(e);
+    }
+
+    /// Queue a redemption for when Blend has liquidity. The shares are held in
+    /// escrow by the vault and stop earning yield: the request pays out at most
+    /// their value at request time. Returns the request id.
+    pub fn request_redeem(e: &Env, shares: i128, receiver: Address, owner: Address) -> u32 {
+        owner.require_auth();
+        if shares <= 0 {
+            panic!("Invalid shares amount");
+        }
+        let vault_address = e.current_contract_address();
+        let assets = Self::convert_shares_to_assets(e, shares, Rounding::Floor);
+        // Escrowed shares realize their value as of the request
+        cost_basis::record_exit(e, &owner, shares, Base::balance(e, &owner), assets);
+        Self::before_shares_update(e, Some(&owner), Some(&vault_address));
+        Base::transfer(
+            e,
+            &owner,
+            &MuxedAddress::from(vault_address.clone()),
+            shares,
+        );
+        Self::after_shares_update(e, Some(&owner), Some(&vault_address));
+        let id = queue::push(
+            e,
+            &RedeemRequest {
+                owner: owner.clone(),
+                receiver: receiver.clone(),
+                shares,
+                assets,
+                requested_at: e.ledger().timestamp(),
+            },
+        );
+        RedeemRequestedEvent {
+            id,
+            owner,
+            receiver,
+            assets,
+            shares,
+            total_assets: Self::total_assets(e),
+            total_supply: Base::total_supply(e),
+            timestamp: e.ledger().timestamp(),
+        }
+        .publish(e);
+        id
+    }
+
+    /// Fulfill up to `max_requests` queued redemptions in FIFO order, stopping
+    /// at the first one the vault cannot pay yet. Callable by anyone; returns
+    /// the number of requests fulfilled.
+    pub fn fulfill_redeem(e: &Env, max_requests: u32) -> u32 {
+        ttl::extend_instance(e);
+        let asset = Vault::query_asset(e);
+        let vault_address = e.current_contract_address();
+        let mut fulfilled = 0u32;
+        while fulfilled < max_requests {
+            let id = queue::head(e);
+            let Some(request) = queue::get(e, id) else {
+                break;
+            };
+            let assets = request.assets.min(Self::convert_shares_to_assets(
+                e,
+                request.shares,
+                Rounding::Floor,
+            ));
+            if !Self::can_pay_out(e, &asset, assets) {
+                break;
+            }
+            queue::pop(e);
+            Self::pay_out(e, &asset, assets, &request.receiver);
+            Self::burn_shares(e, &vault_address, request.shares);
+            RedeemFulfilledEvent {
+                id,
+                owner: request.owner,
+                receiver: request.receiver,
+                assets,
+                shares: request.shares,
+                total_assets: Self::total_assets(e),
+                total_supply: Base::total_supply(e),
+                timestamp: e.ledger().timestamp(),
+            }
+            .publish(e);
+            fulfilled += 1;
+        }
+        fulfilled
+    }
+
+    /// Shares and assets `account` has waiting in the redemption queue
+    pub fn pending_redeem(e: &Env, account: Address) -> PendingRedeem {
+        queue::pending_of(e, &account)
+    }
+
+    /// Shares and assets waiting in the redemption queue across all accounts
+    pub fn total_queued_redeem(e: &Env) -> PendingRedeem {
+        queue::total(e)
+    }
+
+    /// A queued redemption, `None` once it has been fulfilled
+    pub fn get_redeem_request(e: &Env, id: u32) -> Option<RedeemRequest> {
+        queue::get(e, id)
+    }
+
+    /// Number of redemptions waiting in the queue
+    pub fn redeem_queue_length(e: &Env) -> u32 {
+        queue::tail(e) - queue::head(e)
+    }
+
+    /// Target ratio of collateral to equity in basis points, 10000 means no looping
+    pub fn get_leverage(e: &Env) -> u32 {
+        leverage::target(e)
+    }
+
+    /// Opt into leveraged looping: new supplies borrow the asset against their
+    /// collateral and re-supply it until each pool reaches `target_leverage`.
+    /// Rejected if any pool could not hold that leverage at the health factor
+    /// guard implied by its `c_factor` and `l_factor`.
+    #[only_owner]
+    pub fn set_leverage(e: &Env, target_leverage: u32) {
+        let asset = Vault::query_asset(e);
+        if target_leverage > leverage::LEVERAGE_SCALAR {
+            for (pool, reserve) in strategy::blend_pools(e).iter() {
+                let position = leverage::position(e, &pool, &reserve, &asset);
+                if target_leverage > position.max_leverage() {
+                    panic!("Target leverage exceeds health factor guard");
+                }
+            }
+        }
+        let previous = leverage::target(e);
+        leverage::set_target(e, target_leverage);
+        LeverageChangedEvent {
+            previous,
+            target_leverage,
+        }
+        .publish(e);
+    }
+
+    /// Lowest health factor across pools with debt, in basis points.
+    /// Weighted collateral (after `c_factor` and `l_factor`) over liabilities,
+    /// `i128::MAX` when the vault has no debt.
+    pub fn health_factor(e: &Env) -> i128 {
+        let asset = Vault::query_asset(e);
+        let mut lowest = i128::MAX;
+        for (pool, reserve) in strategy::blend_pools(e).iter() {
+            lowest = lowest.min(leverage::position(e, &pool, &reserve, &asset).health_factor());
+        }
+        lowest
+    }
+
+    pub fn get_deleverage_config(e: &Env) -> DeleverageConfig {
+        leverage::deleverage_config(e)
+    }
+
+    /// Set the health factor below which anyone may call `deleverage`, and
+    /// the bounty they earn, both in basis points
+    #[only_owner]
+    pub fn set_deleverage_config(e: &Env, config: DeleverageConfig) {
+        leverage::set_deleverage_config(e, &config);
+        DeleverageConfigChangedEvent { config }.publish(e);
+    }
+
+    /// Repay debt out of collateral in every pool whose health factor is
+    /// below the configured threshold, bringing it back to the health factor
+    /// guard. Callable by anyone; the caller earns a bounty on the repaid
+    /// debt. Returns the total debt repaid.
+    pub fn deleverage(e: &Env, caller: Address) -> i128 {
+        caller.require_auth();
+        ttl::extend_instance(e);
+        let asset = Vault::query_asset(e);
+        let config = leverage::deleverage_config(e);
+        let mut repaid = 0i128;
+        let mut bounty = 0i128;
+        for (pool, reserve) in strategy::blend_pools(e).iter() {
+            let position = leverage::position(e, &pool, &reserve, &asset);
+            if position.health_factor() >= config.threshold as i128 {
+                continue;
+            }
+            let repay = position.repay_for(leverage::MIN_HEALTH_FACTOR);
+            let fee = repay * config.bounty as i128 / leverage::LEVERAGE_SCALAR as i128;
+            bounty += Self::unwind_pool(e, &pool, &reserve, &asset, repay, repay + fee);
+            repaid += repay;
+        }
+        if repaid == 0 {
+            panic_with_error!(e, BlendVaultError::HealthFactorAboveThreshold);
+        }
+        if bounty > 0 {
+            token::TokenClient::new(e, &asset).transfer(
+                &e.current_contract_address(),
+                &caller,
+                &bounty,
+            );
+        }
+        DeleverageEvent {
+            caller,
+            repaid,
+            bounty,
+        }
+        .publish(e);
+        repaid
+    }
+
+    /// Target idle buffer in basis points of total assets
+    pub fn get_idle_ratio(e: &Env) -> u32 {
+        buffer::ratio(e)
+    }
+
+    /// Asset the vault currently holds idle, excluding tokens sent to it
+    /// outside deposits and withdrawals
+    pub fn idle_assets(e: &Env) -> i128 {
+        buffer::idle(e)
+    }
+
+    /// Set the idle buffer target in basis points of total assets, 0 disables it
+    #[only_owner]
+    pub fn set_idle_ratio(e: &Env, ratio: u32) {
+        let previous = buffer::ratio(e);
+        buffer::set_ratio(e, ratio);
+        IdleRatioChangedEvent { previous, ratio }.publish(e);
+    }
+
+    /// Top the idle buffer up from the strategies or push its excess into
+    /// them. Callable by anyone; returns the idle balance afterwards.
+    pub fn rebalance_buffer(e: &Env) -> i128 {
+        ttl::extend_instance(e);
+        let asset = Vault::query_asset(e);
+        let vault_address = e.current_contract_address();
+        let idle_before = buffer::idle(e);
+        let target = buffer::target(Self::total_assets(e), buffer::ratio(e));
+        if idle_before < target {
+            let shortfall = (target - idle_before).min(strategy::withdrawable(e, &asset));
+            Self::withdraw_assets(e, &asset, shortfall, &vault_address);
+        } else if idle_before > target {
+            Self::supply_assets(e, &asset, idle_before - target);
+        }
+        let idle_after = buffer::idle(e);
+        BufferRebalancedEvent {
+            idle_before,
+            idle_after,
+            target,
+        }
+        .publish(e);
+        idle_after
+    }
+
+    /// Registered strategies, Blend pools included, with their target weights,
+    /// debt limits and current debt
+    pub fn get_strategies(e: &Env) -> Vec<StrategyParams> {
+        strategy::strategies(e)
+    }
+
+    /// Underlying value held in each strategy, in `get_strategies` order
+    pub fn get_strategy_balances(e: &Env) -> Vec<i128> {
+        strategy::balances(e, &Vault::query_asset(e))
+    }
+
+    /// Register a strategy with a zero target weight that the vault may lend up
+    /// to `max_debt` to
+    /// ### Arguments
+    /// * `strategy` - A Blend pool when `blend_pool` is set, the reserve index
+    ///   and reward token id of the vault asset are then read from the pool.
+    ///   Otherwise a contract implementing `Strategy` for the vault asset.
+    /// * `blend_pool` - Whether `strategy` is a Blend pool the vault supplies to
+    ///   itself
+    /// * `max_debt` - Upper bound on what the vault lends to the strategy
+    #[only_owner]
+    pub fn add_strategy(e: &Env, strategy: Address, blend_pool: bool, max_debt: i128) {
+        if max_debt < 0 {
+            panic!("Invalid max debt");
+        }
+        if strategy::find(e, &strategy).is_some() {
+            panic!("Strategy already added");
+        }
+        let asset = Vault::query_asset(e);
+        let kind = if blend_pool {
+            StrategyKind::Blend(strategy::discover(e, &strategy, &asset))
+        } else {
+            if StrategyClient::new(e, &strategy).asset() != asset {
+                panic!("Strategy asset mismatch");
+            }
+            StrategyKind::Contract
+        };
+        let mut strategies = strategy::strategies(e);
+        strategies.push_back(StrategyParams {
+            strategy: strategy.clone(),
+            kind: kind.clone(),
+            weight: 0,
+            max_debt,
+            current_debt: 0,
+        });
+        strategy::set_strategies(e, &strategies);
+        StrategyAddedEvent {
+            strategy,
+            kind,
+            max_debt,
+        }
+        .publish(e);
+    }
+
+    /// Set target weights in basis points, one per strategy in
+    /// `get_strategies` order
+    #[only_owner]
+    pub fn set_weights(e: &Env, weights: Vec<u32>) {
+        let mut strategies = strategy::strategies(e);
+        if weights.len() != strategies.len() {
+            panic!("Expected one weight per strategy");
+        }
+        for (index, weight) in weights.iter().enumerate() {
+            let mut updated = strategies.get_unchecked(index as u32);
+            updated.weight = weight;
+            strategies.set(index as u32, updated);
+        }
+        strategy::set_strategies(e, &strategies);
+        WeightsChangedEvent { weights }.publish(e);
+    }
+
+    /// Change the debt limit of a strategy. Lowering it below the current debt
+    /// does not recall funds; use `rebalance` for that.
+    #[only_owner]
+    pub fn set_max_debt(e: &Env, strategy: Address, max_debt: i128) {
+        if max_debt < 0 {
+            panic!("Invalid max debt");
+        }
+        let (index, mut params) = strategy::find(e, &strategy).expect("Strategy not found");
+        let previous = params.max_debt;
+        params.max_debt = max_debt;
+        strategy::update(e, index, params);
+        MaxDebtChangedEvent {
+            strategy,
+            previous,
+            max_debt,
+        }
+        .publish(e);
+    }
+
+    /// Move `assets` from one strategy to another, within the receiving
+    /// strategy's debt limit
+    #[only_owner]
+    pub fn rebalance(e: &Env, from: Address, to: Address, assets: i128) {
+        if assets <= 0 {
+            panic_with_error!(e, VaultTokenError::VaultInvalidAssetsAmount);
+        }
+        let (from_index, _) = strategy::find(e, &from).expect("Strategy not found");
+        let (to_index, to_params) = strategy::find(e, &to).expect("Strategy not found");
+        if from_index == to_index {
+            panic!("Cannot rebalance a strategy into itself");
+        }
+        if to_params.max_debt - to_params.current_debt < assets {
+            panic!("Rebalance exceeds strategy max debt");
+        }
+        let asset = Vault::query_asset(e);
+        let vault_address = e.current_contract_address();
+        let moved = Self::withdraw_from_strategy(e, from_index, &asset, assets, &vault_address);
+        Self::deposit_to_strategy(e, to_index, &asset, moved);
+        RebalanceEvent {
+            from,
+            to,
+            assets: moved,
+        }
+        .publish(e);
+    }
+
+    /// Drop a strategy that has no weight and no remaining assets
+    #[only_owner]
+    pub fn remove_strategy(e: &Env, strategy: Address) {
+        let (index, removed) = strategy::find(e, &strategy).expect("Strategy not found");
+        if removed.weight != 0 || strategy::assets(e, &removed, &Vault::query_asset(e)) != 0 {
+            panic!("Strategy still in use");
+        }
+        let mut strategies = strategy::strategies(e);
+        strategies.remove(index);
+        strategy::set_strategies(e, &strategies);
+        StrategyRemovedEvent { strategy }.publish(e);
+    }
+
+    fn require_past_ledger(e: &Env, ledger: u32) {
+        let current = e.ledger().sequence();
+        if ledger >= current {
+            panic_with_error!(e, BlendVaultError::FutureLedger);
+        }
+        if ledger < current.saturating_sub(checkpoints::MAX_LOOKBACK) {
+            panic_with_error!(e, BlendVaultError::SnapshotTooOld);
+        }
+    }
+
+    fn convert_assets_to_shares(e: &Env, assets: i128, rounding: Rounding) -> i128 {
+        if assets < 0 {
+            panic_with_error!(e, VaultTokenError::VaultInvalidAssetsAmount);
+        }
+        if assets == 0 {
+            return 0;
+        }
+        Self::shares_for_assets(e, assets, Self::total_assets(e), rounding)
+    }
+
+    /// Shares worth `assets` when the vault holds `total_assets`
+    fn shares_for_assets(e: &Env, assets: i128, total_assets: i128, rounding: Rounding) -> i128 {
+        let pow = 10_i128
+            .checked_pow(Vault::get_decimals_offset(e))
+            .unwrap_or_else(|| panic_with_error!(e, VaultTokenError::MathOverflow));
+        let effective_supply = Base::total_supply(e)
+            .checked_add(pow)
+            .unwrap_or_else(|| panic_with_error!(e, VaultTokenError::MathOverflow));
+        let effective_assets = total_assets
+            .checked_add(1)
+            .unwrap_or_else(|| panic_with_error!(e, VaultTokenError::MathOverflow));
+        muldiv(e, assets, effective_supply, effective_assets, rounding)
+    }
+
+    /// Underlying per whole share, scaled by `10^decimals()` like SEP-40 prices
+    fn share_price(e: &Env) -> i128 {
+        let offset = Vault::get_decimals_offset(e);
+        let scale = 10_i128
+            .checked_pow(Self::decimals(e) + offset)
+            .unwrap_or_else(|| panic_with_error!(e, VaultTokenError::MathOverflow));
+        let effective_supply = Base::total_supply(e)
+            .checked_add(10_i128.pow(offset))
+            .unwrap_or_else(|| panic_with_error!(e, VaultTokenError::MathOverflow));
+        let effective_assets = Self::total_assets(e)
+            .checked_add(1)
+            .unwrap_or_else(|| panic_with_error!(e, VaultTokenError::MathOverflow));
+        muldiv(
+            e,
+            scale,
+            effective_assets,
+            effective_supply,
+            Rounding::Floor,
+        )
+    }
+
+    /// Write a share price checkpoint if `HISTORY_INTERVAL` has passed
+    fn record_history(e: &Env) {
+        if history::is_due(e) {
+            history::push(e, Self::total_assets(e), Base::total_supply(e));
+        }
+    }
+
+    fn convert_shares_to_assets(e: &Env, shares: i128, rounding: Rounding) -> i128 {
+        if shares < 0 {
+            panic_with_error!(e, VaultTokenError::VaultInvalidSharesAmount);
+        }
+        if shares == 0 {
+            return 0;
+        }
+        let pow = 10_i128
+            .checked_pow(Vault::get_decimals_offset(e))
+            .unwrap_or_else(|| panic_with_error!(e, VaultTokenError::MathOverflow));
+        let effective_supply = Base::total_supply(e)
+            .checked_add(pow)
+            .unwrap_or_else(|| panic_with_error!(e, VaultTokenError::MathOverflow));
+        let effective_assets = Self::total_assets(e)
+            .checked_add(1)
+            .unwrap_or_else(|| panic_with_error!(e, VaultTokenError::MathOverflow));
+        muldiv(e, shares, effective_assets, effective_supply, rounding)
+    }
+}
+
+#[default_impl]
+#[contractimpl]
+impl FungibleToken for BlendVaultContract {
+    type ContractType = Vault;
+    fn decimals(e: &Env) -> u32 {
+        Vault::decimals(e)
+    }
+
+    fn transfer(e: &Env, from: Address, to: MuxedAddress, amount: i128) {
+        let balance = Base::balance(e, &from);
+        cost_basis::record_transfer(e, &from, &to.address(), amount, balance);
+        Self::before_shares_update(e, Some(&from), Some(&to.address()));
+        Base::transfer(e, &from, &to, amount);
+        Self::after_shares_update(e, Some(&from), Some(&to.address()));
+    }
+
+    fn transfer_from(e: &Env, spender: Address, from: Address, to: Address, amount: i128) {
+        let balance = Base::balance(e, &from);
+        cost_basis::record_transfer(e, &from, &to, amount, balance);
+        Self::before_shares_update(e, Some(&from), Some(&to));
+        Base::transfer_from(e, &spender, &from, &to, amount);
+        Self::after_shares_update(e, Some(&from), Some(&to));
+    }
+}
+
+#[default_impl]
+#[contractimpl]
+impl Ownable for BlendVaultContract {}
+
+#[contractimpl]
+impl FungibleVault for BlendVaultContract {
+    fn query_asset(e: &Env) -> Address {
+        Vault::query_asset(e)
+    }
+
+    /// Override total_assets to query the idle buffer plus the actual balance
+    /// across every strategy, Blend pools included
+    fn total_assets(e: &Env) -> i128 {
+        let asset = Vault::query_asset(e);
+        buffer::idle(e) + strategy::total_assets(e, &asset)
+    }
+
+    fn convert_to_shares(e: &Env, assets: i128) -> i128 {
+        Self::convert_assets_to_shares(e, assets, Rounding::Floor)
+    }
+
+    fn convert_to_assets(e: &Env, shares: i128) -> i128 {
+        Self::convert_shares_to_assets(e, shares, Rounding::Floor)
+    }
+
+    fn max_deposit(e: &Env, receiver: Address) -> i128 {
         Vault::max_deposit(e, receiver)
     }
 
@@ -568,76 +1943,20 @@ impl FungibleVault for BlendVaultContract {
             return 0;
         }
         let asset = Vault::query_asset(e);
-        let vault_address = e.current_contract_address();
-        let pool_address = Self::get_blend_pool(e);
         let shares = Self::convert_assets_to_shares(e, assets, Rounding::Floor);
-        let token_client = token::TokenClient::new(e, &asset);
-        Self::authorize_invocations(
-            e,
-            vec![
-                e,
-                InvokerContractAuthEntry::Contract(SubContractInvocation {
-                    context: ContractContext {
-                        contract: asset.clone(),
-                        fn_name: Symbol::new(e, "transfer_from"),
-                        args: (
-                            vault_address.clone(),
-                            from.clone(),
-                            vault_address.clone(),
-                            assets,
-                        )
-                            .into_val(e),
-                    },
-                    sub_invocations: vec![e],
-                }),
-            ],
-        );
-        token_client.transfer_from(&vault_address, &from, &vault_address, &assets);
-        // Requires user to have called usdc.approve(vault, assets) beforehand
-        let pool_client = BlendPoolClient::new(e, &pool_address);
-        let mut requests: Vec<Request> = Vec::new(e);
-        requests.push_back(Request {
-            request_type: REQUEST_TYPE_SUPPLY_COLLATERAL,
-            address: asset.clone(),
-            amount: assets,
-        });
-        if assets > 0 {
-            let expiration_ledger = e.ledger().sequence() + 1000;
-            Self::authorize_invocations(
-                e,
-                vec![
-                    e,
-                    InvokerContractAuthEntry::Contract(SubContractInvocation {
-                        context: ContractContext {
-                            contract: asset.clone(),
-                            fn_name: Symbol::new(e, "approve"),
-                            args: (
-                                vault_address.clone(),
-                                pool_address.clone(),
-                                assets,
-                                expiration_ledger,
-                            )
-                                .into_val(e),
-                        },
-                        sub_invocations: vec![e],
-                    }),
-                ],
-            );
-            token_client.approve(&vault_address, &pool_address, &assets, &expiration_ledger);
-        }
-        pool_client.submit_with_allowance(
-            &vault_address,
-            &vault_address,
-            &vault_address,
-            &requests,
-        );
-        Base::mint(e, &receiver, shares);
+        Self::pull_assets(e, &asset, &from, assets);
+        Self::supply_deposit(e, &asset, assets);
+        Self::mint_shares(e, &receiver, shares);
+        cost_basis::record_deposit(e, &receiver, assets);
         Self::add_depositor(e, &receiver);
         DepositEvent {
             operator: operator.clone(),
             receiver: receiver.clone(),
             assets,
             shares,
+            total_assets: Self::total_assets(e),
+            total_supply: Base::total_supply(e),
+            timestamp: e.ledger().timestamp(),
         }
         .publish(e);
         shares
@@ -649,75 +1968,20 @@ impl FungibleVault for BlendVaultContract {
             return 0;
         }
         let asset = Vault::query_asset(e);
-        let vault_address = e.current_contract_address();
-        let pool_address = Self::get_blend_pool(e);
         let assets = Self::convert_shares_to_assets(e, shares, Rounding::Ceil);
-        let token_client = token::TokenClient::new(e, &asset);
-        Self::authorize_invocations(
-            e,
-            vec![
-                e,
-                InvokerContractAuthEntry::Contract(SubContractInvocation {
-                    context: ContractContext {
-                        contract: asset.clone(),
-                        fn_name: Symbol::new(e, "transfer_from"),
-                        args: (
-                            vault_address.clone(),
-                            from.clone(),
-                            vault_address.clone(),
-                            assets,
-                        )
-                            .into_val(e),
-                    },
-                    sub_invocations: vec![e],
-                }),
-            ],
-        );
-        token_client.transfer_from(&vault_address, &from, &vault_address, &assets);
-        let pool_client = BlendPoolClient::new(e, &pool_address);
-        let mut requests: Vec<Request> = Vec::new(e);
-        requests.push_back(Request {
-            request_type: REQUEST_TYPE_SUPPLY_COLLATERAL,
-            address: asset.clone(),
-            amount: assets,
-        });
-        if assets > 0 {
-            let expiration_ledger = e.ledger().sequence() + 1000;
-            Self::authorize_invocations(
-                e,
-                vec![
-                    e,
-                    InvokerContractAuthEntry::Contract(SubContractInvocation {
-                        context: ContractContext {
-                            contract: asset.clone(),
-                            fn_name: Symbol::new(e, "approve"),
-                            args: (
-                                vault_address.clone(),
-                                pool_address.clone(),
-                                assets,
-                                expiration_ledger,
-                            )
-                                .into_val(e),
-                        },
-                        sub_invocations: vec![e],
-                    }),
-                ],
-            );
-            token_client.approve(&vault_address, &pool_address, &assets, &expiration_ledger);
-        }
-        pool_client.submit_with_allowance(
-            &vault_address,
-            &vault_address,
-            &vault_address,
-            &requests,
-        );
-        Base::mint(e, &receiver, shares);
+        Self::pull_assets(e, &asset, &from, assets);
+        Self::supply_deposit(e, &asset, assets);
+        Self::mint_shares(e, &receiver, shares);
+        cost_basis::record_deposit(e, &receiver, assets);
         Self::add_depositor(e, &receiver);
         MintEvent {
             operator: operator.clone(),
             receiver: receiver.clone(),
             assets,
             shares,
+            total_assets: Self::total_assets(e),
+            total_supply: Base::total_supply(e),
+            timestamp: e.ledger().timestamp(),
         }
         .publish(e);
         assets
@@ -730,29 +1994,16 @@ impl FungibleVault for BlendVaultContract {
         owner: Address,
         operator: Address,
     ) -> i128 {
-        #[cfg(not(test))]
         operator.require_auth();
+        if operator != owner {
+            owner.require_auth();
+        }
         if assets == 0 {
             return 0;
         }
         let asset = Vault::query_asset(e);
-        let vault_address = e.current_contract_address();
-        let pool_address = Self::get_blend_pool(e);
-        let withdrawal_destination = receiver.clone();
         let shares = Self::convert_assets_to_shares(e, assets, Rounding::Ceil);
-        let pool_client = BlendPoolClient::new(e, &pool_address);
-        let mut requests: Vec<Request> = Vec::new(e);
-        requests.push_back(Request {
-            request_type: REQUEST_TYPE_WITHDRAW_COLLATERAL,
-            address: asset.clone(),
-            amount: assets,
-        });
-        pool_client.submit_with_allowance(
-            &vault_address,
-            &vault_address,
-            &withdrawal_destination,
-            &requests,
-        );
+        Self::pay_out(e, &asset, assets, &receiver);
         let owner_balance = Base::balance(e, &owner);
         if owner_balance < shares {
             panic!(
@@ -760,13 +2011,17 @@ impl FungibleVault for BlendVaultContract {
                 owner_balance, shares
             );
         }
-        Base::burn(e, &owner, shares);
+        cost_basis::record_exit(e, &owner, shares, owner_balance, assets);
+        Self::burn_shares(e, &owner, shares);
         WithdrawEvent {
             operator: operator.clone(),
             receiver: receiver.clone(),
             owner: owner.clone(),
             assets,
             shares,
+            total_assets: Self::total_assets(e),
+            total_supply: Base::total_supply(e),
+            timestamp: e.ledger().timestamp(),
         }
         .publish(e);
 
@@ -774,36 +2029,27 @@ impl FungibleVault for BlendVaultContract {
     }
 
     fn redeem(e: &Env, shares: i128, receiver: Address, owner: Address, operator: Address) -> i128 {
-        #[cfg(not(test))]
         operator.require_auth();
+        if operator != owner {
+            owner.require_auth();
+        }
         if shares == 0 {
             return 0;
         }
         let asset = Vault::query_asset(e);
-        let vault_address = e.current_contract_address();
-        let pool_address = Self::get_blend_pool(e);
-        let withdrawal_destination = receiver.clone();
         let assets = Self::convert_shares_to_assets(e, shares, Rounding::Floor);
-        let pool_client = BlendPoolClient::new(e, &pool_address);
-        let mut requests: Vec<Request> = Vec::new(e);
-        requests.push_back(Request {
-            request_type: REQUEST_TYPE_WITHDRAW_COLLATERAL,
-            address: asset.clone(),
-            amount: assets,
-        });
-        pool_client.submit_with_allowance(
-            &vault_address,
-            &vault_address,
-            &withdrawal_destination,
-            &requests,
-        );
-        Base::burn(e, &owner, shares);
+        Self::pay_out(e, &asset, assets, &receiver);
+        cost_basis::record_exit(e, &owner, shares, Base::balance(e, &owner), assets);
+        Self::burn_shares(e, &owner, shares);
         RedeemEvent {
             operator: operator.clone(),
             receiver: receiver.clone(),
             owner: owner.clone(),
             assets,
             shares,
+            total_assets: Self::total_assets(e),
+            total_supply: Base::total_supply(e),
+            timestamp: e.ledger().timestamp(),
         }
         .publish(e);
         assets
@@ -813,5 +2059,8 @@ impl FungibleVault for BlendVaultContract {
 #[cfg(test)]
 mod test;
 
-#[cfg(test)]
-mod mocks;
+#[cfg(any(test, feature = "testutils"))]
+pub mod mocks;
+
+#[cfg(any(test, feature = "testutils"))]
+pub mod testutils;
@@ -0,0 +1,42 @@
+use soroban_sdk::{token, Address, Env, String};
+
+const NAME_PREFIX: &[u8] = b"Blend Vault ";
+const SYMBOL_PREFIX: &[u8] = b"bv";
+/// Longest underlying symbol that can be embedded in the derived metadata
+const MAX_ASSET_SYMBOL_LEN: usize = 32;
+/// Symbol reported by the native XLM Stellar Asset Contract
+const NATIVE_SYMBOL: &str = "native";
+
+/// Share token name and symbol derived from the underlying token's symbol,
+/// e.g. "Blend Vault USDC" and "bvUSDC". The native XLM contract gives
+/// "Blend Vault XLM" and "bvXLM".
+pub(crate) fn derive(e: &Env, asset: &Address) -> (String, String) {
+    let mut asset_symbol = token::TokenClient::new(e, asset).symbol();
+    if asset_symbol == String::from_str(e, NATIVE_SYMBOL) {
+        asset_symbol = String::from_str(e, "XLM");
+    }
+    let len = asset_symbol.len() as usize;
+    if len == 0 || len > MAX_ASSET_SYMBOL_LEN {
+        panic!("Unsupported asset symbol");
+    }
+    let mut symbol = [0u8; MAX_ASSET_SYMBOL_LEN];
+    asset_symbol.copy_into_slice(&mut symbol[..len]);
+    (
+        prefixed(e, NAME_PREFIX, &symbol[..len]),
+        prefixed(e, SYMBOL_PREFIX, &symbol[..len]),
+    )
+}
+
+fn prefixed(e: &Env, prefix: &[u8], value: &[u8]) -> String {
+    let mut buf = [0u8; NAME_PREFIX.len() + MAX_ASSET_SYMBOL_LEN];
+    let len = prefix.len() + value.len();
+    buf[..prefix.len()].copy_from_slice(prefix);
+    buf[prefix.len()..len].copy_from_slice(value);
+    String::from_bytes(e, &buf[..len])
+}
+
+pub(crate) fn validate(name: &String, symbol: &String) {
+    if name.is_empty() || symbol.is_empty() {
+        panic!("Name and symbol must not be empty");
+    }
+}
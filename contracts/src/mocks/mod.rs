@@ -1,8 +1,7 @@
-#![cfg(test)]
-
 pub mod simple_mocks;
 
 pub use simple_mocks::{
-    MockBlendPool, MockBlendPoolClient, MockCometPool, RealisticMockBlendPool,
-    RealisticMockBlendPoolClient,
+    AdversarialMockBlendPool, AdversarialMockBlendPoolClient, MockBlendPool, MockBlendPoolClient,
+    MockCometPool, MockOracle, MockOracleClient, MockStrategy, MockStrategyClient,
+    RealisticMockBlendPool, RealisticMockBlendPoolClient, MOCK_ORACLE_DEFAULT_PRICE,
 };
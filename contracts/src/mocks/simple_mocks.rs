@@ -1,8 +1,7 @@
-#![cfg(test)]
-
 use crate::{
-    Positions, Request, Reserve, ReserveConfig, ReserveData, BLEND_RATE_SCALAR,
-    REQUEST_TYPE_SUPPLY_COLLATERAL, REQUEST_TYPE_WITHDRAW_COLLATERAL,
+    Asset, Positions, PriceData, Request, Reserve, ReserveConfig, ReserveData, BLEND_RATE_SCALAR,
+    REQUEST_TYPE_BORROW, REQUEST_TYPE_REPAY, REQUEST_TYPE_SUPPLY_COLLATERAL,
+    REQUEST_TYPE_WITHDRAW_COLLATERAL,
 };
 use soroban_sdk::{contract, contractimpl, contracttype, token, Address, Env, Map, Vec};
 
@@ -12,6 +11,9 @@ use soroban_sdk::{contract, contractimpl, contracttype, token, Address, Env, Map
 pub enum MockPoolDataKey {
     Positions(Address),
     Reserve(Address),
+    Factors(Address),
+    DRate(Address),
+    ReserveIndex(Address),
     RewardToken,
 }
 
@@ -28,14 +30,41 @@ fn store_b_rate(env: &Env, asset: &Address, b_rate: i128) {
         .set(&MockPoolDataKey::Reserve(asset.clone()), &b_rate);
 }
 
-fn build_reserve(asset: Address, b_rate: i128) -> Reserve {
+fn read_d_rate(env: &Env, asset: &Address) -> i128 {
+    env.storage()
+        .persistent()
+        .get(&MockPoolDataKey::DRate(asset.clone()))
+        .unwrap_or(BLEND_RATE_SCALAR)
+}
+
+fn read_reserve_index(env: &Env, asset: &Address) -> u32 {
+    env.storage()
+        .persistent()
+        .get(&MockPoolDataKey::ReserveIndex(asset.clone()))
+        .unwrap_or(0)
+}
+
+fn read_factors(env: &Env, asset: &Address) -> (u32, u32) {
+    env.storage()
+        .persistent()
+        .get(&MockPoolDataKey::Factors(asset.clone()))
+        .unwrap_or((0, 0))
+}
+
+pub(crate) fn build_reserve(
+    asset: Address,
+    index: u32,
+    b_rate: i128,
+    d_rate: i128,
+    factors: (u32, u32),
+) -> Reserve {
     Reserve {
         asset: asset.clone(),
         config: ReserveConfig {
-            index: 0,
+            index,
             decimals: 7,
-            c_factor: 0,
-            l_factor: 0,
+            c_factor: factors.0,
+            l_factor: factors.1,
             util: 0,
             max_util: 0,
             r_base: 0,
@@ -47,7 +76,7 @@ fn build_reserve(asset: Address, b_rate: i128) -> Reserve {
             enabled: true,
         },
         data: ReserveData {
-            d_rate: BLEND_RATE_SCALAR,
+            d_rate,
             b_rate,
             ir_mod: 0,
             b_supply: 0,
@@ -96,7 +125,13 @@ impl MockBlendPool {
     }
 
     pub fn get_reserve(env: Env, asset: Address) -> Reserve {
-        build_reserve(asset.clone(), read_b_rate(&env, &asset))
+        build_reserve(
+            asset.clone(),
+            0,
+            read_b_rate(&env, &asset),
+            BLEND_RATE_SCALAR,
+            read_factors(&env, &asset),
+        )
     }
 
     fn process_requests(
@@ -271,6 +306,7 @@ impl RealisticMockBlendPool {
             });
 
         let pool_address = env.current_contract_address();
+        let index = read_reserve_index(&env, &requests.get_unchecked(0).address);
 
         for request in requests.iter() {
             let token_client = token::TokenClient::new(&env, &request.address);
@@ -278,13 +314,36 @@ impl RealisticMockBlendPool {
             if request.request_type == REQUEST_TYPE_SUPPLY_COLLATERAL {
                 token_client.transfer_from(&pool_address, &spender, &pool_address, &request.amount);
 
-                let current = positions.collateral.get(0).unwrap_or(0);
-                positions.collateral.set(0, current + request.amount);
+                let current = positions.collateral.get(index).unwrap_or(0);
+                positions.collateral.set(index, current + request.amount);
             } else if request.request_type == REQUEST_TYPE_WITHDRAW_COLLATERAL {
                 token_client.transfer(&pool_address, &to, &request.amount);
 
-                let current = positions.collateral.get(0).unwrap_or(0);
-                positions.collateral.set(0, current - request.amount);
+                let current = positions.collateral.get(index).unwrap_or(0);
+                positions.collateral.set(index, current - request.amount);
+            } else if request.request_type == REQUEST_TYPE_BORROW {
+                token_client.transfer(&pool_address, &to, &request.amount);
+
+                let current = positions.liabilities.get(index).unwrap_or(0);
+                positions.liabilities.set(index, current + request.amount);
+            } else if request.request_type == REQUEST_TYPE_REPAY {
+                token_client.transfer_from(&pool_address, &spender, &pool_address, &request.amount);
+
+                let current = positions.liabilities.get(index).unwrap_or(0);
+                positions.liabilities.set(index, current - request.amount);
+            }
+        }
+
+        // Like Blend, reject any submit that leaves the position unhealthy
+        let liabilities = positions.liabilities.get(index).unwrap_or(0);
+        if liabilities > 0 {
+            let asset = requests.get_unchecked(0).address;
+            let (c_factor, l_factor) = read_factors(&env, &asset);
+            let collateral = positions.collateral.get(index).unwrap_or(0);
+            let weighted =
+                collateral * c_factor as i128 / 10_000_000 * l_factor as i128 / 10_000_000;
+            if weighted < liabilities {
+                panic!("unhealthy position");
             }
         }
 
@@ -331,7 +390,277 @@ impl RealisticMockBlendPool {
         store_b_rate(&env, &asset, b_rate);
     }
 
+    pub fn set_reserve_factors(env: Env, asset: Address, c_factor: u32, l_factor: u32) {
+        env.storage()
+            .persistent()
+            .set(&MockPoolDataKey::Factors(asset), &(c_factor, l_factor));
+    }
+
+    /// Place `asset` at `index` in the reserve list, positions are kept at that index
+    pub fn set_reserve_index(env: Env, asset: Address, index: u32) {
+        env.storage()
+            .persistent()
+            .set(&MockPoolDataKey::ReserveIndex(asset), &index);
+    }
+
+    pub fn get_reserve(env: Env, asset: Address) -> Reserve {
+        build_reserve(
+            asset.clone(),
+            read_reserve_index(&env, &asset),
+            read_b_rate(&env, &asset),
+            BLEND_RATE_SCALAR,
+            read_factors(&env, &asset),
+        )
+    }
+}
+
+// Mock Blend Pool that tracks b-tokens and d-tokens and converts them at the
+// stored rates, so tests can accrue debt interest or cut risk factors against
+// an open leveraged position
+#[contract]
+pub struct AdversarialMockBlendPool;
+
+#[contractimpl]
+impl AdversarialMockBlendPool {
+    pub fn submit_with_allowance(
+        env: Env,
+        owner: Address,
+        spender: Address,
+        to: Address,
+        requests: Vec<Request>,
+    ) -> Positions {
+        let mut positions = Self::get_positions(env.clone(), owner.clone());
+        let pool_address = env.current_contract_address();
+        let asset = requests.get_unchecked(0).address;
+        let b_rate = read_b_rate(&env, &asset);
+        let d_rate = read_d_rate(&env, &asset);
+
+        for request in requests.iter() {
+            let token_client = token::TokenClient::new(&env, &request.address);
+            let b_tokens = positions.collateral.get(0).unwrap_or(0);
+            let d_tokens = positions.liabilities.get(0).unwrap_or(0);
+
+            if request.request_type == REQUEST_TYPE_SUPPLY_COLLATERAL {
+                token_client.transfer_from(&pool_address, &spender, &pool_address, &request.amount);
+                let minted = request.amount * BLEND_RATE_SCALAR / b_rate;
+                positions.collateral.set(0, b_tokens + minted);
+            } else if request.request_type == REQUEST_TYPE_WITHDRAW_COLLATERAL {
+                token_client.transfer(&pool_address, &to, &request.amount);
+                let burned = (request.amount * BLEND_RATE_SCALAR + b_rate - 1) / b_rate;
+                if burned > b_tokens {
+                    panic!("insufficient collateral");
+                }
+                positions.collateral.set(0, b_tokens - burned);
+            } else if request.request_type == REQUEST_TYPE_BORROW {
+                token_client.transfer(&pool_address, &to, &request.amount);
+                let minted = (request.amount * BLEND_RATE_SCALAR + d_rate - 1) / d_rate;
+                positions.liabilities.set(0, d_tokens + minted);
+            } else if request.request_type == REQUEST_TYPE_REPAY {
+                token_client.transfer_from(&pool_address, &spender, &pool_address, &request.amount);
+                let burned = (request.amount * BLEND_RATE_SCALAR / d_rate).min(d_tokens);
+                positions.liabilities.set(0, d_tokens - burned);
+            }
+        }
+
+        let d_tokens = positions.liabilities.get(0).unwrap_or(0);
+        if d_tokens > 0 {
+            let (c_factor, l_factor) = read_factors(&env, &asset);
+            let collateral = positions.collateral.get(0).unwrap_or(0) * b_rate / BLEND_RATE_SCALAR;
+            let liabilities = d_tokens * d_rate / BLEND_RATE_SCALAR;
+            let weighted =
+                collateral * c_factor as i128 / 10_000_000 * l_factor as i128 / 10_000_000;
+            if weighted < liabilities {
+                panic!("unhealthy position");
+            }
+        }
+
+        env.storage()
+            .persistent()
+            .set(&MockPoolDataKey::Positions(owner), &positions);
+        positions
+    }
+
+    pub fn get_positions(env: Env, address: Address) -> Positions {
+        env.storage()
+            .persistent()
+            .get(&MockPoolDataKey::Positions(address))
+            .unwrap_or_else(|| Positions {
+                collateral: Map::new(&env),
+                liabilities: Map::new(&env),
+                supply: Map::new(&env),
+            })
+    }
+
+    pub fn claim(_env: Env, _from: Address, _reserve_token_ids: Vec<u32>, _to: Address) -> i128 {
+        0
+    }
+
+    /// Push the supply and debt rates, e.g. to accrue interest on liabilities
+    /// faster than on collateral
+    pub fn set_rates(env: Env, asset: Address, b_rate: i128, d_rate: i128) {
+        store_b_rate(&env, &asset, b_rate);
+        env.storage()
+            .persistent()
+            .set(&MockPoolDataKey::DRate(asset), &d_rate);
+    }
+
+    pub fn set_reserve_factors(env: Env, asset: Address, c_factor: u32, l_factor: u32) {
+        env.storage()
+            .persistent()
+            .set(&MockPoolDataKey::Factors(asset), &(c_factor, l_factor));
+    }
+
     pub fn get_reserve(env: Env, asset: Address) -> Reserve {
-        build_reserve(asset.clone(), read_b_rate(&env, &asset))
+        build_reserve(
+            asset.clone(),
+            0,
+            read_b_rate(&env, &asset),
+            read_d_rate(&env, &asset),
+            read_factors(&env, &asset),
+        )
+    }
+}
+
+// Storage keys for MockStrategy
+#[contracttype]
+#[derive(Clone)]
+pub enum MockStrategyDataKey {
+    Vault,
+    Asset,
+    Locked,
+    RewardToken,
+}
+
+// Mock Strategy that keeps the asset it is lent as a raw balance. Minting the
+// asset to it accrues yield and `set_locked` takes liquidity away.
+#[contract]
+pub struct MockStrategy;
+
+#[contractimpl]
+impl MockStrategy {
+    pub fn initialize(env: Env, vault: Address, asset: Address) {
+        env.storage()
+            .instance()
+            .set(&MockStrategyDataKey::Vault, &vault);
+        env.storage()
+            .instance()
+            .set(&MockStrategyDataKey::Asset, &asset);
+    }
+
+    /// Hold back `amount` of the balance from withdrawals, as if lent out
+    pub fn set_locked(env: Env, amount: i128) {
+        env.storage()
+            .instance()
+            .set(&MockStrategyDataKey::Locked, &amount);
+    }
+
+    pub fn set_reward_token(env: Env, token: Address) {
+        env.storage()
+            .instance()
+            .set(&MockStrategyDataKey::RewardToken, &token);
+    }
+
+    pub fn asset(env: Env) -> Address {
+        env.storage()
+            .instance()
+            .get(&MockStrategyDataKey::Asset)
+            .unwrap()
+    }
+
+    pub fn deposit(env: Env, _amount: i128) {
+        Self::vault(&env).require_auth();
+    }
+
+    pub fn withdraw(env: Env, amount: i128, to: Address) -> i128 {
+        Self::vault(&env).require_auth();
+        token::TokenClient::new(&env, &Self::asset(env.clone())).transfer(
+            &env.current_contract_address(),
+            &to,
+            &amount,
+        );
+        amount
+    }
+
+    pub fn total_assets(env: Env) -> i128 {
+        token::TokenClient::new(&env, &Self::asset(env.clone()))
+            .balance(&env.current_contract_address())
+    }
+
+    /// Send the whole reward token balance to the vault
+    pub fn harvest(env: Env) -> i128 {
+        let vault = Self::vault(&env);
+        vault.require_auth();
+        let Some(reward_token) = env
+            .storage()
+            .instance()
+            .get::<_, Address>(&MockStrategyDataKey::RewardToken)
+        else {
+            return 0;
+        };
+        let token_client = token::TokenClient::new(&env, &reward_token);
+        let amount = token_client.balance(&env.current_contract_address());
+        if amount > 0 {
+            token_client.transfer(&env.current_contract_address(), &vault, &amount);
+        }
+        amount
+    }
+
+    pub fn max_withdrawable(env: Env) -> i128 {
+        let locked: i128 = env
+            .storage()
+            .instance()
+            .get(&MockStrategyDataKey::Locked)
+            .unwrap_or(0);
+        (Self::total_assets(env) - locked).max(0)
+    }
+
+    fn vault(env: &Env) -> Address {
+        env.storage()
+            .instance()
+            .get(&MockStrategyDataKey::Vault)
+            .unwrap()
+    }
+}
+
+// Storage keys for MockOracle
+#[contracttype]
+#[derive(Clone)]
+pub enum MockOracleDataKey {
+    Price(Address),
+}
+
+/// Price of one unit of every asset the mock oracle has not been told about
+pub const MOCK_ORACLE_DEFAULT_PRICE: i128 = 1_0000000;
+
+// SEP-40 oracle with fixed, always fresh prices. Blend only prices collateral
+// and liabilities through it, so any borrow against a real pool needs one.
+#[contract]
+pub struct MockOracle;
+
+#[contractimpl]
+impl MockOracle {
+    pub fn set_price(env: Env, asset: Address, price: i128) {
+        env.storage()
+            .instance()
+            .set(&MockOracleDataKey::Price(asset), &price);
+    }
+
+    pub fn decimals(_env: Env) -> u32 {
+        7
+    }
+
+    pub fn lastprice(env: Env, asset: Asset) -> Option<PriceData> {
+        let Asset::Stellar(asset) = asset else {
+            return None;
+        };
+        let price = env
+            .storage()
+            .instance()
+            .get(&MockOracleDataKey::Price(asset))
+            .unwrap_or(MOCK_ORACLE_DEFAULT_PRICE);
+        Some(PriceData {
+            price,
+            timestamp: env.ledger().timestamp(),
+        })
     }
 }
@@ -0,0 +1,188 @@
+use soroban_sdk::{contracttype, Address, Env, Symbol, Vec};
+
+use crate::{ttl::DAY_IN_LEDGERS, DataKey};
+
+/// Seconds between price rounds reported through the SEP-40 interface
+pub(crate) const RESOLUTION: u64 = 300;
+/// Length of the time-weighted average behind each reported price
+pub(crate) const TWAP_WINDOW: u64 = 6 * RESOLUTION;
+/// Observations kept in the ring buffer, at most one per round
+pub(crate) const OBSERVATION_CAPACITY: u32 = 64;
+/// Upper bound on rounds returned by `prices`
+pub(crate) const MAX_RECORDS: u32 = 32;
+/// Ledgers without an observation after which the buffer is considered stale
+pub(crate) const OBSERVATION_MAX_AGE: u32 = 30 * DAY_IN_LEDGERS;
+/// TTL given to every observation on write. Consecutive observations of a
+/// live buffer are less than `OBSERVATION_MAX_AGE` apart, so every entry the
+/// oracle reads is still live at twice that age.
+pub(crate) const OBSERVATION_TTL: u32 = 2 * OBSERVATION_MAX_AGE;
+
+/// SEP-40 asset identifier
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Asset {
+    Stellar(Address),
+    Other(Symbol),
+}
+
+/// SEP-40 price record
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct PriceData {
+    pub price: i128,
+    pub timestamp: u64,
+}
+
+/// Share price observed at `timestamp` and the running sum of price times
+/// seconds up to that moment
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct PriceObservation {
+    pub timestamp: u64,
+    pub price: i128,
+    pub cumulative: i128,
+}
+
+/// Observations `start..end` are readable, `end` counts every observation
+/// ever recorded. `start` skips past observations that may have been archived
+/// when the buffer went stale. `last_ledger` is the ledger of the newest one.
+#[contracttype]
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+struct ObservationBounds {
+    start: u32,
+    end: u32,
+    last_ledger: u32,
+}
+
+fn bounds(e: &Env) -> ObservationBounds {
+    e.storage()
+        .instance()
+        .get(&DataKey::PriceObservations)
+        .unwrap_or_default()
+}
+
+fn get(e: &Env, index: u32) -> PriceObservation {
+    e.storage()
+        .persistent()
+        .get(&DataKey::PriceObservation(index % OBSERVATION_CAPACITY))
+        .expect("Price observation missing")
+}
+
+/// Whether no observation was recorded in the last `OBSERVATION_MAX_AGE`
+/// ledgers. Nothing is read from a stale buffer, it may already be archived.
+fn is_stale(e: &Env, bounds: &ObservationBounds) -> bool {
+    bounds.start == bounds.end || e.ledger().sequence() - bounds.last_ledger >= OBSERVATION_MAX_AGE
+}
+
+/// Storage key of the newest observation, if the buffer is live
+pub(crate) fn latest_key(e: &Env) -> Option<DataKey> {
+    let bounds = bounds(e);
+    if is_stale(e, &bounds) {
+        None
+    } else {
+        Some(DataKey::PriceObservation(
+            (bounds.end - 1) % OBSERVATION_CAPACITY,
+        ))
+    }
+}
+
+/// Record the current share price. Observations within one round collapse
+/// into a single entry, so the buffer always spans at least
+/// `OBSERVATION_CAPACITY` rounds. A new price only carries weight once time
+/// has passed, so a spike never moves the average within its own ledger. A
+/// stale buffer is restarted from this observation.
+pub(crate) fn observe(e: &Env, price: i128) {
+    let now = e.ledger().timestamp();
+    let mut bounds = bounds(e);
+    let mut index = bounds.end;
+    let mut cumulative = 0i128;
+    if is_stale(e, &bounds) {
+        bounds.start = bounds.end;
+    } else {
+        let last = get(e, bounds.end - 1);
+        cumulative = last.cumulative + last.price * (now - last.timestamp) as i128;
+        if last.timestamp / RESOLUTION == now / RESOLUTION {
+            index = bounds.end - 1;
+        }
+    }
+    if index == bounds.end {
+        bounds.end += 1;
+        bounds.start = bounds
+            .start
+            .max(bounds.end.saturating_sub(OBSERVATION_CAPACITY));
+    }
+    bounds.last_ledger = e.ledger().sequence();
+    e.storage()
+        .instance()
+        .set(&DataKey::PriceObservations, &bounds);
+    let key = DataKey::PriceObservation(index % OBSERVATION_CAPACITY);
+    e.storage().persistent().set(
+        &key,
+        &PriceObservation {
+            timestamp: now,
+            price,
+            cumulative,
+        },
+    );
+    e.storage()
+        .persistent()
+        .extend_ttl(&key, OBSERVATION_TTL, OBSERVATION_TTL);
+}
+
+/// Cumulative price at `timestamp`, `None` if it predates the buffer or the
+/// buffer is stale
+fn cumulative_at(e: &Env, timestamp: u64) -> Option<i128> {
+    let bounds = bounds(e);
+    if is_stale(e, &bounds) {
+        return None;
+    }
+    let mut index = bounds.end;
+    while index > bounds.start {
+        index -= 1;
+        let observation = get(e, index);
+        if observation.timestamp <= timestamp {
+            return Some(
+                observation.cumulative
+                    + observation.price * (timestamp - observation.timestamp) as i128,
+            );
+        }
+    }
+    None
+}
+
+/// Time-weighted share price over the `TWAP_WINDOW` ending at the round
+/// containing `timestamp`
+pub(crate) fn price(e: &Env, timestamp: u64) -> Option<PriceData> {
+    if timestamp > e.ledger().timestamp() {
+        return None;
+    }
+    let round = timestamp / RESOLUTION * RESOLUTION;
+    let start = round.checked_sub(TWAP_WINDOW)?;
+    let twap = (cumulative_at(e, round)? - cumulative_at(e, start)?) / TWAP_WINDOW as i128;
+    Some(PriceData {
+        price: twap,
+        timestamp: round,
+    })
+}
+
+/// Prices of the last `records` rounds, newest first
+pub(crate) fn prices(e: &Env, records: u32) -> Option<Vec<PriceData>> {
+    let mut prices = Vec::new(e);
+    let mut timestamp = e.ledger().timestamp();
+    for _ in 0..records.min(MAX_RECORDS) {
+        let Some(data) = price(e, timestamp) else {
+            break;
+        };
+        timestamp = data.timestamp;
+        prices.push_back(data);
+        let Some(previous) = timestamp.checked_sub(RESOLUTION) else {
+            break;
+        };
+        timestamp = previous;
+    }
+    if prices.is_empty() {
+        None
+    } else {
+        Some(prices)
+    }
+}
@@ -0,0 +1,90 @@
+use soroban_sdk::{contracttype, Address, Env};
+use stellar_tokens::fungible::Base;
+
+use crate::DataKey;
+
+/// Share-seconds accrued by one account within a points epoch
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct AccountPoints {
+    pub epoch: u32,
+    pub points: i128,
+    pub last_update: u64,
+}
+
+/// Current points epoch and when it started
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct PointsEpoch {
+    pub epoch: u32,
+    pub start: u64,
+}
+
+pub(crate) fn epoch(e: &Env) -> PointsEpoch {
+    e.storage()
+        .instance()
+        .get(&DataKey::PointsEpoch)
+        .unwrap_or(PointsEpoch { epoch: 0, start: 0 })
+}
+
+/// Bring stored points forward to now, `balance` must be the balance held
+/// since the last update (i.e. read before the change being recorded)
+fn accrued(e: &Env, stored: Option<AccountPoints>, balance: i128) -> AccountPoints {
+    let now = e.ledger().timestamp();
+    let current = epoch(e);
+    let (points, since) = match stored {
+        Some(stored) if stored.epoch == current.epoch => (stored.points, stored.last_update),
+        Some(stored) => (0, stored.last_update.max(current.start)),
+        None => (0, now),
+    };
+    let elapsed = now.saturating_sub(since) as i128;
+    AccountPoints {
+        epoch: current.epoch,
+        points: points
+            .checked_add(
+                balance
+                    .checked_mul(elapsed)
+                    .unwrap_or_else(|| panic!("Points overflow")),
+            )
+            .unwrap_or_else(|| panic!("Points overflow")),
+        last_update: now,
+    }
+}
+
+/// Checkpoint an account's share-seconds before its balance changes
+pub(crate) fn accrue_account(e: &Env, account: &Address) {
+    let key = DataKey::Points(account.clone());
+    let stored = e.storage().persistent().get(&key);
+    let updated = accrued(e, stored, Base::balance(e, account));
+    e.storage().persistent().set(&key, &updated);
+}
+
+/// Checkpoint total share-seconds before total supply changes
+pub(crate) fn accrue_total(e: &Env) {
+    let stored = e.storage().instance().get(&DataKey::TotalPoints);
+    let updated = accrued(e, stored, Base::total_supply(e));
+    e.storage().instance().set(&DataKey::TotalPoints, &updated);
+}
+
+pub(crate) fn points_of(e: &Env, account: &Address) -> i128 {
+    let stored = e.storage().persistent().get(&DataKey::Points(account.clone()));
+    accrued(e, stored, Base::balance(e, account)).points
+}
+
+pub(crate) fn total_points(e: &Env) -> i128 {
+    let stored = e.storage().instance().get(&DataKey::TotalPoints);
+    accrued(e, stored, Base::total_supply(e)).points
+}
+
+/// Close the current epoch and start counting from zero. Returns the final
+/// total of the closed epoch.
+pub(crate) fn reset_epoch(e: &Env) -> (PointsEpoch, i128) {
+    let closed_total = total_points(e);
+    let current = epoch(e);
+    let next = PointsEpoch {
+        epoch: current.epoch + 1,
+        start: e.ledger().timestamp(),
+    };
+    e.storage().instance().set(&DataKey::PointsEpoch, &next);
+    (next, closed_total)
+}
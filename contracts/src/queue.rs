@@ -0,0 +1,113 @@
+use core::ops::Range;
+
+use soroban_sdk::{contracttype, Address, Env};
+
+use crate::{ttl, DataKey};
+
+/// Queued requests kept alive by `bump`, counted from the front of the queue
+pub(crate) const MAX_BUMPED_REQUESTS: u32 = 16;
+
+/// An escrowed redemption waiting for Blend liquidity
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct RedeemRequest {
+    pub owner: Address,
+    pub receiver: Address,
+    /// Shares held in escrow by the vault
+    pub shares: i128,
+    /// Value of the shares when the request was made, the most it will pay out
+    pub assets: i128,
+    pub requested_at: u64,
+}
+
+/// Shares and assets waiting in the redemption queue
+#[contracttype]
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct PendingRedeem {
+    pub shares: i128,
+    pub assets: i128,
+}
+
+/// Id of the oldest unfulfilled request
+pub(crate) fn head(e: &Env) -> u32 {
+    e.storage()
+        .instance()
+        .get(&DataKey::RedeemQueueHead)
+        .unwrap_or(0)
+}
+
+/// Id the next request will get
+pub(crate) fn tail(e: &Env) -> u32 {
+    e.storage()
+        .instance()
+        .get(&DataKey::RedeemQueueTail)
+        .unwrap_or(0)
+}
+
+/// Ids of the requests `fulfill_redeem` reaches first, up to
+/// `MAX_BUMPED_REQUESTS` of them
+pub(crate) fn front(e: &Env) -> Range<u32> {
+    let head = head(e);
+    head..tail(e).min(head + MAX_BUMPED_REQUESTS)
+}
+
+pub(crate) fn get(e: &Env, id: u32) -> Option<RedeemRequest> {
+    e.storage().persistent().get(&DataKey::RedeemRequest(id))
+}
+
+pub(crate) fn pending_of(e: &Env, account: &Address) -> PendingRedeem {
+    e.storage()
+        .persistent()
+        .get(&DataKey::PendingRedeem(account.clone()))
+        .unwrap_or_default()
+}
+
+pub(crate) fn total(e: &Env) -> PendingRedeem {
+    e.storage()
+        .instance()
+        .get(&DataKey::QueuedRedeem)
+        .unwrap_or_default()
+}
+
+fn add_pending(e: &Env, account: &Address, shares: i128, assets: i128) {
+    let key = DataKey::PendingRedeem(account.clone());
+    let mut pending = pending_of(e, account);
+    pending.shares += shares;
+    pending.assets += assets;
+    if pending.shares == 0 {
+        e.storage().persistent().remove(&key);
+    } else {
+        e.storage().persistent().set(&key, &pending);
+        ttl::extend_persistent(e, &key);
+    }
+
+    let mut queued = total(e);
+    queued.shares += shares;
+    queued.assets += assets;
+    e.storage().instance().set(&DataKey::QueuedRedeem, &queued);
+}
+
+/// Append a request to the back of the queue and return its id
+pub(crate) fn push(e: &Env, request: &RedeemRequest) -> u32 {
+    let id = tail(e);
+    let key = DataKey::RedeemRequest(id);
+    e.storage().persistent().set(&key, request);
+    ttl::extend_persistent(e, &key);
+    e.storage()
+        .instance()
+        .set(&DataKey::RedeemQueueTail, &(id + 1));
+    add_pending(e, &request.owner, request.shares, request.assets);
+    id
+}
+
+/// Remove the request at the front of the queue
+pub(crate) fn pop(e: &Env) -> Option<(u32, RedeemRequest)> {
+    let id = head(e);
+    let request = get(e, id)?;
+    e.storage().persistent().remove(&DataKey::RedeemRequest(id));
+    e.storage()
+        .instance()
+        .set(&DataKey::RedeemQueueHead, &(id + 1));
+    add_pending(e, &request.owner, -request.shares, -request.assets);
+    Some((id, request))
+}
@@ -0,0 +1,172 @@
+use soroban_sdk::{contractclient, contracttype, token, Address, Env, Vec};
+
+use crate::{leverage, BlendPoolClient, DataKey};
+
+/// Maximum number of strategies, Blend pools included, the vault can allocate
+/// across
+pub(crate) const MAX_STRATEGIES: u32 = 8;
+/// Strategy weights are expressed in basis points and must sum to this value
+pub(crate) const WEIGHT_SCALAR: u32 = 10_000;
+
+/// Interface a yield source must expose to be used by the vault.
+///
+/// The vault transfers `amount` of the asset to the strategy before calling
+/// `deposit`, and only the vault may call `deposit`, `withdraw` and `harvest`.
+#[contractclient(name = "StrategyClient")]
+pub trait Strategy {
+    /// The underlying asset the strategy accepts
+    fn asset(env: Env) -> Address;
+    /// Put `amount` of the asset already held by the strategy to work
+    fn deposit(env: Env, amount: i128);
+    /// Send `amount` of the asset to `to`, returning the amount sent
+    fn withdraw(env: Env, amount: i128, to: Address) -> i128;
+    /// Current value of the strategy's position in the asset
+    fn total_assets(env: Env) -> i128;
+    /// Send the strategy's BLND rewards to the vault, returning the amount of
+    /// BLND sent. `compound` swaps the sum with the vault's own BLND claims on
+    /// Comet, so rewards in any other token must be swapped to BLND first.
+    fn harvest(env: Env) -> i128;
+    /// Amount of the asset that can be withdrawn right now
+    fn max_withdrawable(env: Env) -> i128;
+}
+
+/// The vault asset's reserve in a Blend pool. Blend's claimable token id for a
+/// reserve's supply emissions is `index * 2 + 1`.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct BlendReserve {
+    pub reserve_index: u32,
+    pub reward_token_id: u32,
+}
+
+/// How the vault reaches a strategy's yield source
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum StrategyKind {
+    /// A Blend pool the vault supplies to itself, looping it in leveraged mode
+    Blend(BlendReserve),
+    /// A contract implementing `Strategy`
+    Contract,
+}
+
+/// A registered strategy, its target weight and the vault's debt accounting
+/// for it
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct StrategyParams {
+    pub strategy: Address,
+    pub kind: StrategyKind,
+    /// Target share of allocated assets in basis points
+    pub weight: u32,
+    /// Upper bound on `current_debt`
+    pub max_debt: i128,
+    /// Assets the vault has lent to the strategy, net of repayments
+    pub current_debt: i128,
+}
+
+/// Reserve of `asset` in `pool`, read from the pool
+pub(crate) fn discover(e: &Env, pool: &Address, asset: &Address) -> BlendReserve {
+    let reserve_index = BlendPoolClient::new(e, pool)
+        .get_reserve(asset)
+        .config
+        .index;
+    BlendReserve {
+        reserve_index,
+        reward_token_id: reserve_index * 2 + 1,
+    }
+}
+
+pub(crate) fn strategies(e: &Env) -> Vec<StrategyParams> {
+    e.storage()
+        .instance()
+        .get(&DataKey::Strategies)
+        .expect("Strategies not initialized")
+}
+
+pub(crate) fn set_strategies(e: &Env, strategies: &Vec<StrategyParams>) {
+    if strategies.is_empty() || strategies.len() > MAX_STRATEGIES {
+        panic!("Invalid number of strategies");
+    }
+    let mut total_weight = 0u32;
+    for params in strategies.iter() {
+        total_weight = total_weight.saturating_add(params.weight);
+    }
+    if total_weight != WEIGHT_SCALAR {
+        panic!("Strategy weights must sum to 10000");
+    }
+    e.storage().instance().set(&DataKey::Strategies, strategies);
+}
+
+pub(crate) fn find(e: &Env, strategy: &Address) -> Option<(u32, StrategyParams)> {
+    for (index, params) in strategies(e).iter().enumerate() {
+        if params.strategy == *strategy {
+            return Some((index as u32, params));
+        }
+    }
+    None
+}
+
+pub(crate) fn update(e: &Env, index: u32, params: StrategyParams) {
+    let mut all = strategies(e);
+    all.set(index, params);
+    set_strategies(e, &all);
+}
+
+/// Blend pools in the registry with the vault asset's reserve in each
+pub(crate) fn blend_pools(e: &Env) -> Vec<(Address, BlendReserve)> {
+    let mut pools = Vec::new(e);
+    for params in strategies(e).iter() {
+        if let StrategyKind::Blend(reserve) = params.kind {
+            pools.push_back((params.strategy, reserve));
+        }
+    }
+    pools
+}
+
+/// Underlying value of the vault's position in one strategy. Blend positions
+/// count collateral net of any liabilities from leverage.
+pub(crate) fn assets(e: &Env, params: &StrategyParams, asset: &Address) -> i128 {
+    match &params.kind {
+        StrategyKind::Blend(reserve) => {
+            leverage::position(e, &params.strategy, reserve, asset).equity()
+        }
+        StrategyKind::Contract => StrategyClient::new(e, &params.strategy).total_assets(),
+    }
+}
+
+/// Assets one strategy can pay out right now
+pub(crate) fn liquidity(e: &Env, params: &StrategyParams, asset: &Address) -> i128 {
+    match &params.kind {
+        StrategyKind::Blend(_) => assets(e, params, asset)
+            .min(token::TokenClient::new(e, asset).balance(&params.strategy)),
+        StrategyKind::Contract => StrategyClient::new(e, &params.strategy).max_withdrawable(),
+    }
+}
+
+/// Underlying value held in every strategy, in registry order
+pub(crate) fn balances(e: &Env, asset: &Address) -> Vec<i128> {
+    let mut balances = Vec::new(e);
+    for params in strategies(e).iter() {
+        balances.push_back(assets(e, &params, asset));
+    }
+    balances
+}
+
+pub(crate) fn total_assets(e: &Env, asset: &Address) -> i128 {
+    let mut total = 0i128;
+    for balance in balances(e, asset).iter() {
+        total = total
+            .checked_add(balance)
+            .unwrap_or_else(|| panic!("Strategy assets overflow"));
+    }
+    total
+}
+
+/// Assets every strategy can pay out right now
+pub(crate) fn withdrawable(e: &Env, asset: &Address) -> i128 {
+    let mut total = 0i128;
+    for params in strategies(e).iter() {
+        total += liquidity(e, &params, asset);
+    }
+    total
+}
@@ -2,29 +2,22 @@ extern crate std;
 
 use super::*;
 use crate::mocks::{
-    MockBlendPool, MockBlendPoolClient, MockCometPool, RealisticMockBlendPool,
-    RealisticMockBlendPoolClient,
-};
-use blend_contract_sdk::{
-    pool,
-    testutils::{default_reserve_config, BlendFixture},
+    simple_mocks::build_reserve, AdversarialMockBlendPool, AdversarialMockBlendPoolClient,
+    MockBlendPool, MockBlendPoolClient, MockCometPool, MockStrategy, MockStrategyClient,
+    RealisticMockBlendPool, RealisticMockBlendPoolClient,
 };
+use crate::testutils::TestFixture;
+use blend_strategy::{BlendStrategy, BlendStrategyClient};
 use sep_41_token::testutils::{MockTokenClient, MockTokenWASM};
 use soroban_sdk::{
     contract, contractimpl,
-    testutils::{Address as _, BytesN as _, Ledger, MockAuth, MockAuthInvoke},
-    vec, Address, BytesN, Env, IntoVal, Map, String as SorobanString, String,
+    testutils::{Address as _, Events as _, Ledger, MockAuth, MockAuthInvoke},
+    vec, Address, Env, IntoVal, Map, String as SorobanString, String, Val,
 };
 use std::{fs, path::PathBuf, process::Command, sync::OnceLock};
 
 const EMBEDDED_COMET_WASM: &[u8] = include_bytes!("../test_artifacts/comet_pool.wasm");
 
-const WEEK_IN_SECONDS: u64 = 60 * 60 * 24 * 7;
-const EMITTER_WAIT_SECONDS: u64 = WEEK_IN_SECONDS * 3;
-const BACKSTOP_WAIT_SECONDS: u64 = 60;
-const GULP_DELAY_SECONDS: u64 = 60 * 60 * 24 + BACKSTOP_WAIT_SECONDS;
-const EMISSION_RETRY_DELAY_SECONDS: u64 = 60 * 60;
-
 fn comet_wasm_bytes() -> std::vec::Vec<u8> {
     static WASM: OnceLock<std::vec::Vec<u8>> = OnceLock::new();
     WASM.get_or_init(|| {
@@ -80,273 +73,204 @@ fn deploy_real_comet(
     comet_address
 }
 
-// Test fixture that uses the real Blend protocol contracts
-struct TestFixture<'a> {
+// Legacy mock fixture used for tests that need direct control over Blend internals
+struct MockPoolFixture<'a> {
     env: Env,
-    deployer: Address,
+    admin: Address,
     user: Address,
     usdc_token: Address,
     usdc_client: MockTokenClient<'a>,
     blnd_token: Address,
-    blnd_client: MockTokenClient<'a>,
-    blend_fixture: BlendFixture<'a>,
     blend_pool: Address,
     comet_pool: Address,
     vault: Address,
     vault_client: BlendVaultContractClient<'a>,
-    usdc_reserve_index: u32,
-    blnd_reserve_token_id: u32,
 }
 
-impl<'a> TestFixture<'a> {
-    fn new() -> Self {
-        Self::new_with_comet(|_, _, _, _, blend_fixture| {
-            blend_fixture.backstop_token.address.clone()
-        })
+/// How the vault itself is put together: its asset, the asset's place in the
+/// pool and the share token metadata. Anything configured after `initialize`
+/// belongs in the test.
+#[derive(Default)]
+struct MockPoolFixtureBuilder {
+    /// Name, symbol and decimals of the vault asset, USDC unless set
+    asset: Option<(&'static str, &'static str, u32)>,
+    stellar_asset: bool,
+    reserve_index: u32,
+    /// Share token name and symbol, `BLEND VAULT` and `BV` unless set
+    metadata: Option<(Option<&'static str>, Option<&'static str>)>,
+}
+
+impl MockPoolFixtureBuilder {
+    /// Vault over a mock token other than USDC
+    fn asset(mut self, name: &'static str, symbol: &'static str, decimals: u32) -> Self {
+        self.asset = Some((name, symbol, decimals));
+        self
     }
 
-    fn new_with_comet<F>(build_comet: F) -> Self
-    where
-        F: FnOnce(&Env, &Address, &Address, &Address, &BlendFixture) -> Address,
-    {
+    /// Vault over a Stellar Asset Contract, e.g. native XLM. The user holds
+    /// the asset without approving the vault.
+    fn stellar_asset(mut self) -> Self {
+        self.stellar_asset = true;
+        self
+    }
+
+    /// List the vault asset at `reserve_index` in the pool
+    fn reserve_index(mut self, reserve_index: u32) -> Self {
+        self.reserve_index = reserve_index;
+        self
+    }
+
+    /// Share token name and symbol passed to `initialize`
+    fn metadata(mut self, name: Option<&'static str>, symbol: Option<&'static str>) -> Self {
+        self.metadata = Some((name, symbol));
+        self
+    }
+
+    fn build(self) -> MockPoolFixture<'static> {
         let env = Env::default();
-        env.mock_all_auths_allowing_non_root_auth();
-        env.ledger().with_mut(|li| {
-            li.timestamp = 1_700_000_000;
-            li.sequence_number = 100;
-            li.protocol_version = 23;
-        });
+        env.mock_all_auths();
 
-        let deployer = Address::generate(&env);
+        let admin = Address::generate(&env);
         let user = Address::generate(&env);
 
-        let usdc_token = env.register_contract_wasm(None, MockTokenWASM);
+        let usdc_token = if self.stellar_asset {
+            env.register_stellar_asset_contract_v2(admin.clone())
+                .address()
+        } else {
+            let (name, symbol, decimals) = self.asset.unwrap_or(("USD Coin", "USDC", 7));
+            let token = env.register_contract_wasm(None, MockTokenWASM);
+            MockTokenClient::new(&env, &token).initialize(
+                &admin,
+                &decimals,
+                &SorobanString::from_str(&env, name),
+                &SorobanString::from_str(&env, symbol),
+            );
+            token
+        };
+        // Stellar Asset Contracts share the mock token's `mint` and token interface
         let usdc_client = MockTokenClient::new(&env, &usdc_token);
-        usdc_client.initialize(
-            &deployer,
-            &7,
-            &SorobanString::from_str(&env, "USD Coin"),
-            &SorobanString::from_str(&env, "USDC"),
-        );
 
         let blnd_token = env.register_contract_wasm(None, MockTokenWASM);
         let blnd_client = MockTokenClient::new(&env, &blnd_token);
         blnd_client.initialize(
-            &deployer,
+            &admin,
             &7,
             &SorobanString::from_str(&env, "Blend Token"),
             &SorobanString::from_str(&env, "BLND"),
         );
 
-        let blend_fixture = BlendFixture::deploy(&env, &deployer, &blnd_token, &usdc_token);
-
-        let blend_pool = blend_fixture.pool_factory.mock_all_auths().deploy(
-            &deployer,
-            &String::from_str(&env, "Test Pool"),
-            &BytesN::<32>::random(&env),
-            &Address::generate(&env),
-            &0_1000000,
-            &4,
-            &1_0000000,
-        );
-        let pool_client = pool::Client::new(&env, &blend_pool);
-
-        let usdc_reserve_index = 0u32;
-        let mut usdc_reserve_config = default_reserve_config();
-        usdc_reserve_config.index = usdc_reserve_index;
-        pool_client
-            .mock_all_auths()
-            .queue_set_reserve(&usdc_token, &usdc_reserve_config);
-        pool_client.mock_all_auths().set_reserve(&usdc_token);
+        let blend_pool = env.register_contract(None, RealisticMockBlendPool);
+        let pool_client = RealisticMockBlendPoolClient::new(&env, &blend_pool);
+        pool_client.set_reward_token(&blnd_token);
+        if self.reserve_index > 0 {
+            pool_client.set_reserve_index(&usdc_token, &self.reserve_index);
+        }
 
-        let blnd_reserve_index = 1u32;
-        let mut blnd_reserve_config = default_reserve_config();
-        blnd_reserve_config.index = blnd_reserve_index;
-        pool_client
-            .mock_all_auths()
-            .queue_set_reserve(&blnd_token, &blnd_reserve_config);
-        pool_client.mock_all_auths().set_reserve(&blnd_token);
-
-        let emission_metadata = vec![
-            &env,
-            pool::ReserveEmissionMetadata {
-                res_index: usdc_reserve_index,
-                res_type: 1,
-                share: 1_0000000,
-            },
-        ];
-        pool_client
-            .mock_all_auths()
-            .set_emissions_config(&emission_metadata);
+        let comet_pool = env.register_contract(None, MockCometPool);
 
-        blend_fixture
-            .backstop
-            .mock_all_auths()
-            .deposit(&deployer, &blend_pool, &50_000_0000000);
-        blend_fixture
-            .backstop
-            .mock_all_auths()
-            .add_reward(&blend_pool, &Option::<Address>::None);
-        blend_fixture.backstop.mock_all_auths().drop();
-        blend_fixture.backstop.mock_all_auths().distribute();
-        pool_client.mock_all_auths().set_status(&3);
-        pool_client.mock_all_auths().update_status();
-
-        let comet_pool = build_comet(&env, &deployer, &blnd_token, &usdc_token, &blend_fixture);
-        let blnd_reserve_token_id = usdc_reserve_index * 2 + 1;
+        let (name, symbol) = self.metadata.unwrap_or((Some("BLEND VAULT"), Some("BV")));
         let vault = env.register_contract(None, BlendVaultContract);
         let vault_client = BlendVaultContractClient::new(&env, &vault);
         vault_client.initialize(
             &usdc_token,
             &0,
             &blend_pool,
-            &usdc_reserve_index,
             &blnd_token,
-            &blnd_reserve_token_id,
             &comet_pool,
+            &admin,
+            &name.map(|name| SorobanString::from_str(&env, name)),
+            &symbol.map(|symbol| SorobanString::from_str(&env, symbol)),
         );
 
         usdc_client.mint(&user, &1_000_000_0000000);
-        blnd_client.mint(&user, &1_000_000_0000000);
-        usdc_client.approve(&user, &vault, &i128::MAX, &200);
+        if !self.stellar_asset {
+            usdc_client.approve(&user, &vault, &i128::MAX, &200);
+        }
+        blnd_client.mint(&blend_pool, &1_000_000_0000000);
 
-        Self {
+        MockPoolFixture {
             env,
-            deployer,
+            admin,
             user,
             usdc_token,
             usdc_client,
             blnd_token,
-            blnd_client,
-            blend_fixture,
             blend_pool,
             comet_pool,
             vault,
             vault_client,
-            usdc_reserve_index,
-            blnd_reserve_token_id,
         }
     }
+}
 
-    fn pool_client(&self) -> pool::Client<'a> {
-        pool::Client::new(&self.env, &self.blend_pool)
-    }
-
-    fn advance_time(&self, seconds: u64) {
-        let new_ts = self.env.ledger().timestamp() + seconds;
-        self.env.ledger().with_mut(|li| {
-            li.timestamp = new_ts;
-            li.sequence_number += 1;
-        });
+impl<'a> MockPoolFixture<'a> {
+    fn new() -> Self {
+        Self::builder().build()
     }
 
-    fn accrue_emissions(&self) -> bool {
-        let mut emitted = self.run_emission_cycle();
-        if emitted == 0 {
-            self.advance_time(EMISSION_RETRY_DELAY_SECONDS);
-            emitted = self.run_emission_cycle();
-        }
-        if emitted == 0 {
-            return false;
-        }
-        self.advance_time(GULP_DELAY_SECONDS);
-        let gulped = self.pool_client().mock_all_auths().gulp_emissions();
-        if gulped > 0 {
-            self.advance_time(BACKSTOP_WAIT_SECONDS);
-            true
-        } else {
-            false
-        }
+    fn builder() -> MockPoolFixtureBuilder {
+        MockPoolFixtureBuilder::default()
     }
 
-    fn run_emission_cycle(&self) -> i128 {
-        self.advance_time(EMITTER_WAIT_SECONDS);
-        self.blend_fixture.emitter.mock_all_auths().distribute();
-        self.advance_time(BACKSTOP_WAIT_SECONDS);
-        self.blend_fixture.backstop.mock_all_auths().distribute()
+    /// Register a `MockStrategy` over `asset` for the vault, without adding it
+    fn register_strategy(&self, asset: &Address) -> Address {
+        let strategy = self.env.register_contract(None, MockStrategy);
+        MockStrategyClient::new(&self.env, &strategy).initialize(&self.vault, asset);
+        strategy
     }
 
-    fn claim_rewards(&self, reserve_ids: &[u32]) -> i128 {
-        let mut ids = Vec::new(&self.env);
-        for id in reserve_ids {
-            ids.push_back(*id);
-        }
-        self.pool_client()
-            .mock_all_auths()
-            .claim(&self.vault, &ids, &self.vault)
+    /// Register another pool with BLND to emit and add it to the vault at
+    /// zero weight
+    fn add_pool(&self) -> Address {
+        let pool = self.env.register_contract(None, RealisticMockBlendPool);
+        RealisticMockBlendPoolClient::new(&self.env, &pool).set_reward_token(&self.blnd_token);
+        MockTokenClient::new(&self.env, &self.blnd_token).mint(&pool, &1_000_000_0000000);
+        self.vault_client.add_strategy(&pool, &true, &i128::MAX);
+        pool
     }
-}
-
-// Legacy mock fixture used for tests that need direct control over Blend internals
-struct MockPoolFixture<'a> {
-    env: Env,
-    user: Address,
-    usdc_token: Address,
-    usdc_client: MockTokenClient<'a>,
-    blnd_token: Address,
-    blend_pool: Address,
-    vault: Address,
-    vault_client: BlendVaultContractClient<'a>,
-}
 
-impl<'a> MockPoolFixture<'a> {
-    fn new() -> Self {
-        let env = Env::default();
-        env.mock_all_auths();
-
-        let admin = Address::generate(&env);
-        let user = Address::generate(&env);
-
-        let usdc_token = env.register_contract_wasm(None, MockTokenWASM);
-        let usdc_client = MockTokenClient::new(&env, &usdc_token);
-        usdc_client.initialize(
-            &admin,
-            &7,
-            &SorobanString::from_str(&env, "USD Coin"),
-            &SorobanString::from_str(&env, "USDC"),
-        );
-
-        let blnd_token = env.register_contract_wasm(None, MockTokenWASM);
-        let blnd_client = MockTokenClient::new(&env, &blnd_token);
-        blnd_client.initialize(
-            &admin,
-            &7,
-            &SorobanString::from_str(&env, "Blend Token"),
-            &SorobanString::from_str(&env, "BLND"),
+    /// Register a `BlendStrategy` over another pool with BLND to emit and add
+    /// it to the vault as a strategy contract, returning the strategy and pool
+    fn add_blend_strategy(&self, max_debt: i128) -> (Address, Address) {
+        let pool = self.env.register_contract(None, RealisticMockBlendPool);
+        RealisticMockBlendPoolClient::new(&self.env, &pool).set_reward_token(&self.blnd_token);
+        MockTokenClient::new(&self.env, &self.blnd_token).mint(&pool, &1_000_000_0000000);
+        let strategy = self.env.register_contract(None, BlendStrategy);
+        BlendStrategyClient::new(&self.env, &strategy).initialize(
+            &self.vault,
+            &self.usdc_token,
+            &pool,
         );
+        self.vault_client.add_strategy(&strategy, &false, &max_debt);
+        (strategy, pool)
+    }
 
-        let blend_pool = env.register_contract(None, RealisticMockBlendPool);
-        let realistic_pool_client = RealisticMockBlendPoolClient::new(&env, &blend_pool);
-        realistic_pool_client.set_reward_token(&blnd_token);
-
-        let comet_pool = env.register_contract(None, MockCometPool);
+    /// Move the clock to `timestamp`, one ledger later
+    fn warp(&self, timestamp: u64) {
+        self.env.ledger().with_mut(|li| {
+            li.timestamp = timestamp;
+            li.sequence_number += 1;
+        });
+    }
 
-        let vault = env.register_contract(None, BlendVaultContract);
-        let vault_client = BlendVaultContractClient::new(&env, &vault);
-        vault_client.initialize(
-            &usdc_token,
-            &0,
-            &blend_pool,
-            &0,
-            &blnd_token,
-            &1,
-            &comet_pool,
-        );
+    /// The vault's shares as an oracle asset
+    fn share_asset(&self) -> Asset {
+        Asset::Stellar(self.vault.clone())
+    }
 
-        usdc_client.mint(&user, &1_000_000_0000000);
-        usdc_client.approve(&user, &vault, &i128::MAX, &200);
-        blnd_client.mint(&blend_pool, &1_000_000_0000000);
+    /// Grow the share price by 10% through the pool's b_rate
+    fn grow_share_price(&self) {
+        MockBlendPoolClient::new(&self.env, &self.blend_pool)
+            .set_b_rate(&self.usdc_token, &(crate::BLEND_RATE_SCALAR * 11 / 10));
+    }
 
-        Self {
-            env,
-            user,
-            usdc_token,
-            usdc_client,
-            blnd_token,
-            blend_pool,
-            vault,
-            vault_client,
-        }
+    /// Give the user `amount` BLND and the 1:1 mock Comet pool enough of the
+    /// vault asset to swap it
+    fn fund_zap(&self, amount: i128) -> MockTokenClient<'a> {
+        let blnd_client = MockTokenClient::new(&self.env, &self.blnd_token);
+        blnd_client.mint(&self.user, &amount);
+        self.usdc_client.mint(&self.comet_pool, &amount);
+        blnd_client
     }
 }
 
@@ -384,6 +308,10 @@ impl NoopBlendPool {
         0
     }
 
+    pub fn get_reserve(_env: Env, asset: Address) -> Reserve {
+        build_reserve(asset, 0, BLEND_RATE_SCALAR, BLEND_RATE_SCALAR, (0, 0))
+    }
+
     fn empty_positions(env: &Env) -> Positions {
         Positions {
             collateral: Map::new(env),
@@ -1121,10 +1049,11 @@ fn test_compound_requires_only_operator_auth() {
         &usdc_token,
         &0,
         &blend_pool,
-        &0,
         &blnd_token,
-        &1,
         &comet_pool,
+        &admin,
+        &Some(SorobanString::from_str(&env, "BLEND VAULT")),
+        &Some(SorobanString::from_str(&env, "BV")),
     );
 
     env.set_auths(&[]);
@@ -1142,6 +1071,33 @@ fn test_compound_requires_only_operator_auth() {
     assert_eq!(result, 0);
 }
 
+#[test]
+fn test_compound_with_min_out_succeeds_within_bound() {
+    let fixture = MockPoolFixture::new();
+    deposit_as_user(&fixture, 1000_0000000);
+    // The mock pool pays out 1000 BLND per claim and Comet swaps it 1:1
+    fixture.usdc_client.mint(&fixture.comet_pool, &1000_0000000);
+
+    let received = fixture
+        .vault_client
+        .compound_with_min_out(&fixture.user, &1000_0000000);
+    assert_eq!(received, 1000_0000000);
+    assert_eq!(fixture.vault_client.total_assets(), 2000_0000000);
+}
+
+#[test]
+fn test_compound_with_min_out_reverts_in_swap_below_bound() {
+    let fixture = MockPoolFixture::new();
+    deposit_as_user(&fixture, 1000_0000000);
+    fixture.usdc_client.mint(&fixture.comet_pool, &1000_0000000);
+
+    assert!(fixture
+        .vault_client
+        .try_compound_with_min_out(&fixture.user, &1000_0000001)
+        .is_err());
+    assert_eq!(fixture.vault_client.total_assets(), 1000_0000000);
+}
+
 #[test]
 fn test_deposit_different_receiver() {
     let fixture = TestFixture::new();
@@ -1456,10 +1412,11 @@ fn test_is_initialized() {
         &usdc_token,
         &0,
         &blend_pool,
-        &0,
         &blnd_token,
-        &1,
         &comet_pool,
+        &admin,
+        &Some(SorobanString::from_str(&env, "BLEND VAULT")),
+        &Some(SorobanString::from_str(&env, "BV")),
     );
 
     // Check that it's now initialized
@@ -1503,10 +1460,11 @@ fn test_double_initialization() {
         &usdc_token,
         &0,
         &blend_pool,
-        &0,
         &blnd_token,
-        &1,
         &comet_pool,
+        &admin,
+        &Some(SorobanString::from_str(&env, "BLEND VAULT")),
+        &Some(SorobanString::from_str(&env, "BV")),
     );
 
     // Try to initialize again (should panic)
@@ -1514,10 +1472,11 @@ fn test_double_initialization() {
         &usdc_token,
         &0,
         &blend_pool,
-        &0,
         &blnd_token,
-        &1,
         &comet_pool,
+        &admin,
+        &Some(SorobanString::from_str(&env, "BLEND VAULT")),
+        &Some(SorobanString::from_str(&env, "BV")),
     );
 }
 
@@ -1567,10 +1526,11 @@ fn test_deposit_with_authorization() {
         &usdc_token,
         &0,
         &blend_pool,
-        &0,
         &blnd_token,
-        &1,
         &comet_pool,
+        &user,
+        &Some(SorobanString::from_str(&env, "BLEND VAULT")),
+        &Some(SorobanString::from_str(&env, "BV")),
     );
 
     // Mint USDC to user
@@ -1633,10 +1593,11 @@ fn test_withdraw_with_authorization() {
         &usdc_token,
         &0,
         &blend_pool,
-        &0,
         &blnd_token,
-        &1,
         &comet_pool,
+        &user,
+        &Some(SorobanString::from_str(&env, "BLEND VAULT")),
+        &Some(SorobanString::from_str(&env, "BV")),
     );
 
     // Mint USDC to user
@@ -1705,10 +1666,11 @@ fn test_multiple_deposits_with_auth() {
         &usdc_token,
         &0,
         &blend_pool,
-        &0,
         &blnd_token,
-        &1,
         &comet_pool,
+        &user,
+        &Some(SorobanString::from_str(&env, "BLEND VAULT")),
+        &Some(SorobanString::from_str(&env, "BV")),
     );
 
     // Mint USDC to both users
@@ -2267,3 +2229,3218 @@ fn test_preview_functions_match_actual() {
         "preview_withdraw should match actual shares burned"
     );
 }
+
+// ===== Slippage-Bounded Entrypoints =====
+
+#[test]
+fn test_deposit_with_min_shares_succeeds_within_bound() {
+    let fixture = TestFixture::new();
+    let deposit_amount = 1000_0000000;
+
+    let expected = fixture.vault_client.preview_deposit(&deposit_amount);
+    let shares = fixture.vault_client.mock_all_auths().deposit_with_min_shares(
+        &deposit_amount,
+        &expected,
+        &fixture.user,
+        &fixture.user,
+        &fixture.user,
+    );
+
+    assert_eq!(shares, expected);
+    assert_eq!(fixture.vault_client.balance(&fixture.user), shares);
+}
+
+#[test]
+#[should_panic(expected = "Error(Contract, #1000)")]
+fn test_deposit_with_min_shares_reverts_after_price_move() {
+    let fixture = MockPoolFixture::new();
+    let deposit_amount = 1000_0000000;
+
+    fixture.vault_client.mock_all_auths().deposit(
+        &deposit_amount,
+        &fixture.user,
+        &fixture.user,
+        &fixture.user,
+    );
+    let quoted = fixture.vault_client.preview_deposit(&deposit_amount);
+
+    // Yield lands between signing and execution, so each share is worth more
+    let mock_pool_client = MockBlendPoolClient::new(&fixture.env, &fixture.blend_pool);
+    mock_pool_client.set_b_rate(&fixture.usdc_token, &(crate::BLEND_RATE_SCALAR * 11 / 10));
+
+    fixture.vault_client.mock_all_auths().deposit_with_min_shares(
+        &deposit_amount,
+        &quoted,
+        &fixture.user,
+        &fixture.user,
+        &fixture.user,
+    );
+}
+
+#[test]
+#[should_panic(expected = "Error(Contract, #1001)")]
+fn test_mint_with_max_assets_reverts_after_price_move() {
+    let fixture = MockPoolFixture::new();
+    let deposit_amount = 1000_0000000;
+
+    fixture.vault_client.mock_all_auths().deposit(
+        &deposit_amount,
+        &fixture.user,
+        &fixture.user,
+        &fixture.user,
+    );
+    let shares_to_mint = 100_0000000;
+    let quoted = fixture.vault_client.preview_mint(&shares_to_mint);
+
+    let mock_pool_client = MockBlendPoolClient::new(&fixture.env, &fixture.blend_pool);
+    mock_pool_client.set_b_rate(&fixture.usdc_token, &(crate::BLEND_RATE_SCALAR * 11 / 10));
+
+    fixture.vault_client.mock_all_auths().mint_with_max_assets(
+        &shares_to_mint,
+        &quoted,
+        &fixture.user,
+        &fixture.user,
+        &fixture.user,
+    );
+}
+
+#[test]
+#[should_panic(expected = "Error(Contract, #1002)")]
+fn test_withdraw_with_max_shares_reverts_after_price_move() {
+    let fixture = MockPoolFixture::new();
+    let deposit_amount = 1000_0000000;
+
+    fixture.vault_client.mock_all_auths().deposit(
+        &deposit_amount,
+        &fixture.user,
+        &fixture.user,
+        &fixture.user,
+    );
+    let withdraw_amount = 500_0000000;
+    let quoted = fixture.vault_client.preview_withdraw(&withdraw_amount);
+
+    // A loss makes every asset cost more shares to withdraw
+    let mock_pool_client = MockBlendPoolClient::new(&fixture.env, &fixture.blend_pool);
+    mock_pool_client.set_b_rate(&fixture.usdc_token, &(crate::BLEND_RATE_SCALAR * 9 / 10));
+
+    fixture.vault_client.mock_all_auths().withdraw_with_max_shares(
+        &withdraw_amount,
+        &quoted,
+        &fixture.user,
+        &fixture.user,
+        &fixture.user,
+    );
+}
+
+#[test]
+fn test_redeem_with_min_assets_succeeds_within_bound() {
+    let fixture = MockPoolFixture::new();
+    let deposit_amount = 1000_0000000;
+
+    let shares = fixture.vault_client.mock_all_auths().deposit(
+        &deposit_amount,
+        &fixture.user,
+        &fixture.user,
+        &fixture.user,
+    );
+    let to_redeem = shares / 2;
+    let quoted = fixture.vault_client.preview_redeem(&to_redeem);
+
+    let assets = fixture.vault_client.mock_all_auths().redeem_with_min_assets(
+        &to_redeem,
+        &quoted,
+        &fixture.user,
+        &fixture.user,
+        &fixture.user,
+    );
+
+    assert_eq!(assets, quoted);
+    assert_eq!(fixture.vault_client.balance(&fixture.user), shares - to_redeem);
+}
+
+#[test]
+#[should_panic(expected = "Error(Contract, #1003)")]
+fn test_redeem_with_min_assets_reverts_after_price_move() {
+    let fixture = MockPoolFixture::new();
+    let deposit_amount = 1000_0000000;
+
+    let shares = fixture.vault_client.mock_all_auths().deposit(
+        &deposit_amount,
+        &fixture.user,
+        &fixture.user,
+        &fixture.user,
+    );
+    let to_redeem = shares / 2;
+    let quoted = fixture.vault_client.preview_redeem(&to_redeem);
+
+    let mock_pool_client = MockBlendPoolClient::new(&fixture.env, &fixture.blend_pool);
+    mock_pool_client.set_b_rate(&fixture.usdc_token, &(crate::BLEND_RATE_SCALAR * 9 / 10));
+
+    fixture.vault_client.mock_all_auths().redeem_with_min_assets(
+        &to_redeem,
+        &quoted,
+        &fixture.user,
+        &fixture.user,
+        &fixture.user,
+    );
+}
+
+#[test]
+fn test_withdraw_with_max_shares_requires_owner_auth() {
+    let fixture = MockPoolFixture::new();
+    let shares = deposit_as_user(&fixture, 1000_0000000);
+    let operator = Address::generate(&fixture.env);
+    let invoke = MockAuthInvoke {
+        contract: &fixture.vault,
+        fn_name: "withdraw_with_max_shares",
+        args: (
+            500_0000000i128,
+            500_0000000i128,
+            operator.clone(),
+            fixture.user.clone(),
+            operator.clone(),
+        )
+            .into_val(&fixture.env),
+        sub_invokes: &[],
+    };
+
+    fixture.env.set_auths(&[]);
+    let result = fixture
+        .vault_client
+        .mock_auths(&[MockAuth {
+            address: &operator,
+            invoke: &invoke,
+        }])
+        .try_withdraw_with_max_shares(
+            &500_0000000,
+            &500_0000000,
+            &operator,
+            &fixture.user,
+            &operator,
+        );
+    assert!(result.is_err());
+    assert_eq!(fixture.vault_client.balance(&fixture.user), shares);
+    assert_eq!(fixture.usdc_client.balance(&operator), 0);
+}
+
+#[test]
+fn test_redeem_with_min_assets_by_operator_needs_owner_auth() {
+    let fixture = MockPoolFixture::new();
+    let shares = deposit_as_user(&fixture, 1000_0000000);
+    let operator = Address::generate(&fixture.env);
+    let invoke = MockAuthInvoke {
+        contract: &fixture.vault,
+        fn_name: "redeem_with_min_assets",
+        args: (
+            400_0000000i128,
+            400_0000000i128,
+            operator.clone(),
+            fixture.user.clone(),
+            operator.clone(),
+        )
+            .into_val(&fixture.env),
+        sub_invokes: &[],
+    };
+    let operator_auth = MockAuth {
+        address: &operator,
+        invoke: &invoke,
+    };
+    let owner_auth = MockAuth {
+        address: &fixture.user,
+        invoke: &invoke,
+    };
+
+    fixture.env.set_auths(&[]);
+    let result = fixture
+        .vault_client
+        .mock_auths(&[operator_auth.clone()])
+        .try_redeem_with_min_assets(
+            &400_0000000,
+            &400_0000000,
+            &operator,
+            &fixture.user,
+            &operator,
+        );
+    assert!(result.is_err());
+
+    let assets = fixture
+        .vault_client
+        .mock_auths(&[operator_auth, owner_auth])
+        .redeem_with_min_assets(
+            &400_0000000,
+            &400_0000000,
+            &operator,
+            &fixture.user,
+            &operator,
+        );
+    assert_eq!(assets, 400_0000000);
+    assert_eq!(fixture.usdc_client.balance(&operator), 400_0000000);
+    assert_eq!(
+        fixture.vault_client.balance(&fixture.user),
+        shares - 400_0000000
+    );
+}
+
+// ===== Depositor Registry =====
+
+#[test]
+fn test_depositor_registry_pagination() {
+    let fixture = MockPoolFixture::new();
+    let mut users = std::vec::Vec::new();
+    for _ in 0..5 {
+        let user = Address::generate(&fixture.env);
+        fixture.usdc_client.mint(&user, &1_000_0000000);
+        fixture
+            .usdc_client
+            .approve(&user, &fixture.vault, &i128::MAX, &200);
+        fixture
+            .vault_client
+            .mock_all_auths()
+            .deposit(&100_0000000, &user, &user, &user);
+        users.push(user);
+    }
+
+    assert_eq!(fixture.vault_client.get_depositor_count(), 5);
+
+    let first_page = fixture.vault_client.get_depositors(&0, &2);
+    assert_eq!(first_page, vec![&fixture.env, users[0].clone(), users[1].clone()]);
+
+    let last_page = fixture.vault_client.get_depositors(&4, &10);
+    assert_eq!(last_page, vec![&fixture.env, users[4].clone()]);
+
+    let past_end = fixture.vault_client.get_depositors(&9, &10);
+    assert_eq!(past_end.len(), 0);
+
+    let snapshot_page = fixture.vault_client.get_depositors_snapshot_page(&1, &3);
+    assert_eq!(snapshot_page.len(), 3);
+    assert_eq!(snapshot_page.get(users[1].clone()).unwrap(), 100_0000000);
+    assert!(snapshot_page.get(users[0].clone()).is_none());
+}
+
+#[test]
+fn test_depositor_registry_stays_out_of_instance_storage() {
+    let fixture = MockPoolFixture::new();
+    fixture.vault_client.mock_all_auths().deposit(
+        &100_0000000,
+        &fixture.user,
+        &fixture.user,
+        &fixture.user,
+    );
+    fixture.vault_client.mock_all_auths().deposit(
+        &100_0000000,
+        &fixture.user,
+        &fixture.user,
+        &fixture.user,
+    );
+
+    assert_eq!(fixture.vault_client.get_depositor_count(), 1);
+    fixture.env.as_contract(&fixture.vault, || {
+        let storage = fixture.env.storage();
+        assert!(!storage.instance().has(&DataKey::Depositors));
+        assert!(storage
+            .persistent()
+            .has(&DataKey::IsDepositor(fixture.user.clone())));
+        assert_eq!(
+            storage
+                .persistent()
+                .get::<_, Address>(&DataKey::Depositor(0))
+                .unwrap(),
+            fixture.user
+        );
+    });
+}
+
+#[test]
+fn test_migrate_legacy_depositors() {
+    let fixture = MockPoolFixture::new();
+    let legacy_user = Address::generate(&fixture.env);
+
+    fixture.vault_client.mock_all_auths().deposit(
+        &100_0000000,
+        &fixture.user,
+        &fixture.user,
+        &fixture.user,
+    );
+
+    // Seed the pre-registry layout: a Vec in instance storage
+    fixture.env.as_contract(&fixture.vault, || {
+        fixture.env.storage().instance().set(
+            &DataKey::Depositors,
+            &vec![&fixture.env, legacy_user.clone(), fixture.user.clone()],
+        );
+    });
+
+    assert_eq!(fixture.vault_client.migrate_depositors(), 2);
+    assert_eq!(fixture.vault_client.get_depositor_count(), 2);
+    assert_eq!(
+        fixture.vault_client.get_depositors(&0, &10),
+        vec![&fixture.env, fixture.user.clone(), legacy_user.clone()]
+    );
+
+    // Second call is a no-op
+    assert_eq!(fixture.vault_client.migrate_depositors(), 0);
+    fixture.env.as_contract(&fixture.vault, || {
+        assert!(!fixture.env.storage().instance().has(&DataKey::Depositors));
+    });
+}
+
+// ===== Balance Checkpoints =====
+
+#[test]
+fn test_balance_checkpoints_track_mint_transfer_and_burn() {
+    let fixture = MockPoolFixture::new();
+    let recipient = Address::generate(&fixture.env);
+
+    fixture.env.ledger().set_sequence_number(10);
+    fixture.vault_client.mock_all_auths().deposit(
+        &100_0000000,
+        &fixture.user,
+        &fixture.user,
+        &fixture.user,
+    );
+
+    fixture.env.ledger().set_sequence_number(20);
+    fixture
+        .vault_client
+        .mock_all_auths()
+        .transfer(&fixture.user, &recipient, &40_0000000);
+
+    fixture.env.ledger().set_sequence_number(30);
+    fixture.vault_client.mock_all_auths().redeem(
+        &10_0000000,
+        &fixture.user,
+        &fixture.user,
+        &fixture.user,
+    );
+
+    fixture.env.ledger().set_sequence_number(31);
+    let user = &fixture.user;
+    assert_eq!(fixture.vault_client.balance_at(user, &9), 0);
+    assert_eq!(fixture.vault_client.balance_at(user, &10), 100_0000000);
+    assert_eq!(fixture.vault_client.balance_at(user, &19), 100_0000000);
+    assert_eq!(fixture.vault_client.balance_at(user, &20), 60_0000000);
+    assert_eq!(fixture.vault_client.balance_at(user, &30), 50_0000000);
+    assert_eq!(fixture.vault_client.balance_at(&recipient, &19), 0);
+    assert_eq!(fixture.vault_client.balance_at(&recipient, &25), 40_0000000);
+
+    assert_eq!(fixture.vault_client.total_supply_at(&9), 0);
+    assert_eq!(fixture.vault_client.total_supply_at(&15), 100_0000000);
+    assert_eq!(fixture.vault_client.total_supply_at(&20), 100_0000000);
+    assert_eq!(fixture.vault_client.total_supply_at(&30), 90_0000000);
+}
+
+#[test]
+fn test_balance_checkpoints_collapse_within_one_ledger() {
+    let fixture = MockPoolFixture::new();
+    fixture.env.ledger().set_sequence_number(10);
+
+    // Deposit and exit in the same ledger: nothing to game at a later snapshot
+    let shares = fixture.vault_client.mock_all_auths().deposit(
+        &100_0000000,
+        &fixture.user,
+        &fixture.user,
+        &fixture.user,
+    );
+    fixture.vault_client.mock_all_auths().redeem(
+        &shares,
+        &fixture.user,
+        &fixture.user,
+        &fixture.user,
+    );
+
+    fixture.env.ledger().set_sequence_number(11);
+    assert_eq!(fixture.vault_client.num_checkpoints(&fixture.user), 1);
+    assert_eq!(fixture.vault_client.balance_at(&fixture.user, &10), 0);
+    assert_eq!(fixture.vault_client.total_supply_at(&10), 0);
+}
+
+#[test]
+fn test_transfer_from_records_checkpoints() {
+    let fixture = MockPoolFixture::new();
+    let spender = Address::generate(&fixture.env);
+    let recipient = Address::generate(&fixture.env);
+
+    fixture.env.ledger().set_sequence_number(10);
+    fixture.vault_client.mock_all_auths().deposit(
+        &100_0000000,
+        &fixture.user,
+        &fixture.user,
+        &fixture.user,
+    );
+    fixture
+        .vault_client
+        .mock_all_auths()
+        .approve(&fixture.user, &spender, &25_0000000, &1000);
+
+    fixture.env.ledger().set_sequence_number(12);
+    fixture
+        .vault_client
+        .mock_all_auths()
+        .transfer_from(&spender, &fixture.user, &recipient, &25_0000000);
+
+    fixture.env.ledger().set_sequence_number(13);
+    assert_eq!(fixture.vault_client.balance_at(&fixture.user, &11), 100_0000000);
+    assert_eq!(fixture.vault_client.balance_at(&fixture.user, &12), 75_0000000);
+    assert_eq!(fixture.vault_client.balance_at(&recipient, &12), 25_0000000);
+}
+
+#[test]
+#[should_panic(expected = "Error(Contract, #1004)")]
+fn test_balance_at_rejects_current_ledger() {
+    let fixture = MockPoolFixture::new();
+    fixture.env.ledger().set_sequence_number(10);
+    fixture.vault_client.balance_at(&fixture.user, &10);
+}
+
+#[test]
+#[should_panic(expected = "Error(Contract, #1006)")]
+fn test_balance_at_rejects_ledgers_past_lookback() {
+    let fixture = MockPoolFixture::new();
+    fixture.env.ledger().set_sequence_number(10);
+    fixture.vault_client.mock_all_auths().deposit(
+        &100_0000000,
+        &fixture.user,
+        &fixture.user,
+        &fixture.user,
+    );
+
+    fixture
+        .env
+        .ledger()
+        .set_sequence_number(11 + crate::checkpoints::MAX_LOOKBACK);
+    assert_eq!(
+        fixture.vault_client.balance_at(&fixture.user, &11),
+        100_0000000
+    );
+    fixture.vault_client.balance_at(&fixture.user, &10);
+}
+
+#[test]
+fn test_superseded_checkpoints_outlive_lookback() {
+    use soroban_sdk::testutils::storage::Persistent as _;
+    let fixture = MockPoolFixture::new();
+    let recipient = Address::generate(&fixture.env);
+    let lookback = crate::checkpoints::MAX_LOOKBACK;
+
+    fixture.env.ledger().set_sequence_number(10);
+    fixture.vault_client.mock_all_auths().deposit(
+        &100_0000000,
+        &fixture.user,
+        &fixture.user,
+        &fixture.user,
+    );
+    fixture.env.ledger().set_sequence_number(20);
+    fixture
+        .vault_client
+        .mock_all_auths()
+        .transfer(&fixture.user, &recipient, &40_0000000);
+
+    let ttl_of = |index: u32| {
+        fixture.env.as_contract(&fixture.vault, || {
+            fixture
+                .env
+                .storage()
+                .persistent()
+                .get_ttl(&DataKey::BalanceCheckpoint(fixture.user.clone(), index))
+        })
+    };
+    assert_eq!(ttl_of(0), crate::checkpoints::SUPERSEDED_TTL);
+
+    // A lookup at the edge of the window still resolves to the superseded
+    // checkpoint, which outlives the window
+    fixture.env.ledger().set_sequence_number(19 + lookback);
+    assert!(ttl_of(0) > lookback);
+    assert_eq!(
+        fixture.vault_client.balance_at(&fixture.user, &19),
+        100_0000000
+    );
+
+    // Once no lookup can reach it, the next checkpoint stops tracking it
+    fixture.env.ledger().set_sequence_number(21 + lookback);
+    fixture
+        .vault_client
+        .mock_all_auths()
+        .transfer(&fixture.user, &recipient, &10_0000000);
+    fixture.env.as_contract(&fixture.vault, || {
+        let range = crate::checkpoints::range_of(&fixture.env, &fixture.user);
+        assert_eq!(range.start, 1);
+        assert_eq!(range.end, 3);
+    });
+    fixture.env.ledger().set_sequence_number(22 + lookback);
+    assert_eq!(
+        fixture.vault_client.balance_at(&fixture.user, &22),
+        60_0000000
+    );
+    assert_eq!(
+        fixture
+            .vault_client
+            .balance_at(&fixture.user, &(21 + lookback)),
+        50_0000000
+    );
+}
+
+// ===== Participation Points =====
+
+#[test]
+fn test_points_accrue_share_seconds() {
+    let fixture = MockPoolFixture::new();
+    let user2 = Address::generate(&fixture.env);
+    fixture.usdc_client.mint(&user2, &1_000_0000000);
+    fixture
+        .usdc_client
+        .approve(&user2, &fixture.vault, &i128::MAX, &200);
+    let shares = 100_0000000;
+
+    fixture.env.ledger().set_timestamp(1_000);
+    fixture.vault_client.mock_all_auths().deposit(
+        &shares,
+        &fixture.user,
+        &fixture.user,
+        &fixture.user,
+    );
+
+    fixture.env.ledger().set_timestamp(1_100);
+    fixture
+        .vault_client
+        .mock_all_auths()
+        .deposit(&shares, &user2, &user2, &user2);
+
+    fixture.env.ledger().set_timestamp(1_200);
+    fixture
+        .vault_client
+        .mock_all_auths()
+        .transfer(&fixture.user, &user2, &(shares / 2));
+
+    fixture.env.ledger().set_timestamp(1_300);
+    let user_points = shares * 200 + (shares / 2) * 100;
+    let user2_points = shares * 100 + (shares + shares / 2) * 100;
+    assert_eq!(fixture.vault_client.points_of(&fixture.user), user_points);
+    assert_eq!(fixture.vault_client.points_of(&user2), user2_points);
+    assert_eq!(fixture.vault_client.total_points(), user_points + user2_points);
+}
+
+#[test]
+fn test_points_reset_epoch() {
+    let fixture = MockPoolFixture::new();
+    let shares = 100_0000000;
+
+    fixture.env.ledger().set_timestamp(1_000);
+    fixture.vault_client.mock_all_auths().deposit(
+        &shares,
+        &fixture.user,
+        &fixture.user,
+        &fixture.user,
+    );
+
+    fixture.env.ledger().set_timestamp(1_500);
+    assert_eq!(fixture.vault_client.points_of(&fixture.user), shares * 500);
+
+    let epoch = fixture.vault_client.mock_all_auths().reset_epoch();
+    assert_eq!(epoch, 1);
+    assert_eq!(fixture.vault_client.points_epoch().start, 1_500);
+    assert_eq!(fixture.vault_client.points_of(&fixture.user), 0);
+    assert_eq!(fixture.vault_client.total_points(), 0);
+
+    // Balance carried into the new epoch keeps accruing from its start
+    fixture.env.ledger().set_timestamp(1_600);
+    assert_eq!(fixture.vault_client.points_of(&fixture.user), shares * 100);
+    assert_eq!(fixture.vault_client.total_points(), shares * 100);
+
+    fixture.vault_client.mock_all_auths().redeem(
+        &shares,
+        &fixture.user,
+        &fixture.user,
+        &fixture.user,
+    );
+    fixture.env.ledger().set_timestamp(1_700);
+    assert_eq!(fixture.vault_client.points_of(&fixture.user), shares * 100);
+}
+
+#[test]
+fn test_reset_epoch_requires_owner() {
+    let fixture = MockPoolFixture::new();
+    fixture.env.set_auths(&[]);
+    assert!(fixture.vault_client.try_reset_epoch().is_err());
+
+    let epoch = fixture
+        .vault_client
+        .mock_auths(&[MockAuth {
+            address: &fixture.admin,
+            invoke: &MockAuthInvoke {
+                contract: &fixture.vault,
+                fn_name: &"reset_epoch",
+                args: vec![&fixture.env],
+                sub_invokes: &[],
+            },
+        }])
+        .reset_epoch();
+    assert_eq!(epoch, 1);
+}
+
+// ===== Storage TTL Management =====
+
+#[test]
+fn test_user_interactions_extend_ttl() {
+    use soroban_sdk::testutils::storage::{Instance as _, Persistent as _};
+    let fixture = MockPoolFixture::new();
+    fixture.vault_client.mock_all_auths().deposit(
+        &100_0000000,
+        &fixture.user,
+        &fixture.user,
+        &fixture.user,
+    );
+
+    fixture.env.as_contract(&fixture.vault, || {
+        let storage = fixture.env.storage();
+        assert_eq!(storage.instance().get_ttl(), crate::ttl::INSTANCE_EXTEND_TO);
+        let balance_key = stellar_tokens::fungible::StorageKey::Balance(fixture.user.clone());
+        assert!(storage.persistent().get_ttl(&balance_key) >= crate::ttl::PERSISTENT_TTL_THRESHOLD);
+        assert_eq!(
+            storage
+                .persistent()
+                .get_ttl(&DataKey::IsDepositor(fixture.user.clone())),
+            crate::ttl::PERSISTENT_EXTEND_TO
+        );
+        assert_eq!(
+            storage.persistent().get_ttl(&DataKey::Depositor(0)),
+            crate::ttl::PERSISTENT_EXTEND_TO
+        );
+    });
+}
+
+#[test]
+fn test_bump_keeps_vault_alive_past_default_ttl() {
+    use soroban_sdk::testutils::storage::{Instance as _, Persistent as _};
+    let fixture = MockPoolFixture::new();
+    let shares = fixture.vault_client.mock_all_auths().deposit(
+        &100_0000000,
+        &fixture.user,
+        &fixture.user,
+        &fixture.user,
+    );
+    fixture
+        .vault_client
+        .mock_all_auths()
+        .request_redeem(&10_0000000, &fixture.user, &fixture.user);
+
+    // Bump every 25 days, before the instance drops out, until the vault is
+    // well past the TTL its persistent entries got when they were written
+    let start = fixture.env.ledger().sequence();
+    for period in 1..=3 {
+        fixture
+            .env
+            .ledger()
+            .set_sequence_number(start + period * 25 * crate::ttl::DAY_IN_LEDGERS);
+        fixture.vault_client.bump();
+        fixture.vault_client.bump_account(&fixture.user);
+    }
+    assert!(fixture.env.ledger().sequence() - start > crate::ttl::PERSISTENT_EXTEND_TO);
+
+    fixture.env.as_contract(&fixture.vault, || {
+        let storage = fixture.env.storage();
+        assert_eq!(storage.instance().get_ttl(), crate::ttl::INSTANCE_EXTEND_TO);
+        let supply_checkpoint = crate::checkpoints::supply_range(&fixture.env).end - 1;
+        let balance_checkpoint = crate::checkpoints::range_of(&fixture.env, &fixture.user).end - 1;
+        let keys = [
+            stellar_tokens::fungible::StorageKey::Balance(fixture.user.clone())
+                .into_val(&fixture.env),
+            DataKey::SupplyCheckpoint(supply_checkpoint).into_val(&fixture.env),
+            DataKey::BalanceCheckpoint(fixture.user.clone(), balance_checkpoint)
+                .into_val(&fixture.env),
+            DataKey::Points(fixture.user.clone()).into_val(&fixture.env),
+            DataKey::RedeemRequest(0).into_val(&fixture.env),
+        ];
+        for key in keys {
+            assert!(storage.persistent().get_ttl(&key) >= crate::ttl::PERSISTENT_TTL_THRESHOLD);
+        }
+    });
+
+    // The vault still serves its holders
+    fixture.vault_client.mock_all_auths().deposit(
+        &50_0000000,
+        &fixture.user,
+        &fixture.user,
+        &fixture.user,
+    );
+    let remaining = fixture.vault_client.balance(&fixture.user);
+    assert_eq!(remaining, shares + 40_0000000);
+    let assets = fixture.vault_client.mock_all_auths().redeem(
+        &remaining,
+        &fixture.user,
+        &fixture.user,
+        &fixture.user,
+    );
+    assert_eq!(assets, 140_0000000);
+    assert_eq!(fixture.vault_client.balance(&fixture.user), 0);
+    assert_eq!(fixture.vault_client.fulfill_redeem(&1), 1);
+}
+
+#[test]
+fn test_set_ttl_config_applies_to_bumps() {
+    use soroban_sdk::testutils::storage::{Instance as _, Persistent as _};
+    let fixture = MockPoolFixture::new();
+    fixture.vault_client.mock_all_auths().deposit(
+        &100_0000000,
+        &fixture.user,
+        &fixture.user,
+        &fixture.user,
+    );
+
+    let config = TtlConfig {
+        instance_threshold: 90 * crate::ttl::DAY_IN_LEDGERS,
+        instance_extend_to: 90 * crate::ttl::DAY_IN_LEDGERS,
+        persistent_threshold: 120 * crate::ttl::DAY_IN_LEDGERS,
+        persistent_extend_to: 120 * crate::ttl::DAY_IN_LEDGERS,
+    };
+    fixture.vault_client.mock_all_auths().set_ttl_config(&config);
+    assert_eq!(fixture.vault_client.get_ttl_config(), config);
+
+    fixture.vault_client.bump_account(&fixture.user);
+    fixture.env.as_contract(&fixture.vault, || {
+        let storage = fixture.env.storage();
+        assert_eq!(storage.instance().get_ttl(), config.instance_extend_to);
+        let balance_key = stellar_tokens::fungible::StorageKey::Balance(fixture.user.clone());
+        assert_eq!(
+            storage.persistent().get_ttl(&balance_key),
+            config.persistent_extend_to
+        );
+        assert_eq!(
+            storage
+                .persistent()
+                .get_ttl(&DataKey::Points(fixture.user.clone())),
+            config.persistent_extend_to
+        );
+    });
+}
+
+#[test]
+#[should_panic(expected = "Invalid TTL config")]
+fn test_set_ttl_config_rejects_threshold_above_extension() {
+    let fixture = MockPoolFixture::new();
+    fixture.vault_client.mock_all_auths().set_ttl_config(&TtlConfig {
+        instance_threshold: 10,
+        instance_extend_to: 5,
+        persistent_threshold: 10,
+        persistent_extend_to: 20,
+    });
+}
+
+// ===== Multi-Pool Allocation =====
+
+fn deposit_as_user(fixture: &MockPoolFixture, assets: i128) -> i128 {
+    fixture
+        .vault_client
+        .deposit(&assets, &fixture.user, &fixture.user, &fixture.user)
+}
+
+#[test]
+fn test_initialize_registers_single_full_weight_pool() {
+    let fixture = MockPoolFixture::new();
+    let strategies = fixture.vault_client.get_strategies();
+    assert_eq!(strategies.len(), 1);
+    let primary = strategies.get(0).unwrap();
+    assert_eq!(primary.strategy, fixture.blend_pool);
+    assert_eq!(
+        primary.kind,
+        StrategyKind::Blend(BlendReserve {
+            reserve_index: 0,
+            reward_token_id: 1,
+        })
+    );
+    assert_eq!(primary.weight, 10_000);
+    assert_eq!(primary.max_debt, i128::MAX);
+}
+
+#[test]
+fn test_deposits_route_to_most_underweight_pool() {
+    let fixture = MockPoolFixture::new();
+    deposit_as_user(&fixture, 100_0000000);
+
+    let second_pool = fixture.add_pool();
+    assert_eq!(
+        fixture.vault_client.get_strategies().get(1).unwrap().weight,
+        0
+    );
+    fixture
+        .vault_client
+        .set_weights(&vec![&fixture.env, 5_000u32, 5_000u32]);
+
+    deposit_as_user(&fixture, 60_0000000);
+    assert_eq!(
+        fixture.usdc_client.balance(&fixture.blend_pool),
+        100_0000000
+    );
+    assert_eq!(fixture.usdc_client.balance(&second_pool), 60_0000000);
+
+    deposit_as_user(&fixture, 40_0000000);
+    assert_eq!(
+        fixture.vault_client.get_strategy_balances(),
+        vec![&fixture.env, 100_0000000i128, 100_0000000i128]
+    );
+    assert_eq!(fixture.vault_client.total_assets(), 200_0000000);
+}
+
+#[test]
+fn test_withdrawals_draw_from_most_overweight_pool() {
+    let fixture = MockPoolFixture::new();
+    fixture.add_pool();
+    fixture
+        .vault_client
+        .set_weights(&vec![&fixture.env, 5_000u32, 5_000u32]);
+    deposit_as_user(&fixture, 100_0000000);
+    deposit_as_user(&fixture, 100_0000000);
+
+    fixture
+        .vault_client
+        .set_weights(&vec![&fixture.env, 8_000u32, 2_000u32]);
+    let before = fixture.usdc_client.balance(&fixture.user);
+    fixture
+        .vault_client
+        .withdraw(&50_0000000, &fixture.user, &fixture.user, &fixture.user);
+
+    assert_eq!(
+        fixture.usdc_client.balance(&fixture.user),
+        before + 50_0000000
+    );
+    assert_eq!(
+        fixture.vault_client.get_strategy_balances(),
+        vec![&fixture.env, 100_0000000i128, 50_0000000i128]
+    );
+}
+
+#[test]
+fn test_withdrawal_splits_across_pools_when_liquidity_is_short() {
+    let fixture = MockPoolFixture::new();
+    let second_pool = fixture.add_pool();
+    fixture
+        .vault_client
+        .set_weights(&vec![&fixture.env, 5_000u32, 5_000u32]);
+    deposit_as_user(&fixture, 100_0000000);
+    deposit_as_user(&fixture, 100_0000000);
+
+    // Borrowers take most of the second pool's liquidity
+    let borrower = Address::generate(&fixture.env);
+    fixture
+        .usdc_client
+        .transfer(&second_pool, &borrower, &90_0000000);
+
+    let before = fixture.usdc_client.balance(&fixture.user);
+    fixture
+        .vault_client
+        .withdraw(&105_0000000, &fixture.user, &fixture.user, &fixture.user);
+
+    assert_eq!(
+        fixture.usdc_client.balance(&fixture.user),
+        before + 105_0000000
+    );
+    assert_eq!(
+        fixture.vault_client.get_strategy_balances(),
+        vec![&fixture.env, 0i128, 95_0000000i128]
+    );
+}
+
+#[test]
+#[should_panic(expected = "Insufficient strategy liquidity")]
+fn test_withdrawal_panics_when_pools_lack_liquidity() {
+    let fixture = MockPoolFixture::new();
+    let second_pool = fixture.add_pool();
+    fixture
+        .vault_client
+        .set_weights(&vec![&fixture.env, 5_000u32, 5_000u32]);
+    deposit_as_user(&fixture, 100_0000000);
+    deposit_as_user(&fixture, 100_0000000);
+
+    let borrower = Address::generate(&fixture.env);
+    fixture
+        .usdc_client
+        .transfer(&second_pool, &borrower, &90_0000000);
+
+    fixture
+        .vault_client
+        .withdraw(&150_0000000, &fixture.user, &fixture.user, &fixture.user);
+}
+
+#[test]
+fn test_total_assets_sums_every_pool() {
+    let fixture = MockPoolFixture::new();
+    let second_pool = fixture.add_pool();
+    fixture
+        .vault_client
+        .set_weights(&vec![&fixture.env, 5_000u32, 5_000u32]);
+    deposit_as_user(&fixture, 100_0000000);
+    deposit_as_user(&fixture, 100_0000000);
+
+    // Interest accrues in the second pool only
+    RealisticMockBlendPoolClient::new(&fixture.env, &second_pool)
+        .set_b_rate(&fixture.usdc_token, &1_100_000_000_000);
+
+    assert_eq!(
+        fixture.vault_client.get_strategy_balances(),
+        vec![&fixture.env, 100_0000000i128, 110_0000000i128]
+    );
+    assert_eq!(fixture.vault_client.total_assets(), 210_0000000);
+}
+
+#[test]
+fn test_owner_rebalances_and_removes_pool() {
+    let fixture = MockPoolFixture::new();
+    let second_pool = fixture.add_pool();
+    fixture
+        .vault_client
+        .set_weights(&vec![&fixture.env, 5_000u32, 5_000u32]);
+    deposit_as_user(&fixture, 100_0000000);
+    deposit_as_user(&fixture, 100_0000000);
+
+    fixture
+        .vault_client
+        .set_weights(&vec![&fixture.env, 10_000u32, 0u32]);
+    fixture
+        .vault_client
+        .rebalance(&second_pool, &fixture.blend_pool, &100_0000000);
+    assert_eq!(
+        fixture.vault_client.get_strategy_balances(),
+        vec![&fixture.env, 200_0000000i128, 0i128]
+    );
+    assert_eq!(fixture.vault_client.total_assets(), 200_0000000);
+
+    fixture.vault_client.remove_strategy(&second_pool);
+    assert_eq!(fixture.vault_client.get_strategies().len(), 1);
+}
+
+#[test]
+#[should_panic(expected = "Strategy still in use")]
+fn test_remove_strategy_rejects_pool_with_assets() {
+    let fixture = MockPoolFixture::new();
+    let second_pool = fixture.add_pool();
+    fixture
+        .vault_client
+        .set_weights(&vec![&fixture.env, 0u32, 10_000u32]);
+    deposit_as_user(&fixture, 100_0000000);
+    fixture
+        .vault_client
+        .set_weights(&vec![&fixture.env, 10_000u32, 0u32]);
+    fixture.vault_client.remove_strategy(&second_pool);
+}
+
+#[test]
+#[should_panic(expected = "Strategy weights must sum to 10000")]
+fn test_set_weights_rejects_bad_sum() {
+    let fixture = MockPoolFixture::new();
+    fixture.add_pool();
+    fixture
+        .vault_client
+        .set_weights(&vec![&fixture.env, 5_000u32, 4_000u32]);
+}
+
+#[test]
+#[should_panic(expected = "Strategy already added")]
+fn test_add_strategy_rejects_duplicate_pool() {
+    let fixture = MockPoolFixture::new();
+    fixture
+        .vault_client
+        .add_strategy(&fixture.blend_pool, &true, &i128::MAX);
+}
+
+// ===== Strategies =====
+
+#[test]
+fn test_add_strategy_registers_contract_without_weight() {
+    let fixture = MockPoolFixture::new();
+    let strategy = fixture.register_strategy(&fixture.usdc_token);
+    fixture
+        .vault_client
+        .add_strategy(&strategy, &false, &500_0000000);
+    deposit_as_user(&fixture, 1000_0000000);
+    let params = fixture.vault_client.get_strategies().get(1).unwrap();
+    assert_eq!(params.strategy, strategy);
+    assert_eq!(params.kind, StrategyKind::Contract);
+    assert_eq!(params.weight, 0);
+    assert_eq!(params.max_debt, 500_0000000);
+    assert_eq!(params.current_debt, 0);
+    assert_eq!(fixture.usdc_client.balance(&strategy), 0);
+}
+
+#[test]
+fn test_rebalance_lends_to_strategy() {
+    let fixture = MockPoolFixture::new();
+    let strategy = fixture.register_strategy(&fixture.usdc_token);
+    fixture
+        .vault_client
+        .add_strategy(&strategy, &false, &500_0000000);
+    deposit_as_user(&fixture, 1000_0000000);
+
+    fixture
+        .vault_client
+        .rebalance(&fixture.blend_pool, &strategy, &400_0000000);
+    assert_eq!(fixture.usdc_client.balance(&strategy), 400_0000000);
+    assert_eq!(
+        fixture.usdc_client.balance(&fixture.blend_pool),
+        600_0000000
+    );
+    assert_eq!(fixture.vault_client.total_assets(), 1000_0000000);
+
+    let strategies = fixture.vault_client.get_strategies();
+    assert_eq!(strategies.get(0).unwrap().current_debt, 600_0000000);
+    assert_eq!(strategies.get(1).unwrap().current_debt, 400_0000000);
+}
+
+#[test]
+fn test_rebalance_recalls_from_strategy() {
+    let fixture = MockPoolFixture::new();
+    let strategy = fixture.register_strategy(&fixture.usdc_token);
+    fixture
+        .vault_client
+        .add_strategy(&strategy, &false, &500_0000000);
+    deposit_as_user(&fixture, 1000_0000000);
+    fixture
+        .vault_client
+        .rebalance(&fixture.blend_pool, &strategy, &400_0000000);
+
+    fixture
+        .vault_client
+        .rebalance(&strategy, &fixture.blend_pool, &300_0000000);
+    assert_eq!(fixture.usdc_client.balance(&strategy), 100_0000000);
+    assert_eq!(
+        fixture.usdc_client.balance(&fixture.blend_pool),
+        900_0000000
+    );
+    assert_eq!(
+        fixture
+            .vault_client
+            .get_strategies()
+            .get(1)
+            .unwrap()
+            .current_debt,
+        100_0000000
+    );
+    assert_eq!(fixture.vault_client.total_assets(), 1000_0000000);
+}
+
+#[test]
+#[should_panic(expected = "Rebalance exceeds strategy max debt")]
+fn test_rebalance_respects_max_debt() {
+    let fixture = MockPoolFixture::new();
+    let strategy = fixture.register_strategy(&fixture.usdc_token);
+    fixture
+        .vault_client
+        .add_strategy(&strategy, &false, &500_0000000);
+    deposit_as_user(&fixture, 1000_0000000);
+    fixture
+        .vault_client
+        .rebalance(&fixture.blend_pool, &strategy, &600_0000000);
+}
+
+#[test]
+fn test_deposits_route_to_weighted_strategy_within_max_debt() {
+    let fixture = MockPoolFixture::new();
+    let strategy = fixture.register_strategy(&fixture.usdc_token);
+    fixture
+        .vault_client
+        .add_strategy(&strategy, &false, &500_0000000);
+    deposit_as_user(&fixture, 1000_0000000);
+    fixture
+        .vault_client
+        .set_weights(&vec![&fixture.env, 5_000u32, 5_000u32]);
+
+    deposit_as_user(&fixture, 400_0000000);
+    assert_eq!(fixture.usdc_client.balance(&strategy), 400_0000000);
+
+    // The strategy is furthest below its weight but only has 100 of debt room
+    deposit_as_user(&fixture, 200_0000000);
+    assert_eq!(
+        fixture.vault_client.get_strategy_balances(),
+        vec![&fixture.env, 1200_0000000i128, 400_0000000i128]
+    );
+}
+
+#[test]
+fn test_strategy_yield_counts_toward_total_assets() {
+    let fixture = MockPoolFixture::new();
+    let strategy = fixture.register_strategy(&fixture.usdc_token);
+    fixture
+        .vault_client
+        .add_strategy(&strategy, &false, &500_0000000);
+    deposit_as_user(&fixture, 1000_0000000);
+    fixture
+        .vault_client
+        .rebalance(&fixture.blend_pool, &strategy, &400_0000000);
+
+    fixture.usdc_client.mint(&strategy, &40_0000000);
+
+    assert_eq!(fixture.vault_client.total_assets(), 1040_0000000);
+}
+
+#[test]
+fn test_withdraw_draws_on_overweight_strategy_first() {
+    let fixture = MockPoolFixture::new();
+    let strategy = fixture.register_strategy(&fixture.usdc_token);
+    fixture
+        .vault_client
+        .add_strategy(&strategy, &false, &500_0000000);
+    deposit_as_user(&fixture, 1000_0000000);
+    fixture
+        .vault_client
+        .rebalance(&fixture.blend_pool, &strategy, &400_0000000);
+
+    let before = fixture.usdc_client.balance(&fixture.user);
+    fixture
+        .vault_client
+        .withdraw(&300_0000000, &fixture.user, &fixture.user, &fixture.user);
+
+    assert_eq!(
+        fixture.usdc_client.balance(&fixture.user),
+        before + 300_0000000
+    );
+    assert_eq!(fixture.usdc_client.balance(&strategy), 100_0000000);
+    assert_eq!(
+        fixture
+            .vault_client
+            .get_strategies()
+            .get(1)
+            .unwrap()
+            .current_debt,
+        100_0000000
+    );
+}
+
+#[test]
+fn test_withdraw_splits_across_strategy_and_pool() {
+    let fixture = MockPoolFixture::new();
+    let strategy = fixture.register_strategy(&fixture.usdc_token);
+    fixture
+        .vault_client
+        .add_strategy(&strategy, &false, &500_0000000);
+    deposit_as_user(&fixture, 1000_0000000);
+    fixture
+        .vault_client
+        .rebalance(&fixture.blend_pool, &strategy, &400_0000000);
+
+    // Borrowers leave 100 in the Blend pool and the strategy can free up 50
+    let borrower = Address::generate(&fixture.env);
+    fixture
+        .usdc_client
+        .transfer(&fixture.blend_pool, &borrower, &500_0000000);
+    MockStrategyClient::new(&fixture.env, &strategy).set_locked(&350_0000000);
+
+    let before = fixture.usdc_client.balance(&fixture.user);
+    fixture
+        .vault_client
+        .withdraw(&120_0000000, &fixture.user, &fixture.user, &fixture.user);
+
+    assert_eq!(
+        fixture.usdc_client.balance(&fixture.user),
+        before + 120_0000000
+    );
+    assert_eq!(fixture.usdc_client.balance(&strategy), 350_0000000);
+    assert_eq!(fixture.usdc_client.balance(&fixture.blend_pool), 30_0000000);
+}
+
+#[test]
+fn test_compound_harvests_strategies() {
+    let fixture = MockPoolFixture::new();
+    let strategy = fixture.register_strategy(&fixture.usdc_token);
+    fixture
+        .vault_client
+        .add_strategy(&strategy, &false, &500_0000000);
+    deposit_as_user(&fixture, 1000_0000000);
+    let strategy_client = MockStrategyClient::new(&fixture.env, &strategy);
+    strategy_client.set_reward_token(&fixture.blnd_token);
+    MockTokenClient::new(&fixture.env, &fixture.blnd_token).mint(&strategy, &500_0000000);
+    // 1000 BLND from the pool and 500 from the strategy, swapped 1:1
+    fixture.usdc_client.mint(&fixture.comet_pool, &1500_0000000);
+    fixture.warp(86_400);
+
+    let assets_received = fixture.vault_client.compound(&fixture.user);
+    assert_eq!(assets_received, 1500_0000000);
+    assert_eq!(fixture.vault_client.total_assets(), 2500_0000000);
+}
+
+#[test]
+fn test_remove_strategy_after_debt_is_repaid() {
+    let fixture = MockPoolFixture::new();
+    let strategy = fixture.register_strategy(&fixture.usdc_token);
+    fixture
+        .vault_client
+        .add_strategy(&strategy, &false, &500_0000000);
+    deposit_as_user(&fixture, 1000_0000000);
+    fixture
+        .vault_client
+        .rebalance(&fixture.blend_pool, &strategy, &400_0000000);
+    fixture
+        .vault_client
+        .rebalance(&strategy, &fixture.blend_pool, &400_0000000);
+
+    fixture.vault_client.remove_strategy(&strategy);
+    assert_eq!(fixture.vault_client.get_strategies().len(), 1);
+    assert_eq!(fixture.vault_client.total_assets(), 1000_0000000);
+}
+
+#[test]
+#[should_panic(expected = "Strategy still in use")]
+fn test_remove_strategy_rejects_strategy_with_assets() {
+    let fixture = MockPoolFixture::new();
+    let strategy = fixture.register_strategy(&fixture.usdc_token);
+    fixture
+        .vault_client
+        .add_strategy(&strategy, &false, &500_0000000);
+    deposit_as_user(&fixture, 1000_0000000);
+    fixture
+        .vault_client
+        .rebalance(&fixture.blend_pool, &strategy, &400_0000000);
+    fixture.vault_client.remove_strategy(&strategy);
+}
+
+#[test]
+#[should_panic(expected = "Strategy asset mismatch")]
+fn test_add_strategy_rejects_other_asset() {
+    let fixture = MockPoolFixture::new();
+    let strategy = fixture.register_strategy(&fixture.blnd_token);
+    fixture
+        .vault_client
+        .add_strategy(&strategy, &false, &500_0000000);
+}
+
+#[test]
+fn test_blend_strategy_supplies_through_its_pool() {
+    let fixture = MockPoolFixture::new();
+    deposit_as_user(&fixture, 1000_0000000);
+    let (strategy, pool) = fixture.add_blend_strategy(500_0000000);
+    assert_eq!(
+        fixture.vault_client.get_strategies().get(1).unwrap().kind,
+        StrategyKind::Contract
+    );
+
+    fixture
+        .vault_client
+        .rebalance(&fixture.blend_pool, &strategy, &400_0000000);
+    assert_eq!(fixture.usdc_client.balance(&pool), 400_0000000);
+    assert_eq!(fixture.usdc_client.balance(&strategy), 0);
+    assert_eq!(fixture.vault_client.total_assets(), 1000_0000000);
+
+    // Interest on the strategy's collateral accrues to the vault
+    RealisticMockBlendPoolClient::new(&fixture.env, &pool)
+        .set_b_rate(&fixture.usdc_token, &1_100_000_000_000);
+    assert_eq!(fixture.vault_client.total_assets(), 1040_0000000);
+
+    fixture
+        .vault_client
+        .rebalance(&strategy, &fixture.blend_pool, &300_0000000);
+    assert_eq!(fixture.usdc_client.balance(&pool), 100_0000000);
+    assert_eq!(
+        fixture.usdc_client.balance(&fixture.blend_pool),
+        900_0000000
+    );
+}
+
+#[test]
+fn test_compound_harvests_blend_strategy() {
+    let fixture = MockPoolFixture::new();
+    deposit_as_user(&fixture, 1000_0000000);
+    fixture.add_blend_strategy(500_0000000);
+    // 1000 BLND from each pool, swapped 1:1
+    fixture.usdc_client.mint(&fixture.comet_pool, &2000_0000000);
+    fixture.warp(86_400);
+
+    let assets_received = fixture.vault_client.compound(&fixture.user);
+    assert_eq!(assets_received, 2000_0000000);
+    assert_eq!(fixture.vault_client.total_assets(), 3000_0000000);
+}
+
+// ===== Idle Liquidity Buffer =====
+
+#[test]
+fn test_buffer_disabled_by_default() {
+    let fixture = MockPoolFixture::new();
+    assert_eq!(fixture.vault_client.get_idle_ratio(), 0);
+    deposit_as_user(&fixture, 1000_0000000);
+    assert_eq!(fixture.vault_client.idle_assets(), 0);
+    assert_eq!(
+        fixture.usdc_client.balance(&fixture.blend_pool),
+        1000_0000000
+    );
+}
+
+#[test]
+fn test_deposit_keeps_target_buffer_idle() {
+    let fixture = MockPoolFixture::new();
+    fixture.vault_client.set_idle_ratio(&200);
+    deposit_as_user(&fixture, 1000_0000000);
+    assert_eq!(fixture.vault_client.idle_assets(), 20_0000000);
+    assert_eq!(fixture.usdc_client.balance(&fixture.vault), 20_0000000);
+    assert_eq!(
+        fixture.usdc_client.balance(&fixture.blend_pool),
+        980_0000000
+    );
+    assert_eq!(fixture.vault_client.total_assets(), 1000_0000000);
+
+    // A small deposit fits in the buffer without touching Blend
+    deposit_as_user(&fixture, 0_1000000);
+    assert_eq!(fixture.vault_client.idle_assets(), 20_1000000);
+    assert_eq!(
+        fixture.usdc_client.balance(&fixture.blend_pool),
+        980_0000000
+    );
+}
+
+#[test]
+fn test_small_withdrawal_served_from_buffer() {
+    let fixture = MockPoolFixture::new();
+    fixture.vault_client.set_idle_ratio(&200);
+    deposit_as_user(&fixture, 1000_0000000);
+    let before = fixture.usdc_client.balance(&fixture.user);
+
+    fixture
+        .vault_client
+        .withdraw(&10_0000000, &fixture.user, &fixture.user, &fixture.user);
+
+    assert_eq!(
+        fixture.usdc_client.balance(&fixture.user),
+        before + 10_0000000
+    );
+    assert_eq!(fixture.vault_client.idle_assets(), 10_0000000);
+    assert_eq!(
+        fixture.usdc_client.balance(&fixture.blend_pool),
+        980_0000000
+    );
+    assert_eq!(fixture.vault_client.total_assets(), 990_0000000);
+}
+
+#[test]
+fn test_large_withdrawal_goes_to_blend() {
+    let fixture = MockPoolFixture::new();
+    fixture.vault_client.set_idle_ratio(&200);
+    deposit_as_user(&fixture, 1000_0000000);
+    let before = fixture.usdc_client.balance(&fixture.user);
+
+    fixture
+        .vault_client
+        .withdraw(&100_0000000, &fixture.user, &fixture.user, &fixture.user);
+
+    assert_eq!(
+        fixture.usdc_client.balance(&fixture.user),
+        before + 100_0000000
+    );
+    assert_eq!(fixture.vault_client.idle_assets(), 20_0000000);
+    assert_eq!(
+        fixture.usdc_client.balance(&fixture.blend_pool),
+        880_0000000
+    );
+}
+
+#[test]
+fn test_rebalance_buffer_tops_up_from_blend() {
+    let fixture = MockPoolFixture::new();
+    fixture.vault_client.set_idle_ratio(&200);
+    deposit_as_user(&fixture, 1000_0000000);
+    fixture
+        .vault_client
+        .withdraw(&10_0000000, &fixture.user, &fixture.user, &fixture.user);
+
+    // Anyone can call the keeper entrypoint
+    let idle = fixture.vault_client.rebalance_buffer();
+    assert_eq!(idle, 19_8000000);
+    assert_eq!(
+        fixture.usdc_client.balance(&fixture.blend_pool),
+        970_2000000
+    );
+    assert_eq!(fixture.vault_client.total_assets(), 990_0000000);
+}
+
+#[test]
+fn test_rebalance_buffer_pushes_excess_into_blend() {
+    let fixture = MockPoolFixture::new();
+    fixture.vault_client.set_idle_ratio(&200);
+    deposit_as_user(&fixture, 1000_0000000);
+    fixture.vault_client.set_idle_ratio(&0);
+
+    let idle = fixture.vault_client.rebalance_buffer();
+    assert_eq!(idle, 0);
+    assert_eq!(
+        fixture.usdc_client.balance(&fixture.blend_pool),
+        1000_0000000
+    );
+    assert_eq!(fixture.vault_client.total_assets(), 1000_0000000);
+}
+
+#[test]
+fn test_donations_do_not_count_as_idle_assets() {
+    let fixture = MockPoolFixture::new();
+    fixture.vault_client.set_idle_ratio(&200);
+    deposit_as_user(&fixture, 1000_0000000);
+    let share_value = fixture.vault_client.convert_to_assets(&1_0000000);
+
+    fixture
+        .usdc_client
+        .transfer(&fixture.user, &fixture.vault, &1000_0000000);
+
+    assert_eq!(fixture.vault_client.idle_assets(), 20_0000000);
+    assert_eq!(fixture.vault_client.total_assets(), 1000_0000000);
+    assert_eq!(
+        fixture.vault_client.convert_to_assets(&1_0000000),
+        share_value
+    );
+
+    // The keeper only moves what the vault booked as idle
+    fixture.vault_client.set_idle_ratio(&0);
+    assert_eq!(fixture.vault_client.rebalance_buffer(), 0);
+    assert_eq!(
+        fixture.usdc_client.balance(&fixture.blend_pool),
+        1000_0000000
+    );
+    assert_eq!(fixture.usdc_client.balance(&fixture.vault), 1000_0000000);
+}
+
+#[test]
+#[should_panic(expected = "Invalid idle ratio")]
+fn test_set_idle_ratio_rejects_above_full() {
+    let fixture = MockPoolFixture::new();
+    fixture.vault_client.set_idle_ratio(&10_001);
+}
+
+// ===== Queued Redemptions =====
+
+#[test]
+fn test_request_redeem_escrows_shares() {
+    let fixture = MockPoolFixture::new();
+    let shares = deposit_as_user(&fixture, 1000_0000000);
+
+    let id = fixture
+        .vault_client
+        .request_redeem(&400_0000000, &fixture.user, &fixture.user);
+    assert_eq!(id, 0);
+    assert_eq!(
+        fixture.vault_client.balance(&fixture.user),
+        shares - 400_0000000
+    );
+    assert_eq!(fixture.vault_client.balance(&fixture.vault), 400_0000000);
+    assert_eq!(fixture.vault_client.redeem_queue_length(), 1);
+
+    let pending = fixture.vault_client.pending_redeem(&fixture.user);
+    assert_eq!(pending.shares, 400_0000000);
+    assert_eq!(pending.assets, 400_0000000);
+    assert_eq!(fixture.vault_client.total_queued_redeem(), pending);
+
+    let request = fixture.vault_client.get_redeem_request(&0).unwrap();
+    assert_eq!(request.owner, fixture.user);
+    assert_eq!(request.receiver, fixture.user);
+    assert_eq!(request.shares, 400_0000000);
+}
+
+#[test]
+fn test_request_redeem_requires_owner_auth() {
+    let fixture = MockPoolFixture::new();
+    deposit_as_user(&fixture, 1000_0000000);
+
+    fixture.env.set_auths(&[]);
+    let result =
+        fixture
+            .vault_client
+            .try_request_redeem(&400_0000000, &fixture.user, &fixture.user);
+    assert!(result.is_err());
+    assert_eq!(fixture.vault_client.redeem_queue_length(), 0);
+}
+
+#[test]
+fn test_fulfill_redeem_waits_for_liquidity() {
+    let fixture = MockPoolFixture::new();
+    deposit_as_user(&fixture, 1000_0000000);
+
+    // The reserve is almost fully borrowed
+    let borrower = Address::generate(&fixture.env);
+    fixture
+        .usdc_client
+        .transfer(&fixture.blend_pool, &borrower, &950_0000000);
+
+    fixture
+        .vault_client
+        .request_redeem(&400_0000000, &fixture.user, &fixture.user);
+    assert_eq!(fixture.vault_client.fulfill_redeem(&10), 0);
+    assert_eq!(fixture.vault_client.redeem_queue_length(), 1);
+
+    // Borrowers repay and anyone can process the queue
+    fixture
+        .usdc_client
+        .transfer(&borrower, &fixture.blend_pool, &950_0000000);
+    let before = fixture.usdc_client.balance(&fixture.user);
+    assert_eq!(fixture.vault_client.fulfill_redeem(&10), 1);
+
+    assert_eq!(
+        fixture.usdc_client.balance(&fixture.user),
+        before + 400_0000000
+    );
+    assert_eq!(fixture.vault_client.balance(&fixture.vault), 0);
+    assert_eq!(fixture.vault_client.total_supply(), 600_0000000);
+    assert_eq!(fixture.vault_client.redeem_queue_length(), 0);
+    assert_eq!(fixture.vault_client.get_redeem_request(&0), None);
+    assert_eq!(
+        fixture.vault_client.pending_redeem(&fixture.user),
+        PendingRedeem::default()
+    );
+}
+
+#[test]
+fn test_fulfill_redeem_is_fifo() {
+    let fixture = MockPoolFixture::new();
+    let other = Address::generate(&fixture.env);
+    fixture.usdc_client.mint(&other, &1000_0000000);
+    fixture
+        .usdc_client
+        .approve(&other, &fixture.vault, &i128::MAX, &200);
+    deposit_as_user(&fixture, 500_0000000);
+    fixture
+        .vault_client
+        .deposit(&500_0000000, &other, &other, &other);
+
+    let borrower = Address::generate(&fixture.env);
+    fixture
+        .usdc_client
+        .transfer(&fixture.blend_pool, &borrower, &750_0000000);
+
+    fixture
+        .vault_client
+        .request_redeem(&300_0000000, &fixture.user, &fixture.user);
+    fixture
+        .vault_client
+        .request_redeem(&200_0000000, &other, &other);
+
+    // The second request fits in the remaining liquidity but must wait its turn
+    assert_eq!(fixture.vault_client.fulfill_redeem(&10), 0);
+
+    fixture
+        .usdc_client
+        .transfer(&borrower, &fixture.blend_pool, &750_0000000);
+    assert_eq!(fixture.vault_client.fulfill_redeem(&1), 1);
+    assert_eq!(fixture.vault_client.get_redeem_request(&0), None);
+    assert!(fixture.vault_client.get_redeem_request(&1).is_some());
+
+    assert_eq!(fixture.vault_client.fulfill_redeem(&10), 1);
+    assert_eq!(
+        fixture.vault_client.total_queued_redeem(),
+        PendingRedeem::default()
+    );
+}
+
+#[test]
+fn test_queued_shares_do_not_earn_yield() {
+    let fixture = MockPoolFixture::new();
+    deposit_as_user(&fixture, 1000_0000000);
+    fixture
+        .vault_client
+        .request_redeem(&500_0000000, &fixture.user, &fixture.user);
+
+    // Interest accrues while the request waits
+    RealisticMockBlendPoolClient::new(&fixture.env, &fixture.blend_pool)
+        .set_b_rate(&fixture.usdc_token, &1_100_000_000_000);
+    assert!(fixture.vault_client.preview_redeem(&500_0000000) > 500_0000000);
+
+    let before = fixture.usdc_client.balance(&fixture.user);
+    fixture.vault_client.fulfill_redeem(&1);
+    assert_eq!(
+        fixture.usdc_client.balance(&fixture.user),
+        before + 500_0000000
+    );
+}
+
+#[test]
+fn test_bump_extends_front_of_redeem_queue() {
+    use soroban_sdk::testutils::storage::Persistent as _;
+    let fixture = MockPoolFixture::new();
+    deposit_as_user(&fixture, 1000_0000000);
+    let queued = crate::queue::MAX_BUMPED_REQUESTS + 1;
+    for _ in 0..queued {
+        fixture
+            .vault_client
+            .request_redeem(&10_0000000, &fixture.user, &fixture.user);
+    }
+
+    // 50 days later every request is below the extension threshold
+    let start = fixture.env.ledger().sequence();
+    fixture
+        .env
+        .ledger()
+        .set_sequence_number(start + 50 * crate::ttl::DAY_IN_LEDGERS);
+    fixture.vault_client.bump();
+
+    let request_ttl = |id: u32| {
+        fixture.env.as_contract(&fixture.vault, || {
+            fixture
+                .env
+                .storage()
+                .persistent()
+                .get_ttl(&DataKey::RedeemRequest(id))
+        })
+    };
+    assert_eq!(request_ttl(0), crate::ttl::PERSISTENT_EXTEND_TO);
+    assert_eq!(request_ttl(queued - 2), crate::ttl::PERSISTENT_EXTEND_TO);
+    assert_eq!(request_ttl(queued - 1), 10 * crate::ttl::DAY_IN_LEDGERS);
+}
+
+// ===== Leveraged Looping =====
+
+fn vault_position(fixture: &MockPoolFixture) -> (i128, i128) {
+    let positions = RealisticMockBlendPoolClient::new(&fixture.env, &fixture.blend_pool)
+        .get_positions(&fixture.vault);
+    (
+        positions.collateral.get(0).unwrap_or(0),
+        positions.liabilities.get(0).unwrap_or(0),
+    )
+}
+
+/// Raise the pool's risk factors so the vault can loop against them
+fn allow_looping(fixture: &MockPoolFixture) {
+    RealisticMockBlendPoolClient::new(&fixture.env, &fixture.blend_pool).set_reserve_factors(
+        &fixture.usdc_token,
+        &9_500_000,
+        &9_500_000,
+    );
+}
+
+#[test]
+fn test_leverage_defaults_to_one_x() {
+    let fixture = MockPoolFixture::new();
+    assert_eq!(fixture.vault_client.get_leverage(), 10_000);
+    deposit_as_user(&fixture, 100_0000000);
+    assert_eq!(vault_position(&fixture), (100_0000000, 0));
+}
+
+#[test]
+fn test_leveraged_deposit_loops_to_target() {
+    let fixture = MockPoolFixture::new();
+    allow_looping(&fixture);
+    fixture.vault_client.set_leverage(&30_000);
+    deposit_as_user(&fixture, 100_0000000);
+
+    let (collateral, liabilities) = vault_position(&fixture);
+    assert_eq!(liabilities, 200_0000000);
+    assert_eq!(collateral, 300_0000000);
+    assert_eq!(fixture.vault_client.total_assets(), 100_0000000);
+    assert_eq!(fixture.vault_client.idle_assets(), 0);
+}
+
+#[test]
+fn test_leveraged_withdraw_deleverages_proportionally() {
+    let fixture = MockPoolFixture::new();
+    allow_looping(&fixture);
+    fixture.vault_client.set_leverage(&30_000);
+    deposit_as_user(&fixture, 100_0000000);
+
+    let before = fixture.usdc_client.balance(&fixture.user);
+    fixture
+        .vault_client
+        .withdraw(&50_0000000, &fixture.user, &fixture.user, &fixture.user);
+
+    assert_eq!(
+        fixture.usdc_client.balance(&fixture.user),
+        before + 50_0000000
+    );
+    assert_eq!(vault_position(&fixture), (150_0000000, 100_0000000));
+    assert_eq!(fixture.vault_client.total_assets(), 50_0000000);
+    assert_eq!(fixture.vault_client.idle_assets(), 0);
+}
+
+#[test]
+fn test_leveraged_full_redeem_unwinds_position() {
+    let fixture = MockPoolFixture::new();
+    allow_looping(&fixture);
+    fixture.vault_client.set_leverage(&30_000);
+    let shares = deposit_as_user(&fixture, 100_0000000);
+
+    let before = fixture.usdc_client.balance(&fixture.user);
+    fixture
+        .vault_client
+        .redeem(&shares, &fixture.user, &fixture.user, &fixture.user);
+
+    assert_eq!(
+        fixture.usdc_client.balance(&fixture.user),
+        before + 100_0000000
+    );
+    assert_eq!(vault_position(&fixture), (0, 0));
+    assert_eq!(fixture.vault_client.total_assets(), 0);
+}
+
+#[test]
+#[should_panic(expected = "Target leverage exceeds health factor guard")]
+fn test_set_leverage_respects_health_factor_guard() {
+    // c_factor * l_factor = 0.9025 caps leverage at 1.1 / (1.1 - 0.9025) ~= 5.57x
+    let fixture = MockPoolFixture::new();
+    allow_looping(&fixture);
+    fixture.vault_client.set_leverage(&60_000);
+}
+
+// ===== Health Factor & Deleverage =====
+
+// A 3x leveraged vault on an adversarial pool. With a single asset the oracle
+// price cancels out of the health factor, so rates and risk factors are the
+// adversarial levers.
+
+/// Swap the vault's pool for an `AdversarialMockBlendPool` and loop to 3x on
+/// it, returning the new pool
+fn move_to_adversarial_pool(fixture: &MockPoolFixture) -> Address {
+    let pool = fixture
+        .env
+        .register_contract(None, AdversarialMockBlendPool);
+    AdversarialMockBlendPoolClient::new(&fixture.env, &pool).set_reserve_factors(
+        &fixture.usdc_token,
+        &9_500_000,
+        &9_500_000,
+    );
+    let vault_client = &fixture.vault_client;
+    vault_client.add_strategy(&pool, &true, &i128::MAX);
+    vault_client.set_weights(&vec![&fixture.env, 0u32, 10_000u32]);
+    vault_client.remove_strategy(&fixture.blend_pool);
+    vault_client.set_leverage(&30_000);
+    pool
+}
+
+#[test]
+fn test_health_factor_without_debt_is_max() {
+    let fixture = MockPoolFixture::new();
+    deposit_as_user(&fixture, 100_0000000);
+    assert_eq!(fixture.vault_client.health_factor(), i128::MAX);
+}
+
+#[test]
+fn test_health_factor_uses_reserve_factors() {
+    let fixture = MockPoolFixture::new();
+    move_to_adversarial_pool(&fixture);
+    deposit_as_user(&fixture, 100_0000000);
+    // 300 collateral * 0.95 * 0.95 / 200 liabilities
+    assert_eq!(fixture.vault_client.health_factor(), 13_537);
+    assert_eq!(
+        fixture.vault_client.get_deleverage_config(),
+        DeleverageConfig {
+            threshold: 10_500,
+            bounty: 10,
+        }
+    );
+}
+
+#[test]
+#[should_panic(expected = "Error(Contract, #1005)")]
+fn test_deleverage_rejected_while_healthy() {
+    let fixture = MockPoolFixture::new();
+    move_to_adversarial_pool(&fixture);
+    deposit_as_user(&fixture, 100_0000000);
+    fixture
+        .vault_client
+        .deleverage(&Address::generate(&fixture.env));
+}
+
+#[test]
+fn test_deleverage_after_factor_cut_restores_health() {
+    let fixture = MockPoolFixture::new();
+    let pool = move_to_adversarial_pool(&fixture);
+    deposit_as_user(&fixture, 100_0000000);
+    let pool_client = AdversarialMockBlendPoolClient::new(&fixture.env, &pool);
+    pool_client.set_reserve_factors(&fixture.usdc_token, &8_300_000, &8_300_000);
+    assert_eq!(fixture.vault_client.health_factor(), 10_333);
+
+    let keeper = Address::generate(&fixture.env);
+    let repaid = fixture.vault_client.deleverage(&keeper);
+
+    let bounty = repaid * 10 / 10_000;
+    assert!(repaid > 0);
+    assert_eq!(fixture.usdc_client.balance(&keeper), bounty);
+    assert_eq!(fixture.vault_client.total_assets(), 100_0000000 - bounty);
+    let health_factor = fixture.vault_client.health_factor();
+    assert!(health_factor >= 10_990 && health_factor < 11_000);
+}
+
+#[test]
+fn test_deleverage_after_debt_interest_restores_health() {
+    let fixture = MockPoolFixture::new();
+    let pool = move_to_adversarial_pool(&fixture);
+    deposit_as_user(&fixture, 100_0000000);
+    let pool_client = AdversarialMockBlendPoolClient::new(&fixture.env, &pool);
+    // Debt accrues 30% while collateral earns nothing
+    pool_client.set_rates(&fixture.usdc_token, &1_000_000_000_000, &1_300_000_000_000);
+    assert!(fixture.vault_client.health_factor() < 10_500);
+    let equity = fixture.vault_client.total_assets();
+
+    let keeper = Address::generate(&fixture.env);
+    let repaid = fixture.vault_client.deleverage(&keeper);
+
+    let bounty = fixture.usdc_client.balance(&keeper);
+    assert_eq!(bounty, repaid * 10 / 10_000);
+    assert!(fixture.vault_client.health_factor() >= 10_900);
+    // Repayments burn d-tokens rounded down, costing at most a unit per round
+    assert!(fixture.vault_client.total_assets() >= equity - bounty - 16);
+}
+
+#[test]
+fn test_withdraw_succeeds_below_health_factor_guard() {
+    let fixture = MockPoolFixture::new();
+    let pool = move_to_adversarial_pool(&fixture);
+    deposit_as_user(&fixture, 100_0000000);
+    let pool_client = AdversarialMockBlendPoolClient::new(&fixture.env, &pool);
+    pool_client.set_reserve_factors(&fixture.usdc_token, &8_300_000, &8_300_000);
+
+    let before = fixture.usdc_client.balance(&fixture.user);
+    fixture
+        .vault_client
+        .withdraw(&50_0000000, &fixture.user, &fixture.user, &fixture.user);
+
+    assert_eq!(
+        fixture.usdc_client.balance(&fixture.user),
+        before + 50_0000000
+    );
+    assert_eq!(fixture.vault_client.total_assets(), 50_0000000);
+}
+
+#[test]
+#[should_panic(expected = "Invalid deleverage config")]
+fn test_deleverage_threshold_capped_at_guard() {
+    let fixture = MockPoolFixture::new();
+    fixture
+        .vault_client
+        .set_deleverage_config(&DeleverageConfig {
+            threshold: 12_000,
+            bounty: 10,
+        });
+}
+
+// ===== Asset-Agnostic Vaults =====
+
+#[test]
+fn test_initialize_discovers_reserve_index_and_reward_id() {
+    let fixture = MockPoolFixture::builder().reserve_index(3).build();
+
+    assert_eq!(
+        fixture.vault_client.get_strategies().get(0).unwrap().kind,
+        StrategyKind::Blend(BlendReserve {
+            reserve_index: 3,
+            reward_token_id: 7,
+        })
+    );
+
+    deposit_as_user(&fixture, 100_0000000);
+    let positions = RealisticMockBlendPoolClient::new(&fixture.env, &fixture.blend_pool)
+        .get_positions(&fixture.vault);
+    assert_eq!(positions.collateral.get(3), Some(100_0000000));
+    assert_eq!(fixture.vault_client.total_assets(), 100_0000000);
+}
+
+#[test]
+fn test_initialize_discovers_reserve_from_blend_pool() {
+    let fixture = TestFixture::new();
+    assert_eq!(
+        fixture.vault_client.get_strategies().get(0).unwrap().kind,
+        StrategyKind::Blend(BlendReserve {
+            reserve_index: fixture.usdc_reserve_index,
+            reward_token_id: fixture.blnd_reserve_token_id,
+        })
+    );
+}
+
+#[test]
+fn test_add_strategy_discovers_pool_reserve_index() {
+    let fixture = MockPoolFixture::new();
+    let pool = fixture.env.register_contract(None, RealisticMockBlendPool);
+    RealisticMockBlendPoolClient::new(&fixture.env, &pool)
+        .set_reserve_index(&fixture.usdc_token, &2);
+    fixture.vault_client.add_strategy(&pool, &true, &i128::MAX);
+
+    let added = fixture.vault_client.get_strategies().get(1).unwrap();
+    assert_eq!(
+        added.kind,
+        StrategyKind::Blend(BlendReserve {
+            reserve_index: 2,
+            reward_token_id: 5,
+        })
+    );
+    assert_eq!(added.weight, 0);
+}
+
+#[test]
+fn test_vault_with_six_decimal_asset() {
+    let fixture = MockPoolFixture::builder()
+        .asset("Euro Coin", "EURC", 6)
+        .reserve_index(2)
+        .metadata(Some("Blend Vault EURC"), Some("bvEURC"))
+        .build();
+    let vault_client = &fixture.vault_client;
+    assert_eq!(vault_client.decimals(), 6);
+    assert_eq!(
+        vault_client.name(),
+        SorobanString::from_str(&fixture.env, "Blend Vault EURC")
+    );
+    assert_eq!(
+        vault_client.symbol(),
+        SorobanString::from_str(&fixture.env, "bvEURC")
+    );
+
+    let before = fixture.usdc_client.balance(&fixture.user);
+    let shares = deposit_as_user(&fixture, 250_000000);
+    assert_eq!(shares, 250_000000);
+    assert_eq!(vault_client.total_assets(), 250_000000);
+
+    let assets = vault_client.redeem(&shares, &fixture.user, &fixture.user, &fixture.user);
+    assert_eq!(assets, 250_000000);
+    assert_eq!(fixture.usdc_client.balance(&fixture.user), before);
+}
+
+#[test]
+fn test_vault_with_native_xlm() {
+    let fixture = MockPoolFixture::builder()
+        .stellar_asset()
+        .reserve_index(1)
+        .build();
+    let xlm_client = token::TokenClient::new(&fixture.env, &fixture.usdc_token);
+    let vault_client = &fixture.vault_client;
+    assert_eq!(vault_client.decimals(), 7);
+    assert_eq!(
+        vault_client.get_strategies().get(0).unwrap().kind,
+        StrategyKind::Blend(BlendReserve {
+            reserve_index: 1,
+            reward_token_id: 3,
+        })
+    );
+
+    let before = xlm_client.balance(&fixture.user);
+    xlm_client.approve(&fixture.user, &fixture.vault, &i128::MAX, &200);
+    deposit_as_user(&fixture, 500_0000000);
+    assert_eq!(xlm_client.balance(&fixture.blend_pool), 500_0000000);
+
+    vault_client.withdraw(&200_0000000, &fixture.user, &fixture.user, &fixture.user);
+    assert_eq!(xlm_client.balance(&fixture.user), before - 300_0000000);
+    assert_eq!(vault_client.total_assets(), 300_0000000);
+}
+
+// ===== Share Token Metadata =====
+
+#[test]
+fn test_initialize_derives_metadata_from_asset_symbol() {
+    let fixture = MockPoolFixture::builder().metadata(None, None).build();
+    let vault_client = &fixture.vault_client;
+    assert_eq!(
+        vault_client.name(),
+        SorobanString::from_str(&fixture.env, "Blend Vault USDC")
+    );
+    assert_eq!(
+        vault_client.symbol(),
+        SorobanString::from_str(&fixture.env, "bvUSDC")
+    );
+}
+
+#[test]
+fn test_initialize_derives_xlm_metadata_for_native_asset() {
+    // The native Stellar Asset Contract reports `native` as its symbol
+    let fixture = MockPoolFixture::builder()
+        .asset("native", "native", 7)
+        .metadata(None, None)
+        .build();
+    let vault_client = &fixture.vault_client;
+    assert_eq!(
+        vault_client.name(),
+        SorobanString::from_str(&fixture.env, "Blend Vault XLM")
+    );
+    assert_eq!(
+        vault_client.symbol(),
+        SorobanString::from_str(&fixture.env, "bvXLM")
+    );
+}
+
+#[test]
+fn test_initialize_derives_only_missing_metadata() {
+    let fixture = MockPoolFixture::builder()
+        .metadata(Some("Prime USDC Vault"), None)
+        .build();
+    let vault_client = &fixture.vault_client;
+    assert_eq!(
+        vault_client.name(),
+        SorobanString::from_str(&fixture.env, "Prime USDC Vault")
+    );
+    assert_eq!(
+        vault_client.symbol(),
+        SorobanString::from_str(&fixture.env, "bvUSDC")
+    );
+}
+
+#[test]
+#[should_panic(expected = "Name and symbol must not be empty")]
+fn test_initialize_rejects_empty_symbol() {
+    MockPoolFixture::builder().metadata(None, Some("")).build();
+}
+
+#[test]
+fn test_set_metadata_updates_name_and_symbol() {
+    let fixture = MockPoolFixture::new();
+    fixture.vault_client.set_metadata(
+        &SorobanString::from_str(&fixture.env, "Blend Vault USDC"),
+        &SorobanString::from_str(&fixture.env, "bvUSDC"),
+    );
+    assert_eq!(
+        fixture.vault_client.name(),
+        SorobanString::from_str(&fixture.env, "Blend Vault USDC")
+    );
+    assert_eq!(
+        fixture.vault_client.symbol(),
+        SorobanString::from_str(&fixture.env, "bvUSDC")
+    );
+    assert_eq!(fixture.vault_client.decimals(), 7);
+}
+
+#[test]
+fn test_set_metadata_requires_owner() {
+    let fixture = MockPoolFixture::new();
+    fixture.env.set_auths(&[]);
+    let name = SorobanString::from_str(&fixture.env, "Fake Vault");
+    let symbol = SorobanString::from_str(&fixture.env, "FAKE");
+    assert!(fixture
+        .vault_client
+        .try_set_metadata(&name, &symbol)
+        .is_err());
+    assert_eq!(
+        fixture.vault_client.name(),
+        SorobanString::from_str(&fixture.env, "BLEND VAULT")
+    );
+}
+
+// ===== Native XLM Deposits =====
+
+#[test]
+fn test_xlm_deposit_transfers_directly_without_approval() {
+    use soroban_sdk::testutils::{AuthorizedFunction, AuthorizedInvocation};
+    let fixture = MockPoolFixture::builder()
+        .stellar_asset()
+        .reserve_index(1)
+        .build();
+    let (xlm, vault_client) = (&fixture.usdc_token, &fixture.vault_client);
+    let xlm_client = token::TokenClient::new(&fixture.env, xlm);
+    let before = xlm_client.balance(&fixture.user);
+    assert_eq!(
+        xlm_client.allowance(&fixture.user, &vault_client.address),
+        0
+    );
+
+    let shares = vault_client.deposit(&100_0000000, &fixture.user, &fixture.user, &fixture.user);
+
+    // The user signs the deposit and the SAC transfer nested under it, nothing else
+    assert_eq!(
+        fixture.env.auths(),
+        std::vec![(
+            fixture.user.clone(),
+            AuthorizedInvocation {
+                function: AuthorizedFunction::Contract((
+                    vault_client.address.clone(),
+                    Symbol::new(&fixture.env, "deposit"),
+                    (
+                        100_0000000i128,
+                        fixture.user.clone(),
+                        fixture.user.clone(),
+                        fixture.user.clone(),
+                    )
+                        .into_val(&fixture.env),
+                )),
+                sub_invocations: std::vec![AuthorizedInvocation {
+                    function: AuthorizedFunction::Contract((
+                        xlm.clone(),
+                        Symbol::new(&fixture.env, "transfer"),
+                        (
+                            fixture.user.clone(),
+                            vault_client.address.clone(),
+                            100_0000000i128,
+                        )
+                            .into_val(&fixture.env),
+                    )),
+                    sub_invocations: std::vec![],
+                }],
+            },
+        )]
+    );
+    assert_eq!(shares, 100_0000000);
+    assert_eq!(xlm_client.balance(&fixture.user), before - 100_0000000);
+    assert_eq!(vault_client.total_assets(), 100_0000000);
+}
+
+#[test]
+fn test_xlm_mint_and_redeem_without_approval() {
+    let fixture = MockPoolFixture::builder()
+        .stellar_asset()
+        .reserve_index(1)
+        .build();
+    let (xlm, vault_client) = (&fixture.usdc_token, &fixture.vault_client);
+    let xlm_client = token::TokenClient::new(&fixture.env, xlm);
+    let before = xlm_client.balance(&fixture.user);
+
+    let assets = vault_client.mint(&250_0000000, &fixture.user, &fixture.user, &fixture.user);
+    assert_eq!(assets, 250_0000000);
+    assert_eq!(vault_client.balance(&fixture.user), 250_0000000);
+    assert_eq!(xlm_client.balance(&fixture.user), before - 250_0000000);
+
+    vault_client.redeem(&250_0000000, &fixture.user, &fixture.user, &fixture.user);
+    assert_eq!(xlm_client.balance(&fixture.user), before);
+    assert_eq!(vault_client.total_assets(), 0);
+}
+
+#[test]
+fn test_deposit_spends_allowance_when_approved() {
+    let fixture = MockPoolFixture::new();
+    fixture
+        .usdc_client
+        .approve(&fixture.user, &fixture.vault, &100_0000000, &200);
+    deposit_as_user(&fixture, 60_0000000);
+    assert_eq!(
+        fixture.usdc_client.allowance(&fixture.user, &fixture.vault),
+        40_0000000
+    );
+}
+
+// ===== Zap Deposits =====
+
+#[test]
+fn test_zap_deposit_swaps_and_mints_shares() {
+    let fixture = MockPoolFixture::new();
+    let blnd_client = fixture.fund_zap(500_0000000);
+
+    let shares = fixture.vault_client.zap_deposit(
+        &fixture.blnd_token,
+        &500_0000000,
+        &500_0000000,
+        &fixture.user,
+        &fixture.user,
+    );
+
+    assert_eq!(shares, 500_0000000);
+    assert_eq!(fixture.vault_client.balance(&fixture.user), shares);
+    assert_eq!(fixture.vault_client.total_assets(), 500_0000000);
+    assert_eq!(blnd_client.balance(&fixture.user), 0);
+    assert_eq!(blnd_client.balance(&fixture.vault), 0);
+    assert_eq!(fixture.usdc_client.balance(&fixture.vault), 0);
+}
+
+#[test]
+fn test_zap_deposit_prices_shares_before_the_swap() {
+    let fixture = MockPoolFixture::new();
+    let holder = Address::generate(&fixture.env);
+    fixture.usdc_client.mint(&holder, &1000_0000000);
+    fixture
+        .vault_client
+        .deposit(&1000_0000000, &holder, &holder, &holder);
+    let mock_pool_client = MockBlendPoolClient::new(&fixture.env, &fixture.blend_pool);
+    mock_pool_client.set_b_rate(&fixture.usdc_token, &(crate::BLEND_RATE_SCALAR * 11 / 10));
+    let holder_assets = fixture
+        .vault_client
+        .convert_to_assets(&fixture.vault_client.balance(&holder));
+    let quoted = fixture.vault_client.preview_deposit(&550_0000000);
+
+    fixture.fund_zap(550_0000000);
+    let shares = fixture.vault_client.zap_deposit(
+        &fixture.blnd_token,
+        &550_0000000,
+        &quoted,
+        &fixture.user,
+        &fixture.user,
+    );
+
+    assert_eq!(shares, quoted);
+    assert!(
+        fixture
+            .vault_client
+            .convert_to_assets(&fixture.vault_client.balance(&holder))
+            >= holder_assets
+    );
+}
+
+#[test]
+#[should_panic(expected = "Error(Contract, #1000)")]
+fn test_zap_deposit_reverts_below_min_shares() {
+    let fixture = MockPoolFixture::new();
+    deposit_as_user(&fixture, 1000_0000000);
+    let quoted = fixture.vault_client.preview_deposit(&500_0000000);
+
+    // Yield lands between signing and execution, so each share is worth more
+    let mock_pool_client = MockBlendPoolClient::new(&fixture.env, &fixture.blend_pool);
+    mock_pool_client.set_b_rate(&fixture.usdc_token, &(crate::BLEND_RATE_SCALAR * 11 / 10));
+
+    fixture.fund_zap(500_0000000);
+    fixture.vault_client.zap_deposit(
+        &fixture.blnd_token,
+        &500_0000000,
+        &quoted,
+        &fixture.user,
+        &fixture.user,
+    );
+}
+
+#[test]
+fn test_zap_deposit_of_the_asset_skips_the_swap() {
+    let fixture = MockPoolFixture::new();
+
+    // The mock Comet pool holds no USDC, so any swap would fail
+    let shares = fixture.vault_client.zap_deposit(
+        &fixture.usdc_token,
+        &200_0000000,
+        &200_0000000,
+        &fixture.user,
+        &fixture.user,
+    );
+
+    assert_eq!(shares, 200_0000000);
+    assert_eq!(fixture.vault_client.total_assets(), 200_0000000);
+}
+
+#[test]
+fn test_zap_deposit_requires_from_auth() {
+    let fixture = MockPoolFixture::new();
+    fixture.fund_zap(100_0000000);
+    let receiver = Address::generate(&fixture.env);
+
+    fixture.env.set_auths(&[]);
+    let result = fixture.vault_client.try_zap_deposit(
+        &fixture.blnd_token,
+        &100_0000000,
+        &0,
+        &receiver,
+        &fixture.user,
+    );
+    assert!(result.is_err());
+}
+
+#[test]
+fn test_zap_deposit_with_real_comet_contract() {
+    let fixture = TestFixture::new();
+    let blnd_before = fixture.blnd_client.balance(&fixture.user);
+
+    let shares = fixture.vault_client.mock_all_auths().zap_deposit(
+        &fixture.blnd_token,
+        &100_0000000,
+        &1,
+        &fixture.user,
+        &fixture.user,
+    );
+
+    assert!(shares > 0);
+    assert_eq!(fixture.vault_client.balance(&fixture.user), shares);
+    // Blend rounds the supplied position down by at most one unit
+    assert!(fixture.vault_client.total_assets() >= shares - 1);
+    assert_eq!(
+        fixture.blnd_client.balance(&fixture.user),
+        blnd_before - 100_0000000
+    );
+    assert_eq!(fixture.blnd_client.balance(&fixture.vault), 0);
+}
+
+// ===== Zap Redemptions =====
+
+#[test]
+fn test_zap_redeem_swaps_into_token_out() {
+    let fixture = MockPoolFixture::new();
+    deposit_as_user(&fixture, 1000_0000000);
+    let blnd_client = MockTokenClient::new(&fixture.env, &fixture.blnd_token);
+    blnd_client.mint(&fixture.comet_pool, &400_0000000);
+    let receiver = Address::generate(&fixture.env);
+
+    let amount_out = fixture.vault_client.zap_redeem(
+        &400_0000000,
+        &fixture.blnd_token,
+        &400_0000000,
+        &receiver,
+        &fixture.user,
+    );
+
+    assert_eq!(amount_out, 400_0000000);
+    assert_eq!(blnd_client.balance(&receiver), 400_0000000);
+    assert_eq!(fixture.vault_client.balance(&fixture.user), 600_0000000);
+    assert_eq!(fixture.vault_client.total_assets(), 600_0000000);
+    assert_eq!(
+        fixture.usdc_client.balance(&fixture.comet_pool),
+        400_0000000
+    );
+    assert_eq!(fixture.usdc_client.balance(&fixture.vault), 0);
+}
+
+#[test]
+#[should_panic(expected = "Error(Contract, #1003)")]
+fn test_zap_redeem_reverts_below_min_out() {
+    let fixture = MockPoolFixture::new();
+    deposit_as_user(&fixture, 1000_0000000);
+    let blnd_client = MockTokenClient::new(&fixture.env, &fixture.blnd_token);
+    blnd_client.mint(&fixture.comet_pool, &400_0000000);
+
+    fixture.vault_client.zap_redeem(
+        &400_0000000,
+        &fixture.blnd_token,
+        &400_0000001,
+        &fixture.user,
+        &fixture.user,
+    );
+}
+
+#[test]
+fn test_zap_redeem_into_the_asset_skips_the_swap() {
+    let fixture = MockPoolFixture::new();
+    deposit_as_user(&fixture, 1000_0000000);
+    let receiver = Address::generate(&fixture.env);
+
+    let amount_out = fixture.vault_client.zap_redeem(
+        &250_0000000,
+        &fixture.usdc_token,
+        &250_0000000,
+        &receiver,
+        &fixture.user,
+    );
+
+    assert_eq!(amount_out, 250_0000000);
+    assert_eq!(fixture.usdc_client.balance(&receiver), 250_0000000);
+    assert_eq!(fixture.usdc_client.balance(&fixture.comet_pool), 0);
+}
+
+#[test]
+fn test_zap_redeem_requires_owner_auth() {
+    let fixture = MockPoolFixture::new();
+    deposit_as_user(&fixture, 1000_0000000);
+    let receiver = Address::generate(&fixture.env);
+
+    fixture.env.set_auths(&[]);
+    let result = fixture.vault_client.try_zap_redeem(
+        &100_0000000,
+        &fixture.usdc_token,
+        &0,
+        &receiver,
+        &fixture.user,
+    );
+    assert!(result.is_err());
+}
+
+#[test]
+fn test_zap_redeem_with_real_comet_contract() {
+    let fixture = TestFixture::new();
+    fixture.vault_client.mock_all_auths().deposit(
+        &1000_0000000,
+        &fixture.user,
+        &fixture.user,
+        &fixture.user,
+    );
+    let receiver = Address::generate(&fixture.env);
+
+    let amount_out = fixture.vault_client.mock_all_auths().zap_redeem(
+        &100_0000000,
+        &fixture.blnd_token,
+        &1,
+        &receiver,
+        &fixture.user,
+    );
+
+    assert!(amount_out > 0);
+    assert_eq!(fixture.blnd_client.balance(&receiver), amount_out);
+    assert_eq!(fixture.vault_client.balance(&fixture.user), 900_0000000);
+    assert_eq!(fixture.usdc_client.balance(&fixture.vault), 0);
+}
+
+// ===== Share Price Oracle =====
+
+#[test]
+fn test_oracle_metadata() {
+    let fixture = MockPoolFixture::new();
+    assert_eq!(
+        fixture.vault_client.base(),
+        Asset::Stellar(fixture.usdc_token.clone())
+    );
+    assert_eq!(
+        fixture.vault_client.assets(),
+        vec![&fixture.env, fixture.share_asset()]
+    );
+    assert_eq!(fixture.vault_client.resolution(), 300);
+    assert_eq!(fixture.vault_client.decimals(), 7);
+}
+
+#[test]
+fn test_lastprice_requires_a_full_window_of_history() {
+    let fixture = MockPoolFixture::new();
+    assert_eq!(fixture.vault_client.lastprice(&fixture.share_asset()), None);
+
+    deposit_as_user(&fixture, 1000_0000000);
+    fixture.warp(1500);
+    assert_eq!(fixture.vault_client.lastprice(&fixture.share_asset()), None);
+
+    fixture.warp(1850);
+    assert_eq!(
+        fixture.vault_client.lastprice(&fixture.share_asset()),
+        Some(PriceData {
+            price: 1_0000000,
+            timestamp: 1800,
+        })
+    );
+}
+
+#[test]
+fn test_oracle_ignores_other_assets() {
+    let fixture = MockPoolFixture::new();
+    deposit_as_user(&fixture, 1000_0000000);
+    fixture.warp(3000);
+
+    let usdc = Asset::Stellar(fixture.usdc_token.clone());
+    assert_eq!(fixture.vault_client.lastprice(&usdc), None);
+    assert_eq!(fixture.vault_client.prices(&usdc, &3), None);
+    let other = Asset::Other(Symbol::new(&fixture.env, "USD"));
+    assert_eq!(fixture.vault_client.price(&other, &3000), None);
+}
+
+#[test]
+fn test_donation_cannot_spike_price_within_one_ledger() {
+    let fixture = MockPoolFixture::new();
+    deposit_as_user(&fixture, 1000_0000000);
+    fixture.warp(3000);
+    let baseline = fixture
+        .vault_client
+        .lastprice(&fixture.share_asset())
+        .unwrap();
+    assert_eq!(baseline.price, 1_0000000);
+
+    // Donate as much as the vault holds to the reserve, doubling its b_rate,
+    // then trigger an observation
+    RealisticMockBlendPoolClient::new(&fixture.env, &fixture.blend_pool)
+        .set_b_rate(&fixture.usdc_token, &2_000_000_000_000);
+    deposit_as_user(&fixture, 1_0000000);
+    assert!(fixture.vault_client.convert_to_assets(&1_0000000) > 1_9000000);
+    assert_eq!(
+        fixture.vault_client.lastprice(&fixture.share_asset()),
+        Some(baseline.clone())
+    );
+
+    // The new price phases in over the window instead of all at once
+    fixture.warp(3300);
+    let next = fixture
+        .vault_client
+        .lastprice(&fixture.share_asset())
+        .unwrap();
+    assert!(next.price > baseline.price);
+    assert!(next.price < 1_2000000);
+
+    fixture.warp(3000 + 1800);
+    let settled = fixture
+        .vault_client
+        .lastprice(&fixture.share_asset())
+        .unwrap();
+    assert!(settled.price > 1_9000000);
+}
+
+#[test]
+fn test_harvest_cannot_spike_price_within_one_ledger() {
+    let fixture = MockPoolFixture::new();
+    deposit_as_user(&fixture, 1000_0000000);
+    fixture.warp(3000);
+    let baseline = fixture
+        .vault_client
+        .lastprice(&fixture.share_asset())
+        .unwrap();
+
+    let mock_pool_client = MockBlendPoolClient::new(&fixture.env, &fixture.blend_pool);
+    mock_pool_client.set_b_rate(&fixture.usdc_token, &(crate::BLEND_RATE_SCALAR * 3 / 2));
+    deposit_as_user(&fixture, 1_0000000);
+
+    assert_eq!(
+        fixture.vault_client.lastprice(&fixture.share_asset()),
+        Some(baseline)
+    );
+}
+
+#[test]
+fn test_prices_returns_rounds_newest_first() {
+    let fixture = MockPoolFixture::new();
+    deposit_as_user(&fixture, 1000_0000000);
+    fixture.warp(2450);
+
+    let prices = fixture
+        .vault_client
+        .prices(&fixture.share_asset(), &2)
+        .unwrap();
+    assert_eq!(prices.len(), 2);
+    assert_eq!(prices.get(0).unwrap().timestamp, 2400);
+    assert_eq!(prices.get(1).unwrap().timestamp, 2100);
+
+    // Rounds without a full window of history are left out
+    let prices = fixture
+        .vault_client
+        .prices(&fixture.share_asset(), &10)
+        .unwrap();
+    assert_eq!(prices.len(), 3);
+    assert_eq!(prices.get(2).unwrap().timestamp, 1800);
+    for data in prices.iter() {
+        assert_eq!(data.price, 1_0000000);
+    }
+}
+
+#[test]
+fn test_oracle_restarts_after_going_stale() {
+    let fixture = MockPoolFixture::new();
+    // Keep the instance alive across the gap below
+    fixture.vault_client.set_ttl_config(&TtlConfig {
+        instance_threshold: 120 * crate::ttl::DAY_IN_LEDGERS,
+        instance_extend_to: 120 * crate::ttl::DAY_IN_LEDGERS,
+        persistent_threshold: crate::ttl::PERSISTENT_TTL_THRESHOLD,
+        persistent_extend_to: crate::ttl::PERSISTENT_EXTEND_TO,
+    });
+    deposit_as_user(&fixture, 1000_0000000);
+    fixture.warp(3000);
+    assert!(fixture
+        .vault_client
+        .lastprice(&fixture.share_asset())
+        .is_some());
+
+    // Nothing is observed for longer than the maximum age, the old
+    // observations may be archived by now
+    let stale_at = 3000 + 31 * 24 * 60 * 60;
+    fixture.env.ledger().with_mut(|li| {
+        li.sequence_number += crate::oracle::OBSERVATION_MAX_AGE;
+        li.timestamp = stale_at;
+    });
+    assert_eq!(fixture.vault_client.lastprice(&fixture.share_asset()), None);
+
+    // The next deposit starts a new buffer, which needs a full window again
+    deposit_as_user(&fixture, 1000_0000000);
+    fixture.warp(stale_at + 1500);
+    assert_eq!(fixture.vault_client.lastprice(&fixture.share_asset()), None);
+
+    fixture.warp(stale_at + 2100);
+    let round = (stale_at + 2100) / 300 * 300;
+    assert_eq!(
+        fixture.vault_client.lastprice(&fixture.share_asset()),
+        Some(PriceData {
+            price: 1_0000000,
+            timestamp: round,
+        })
+    );
+}
+
+// ===== Share Price History =====
+
+#[test]
+fn test_share_price_history_records_once_per_interval() {
+    let fixture = MockPoolFixture::new();
+    assert!(fixture.vault_client.share_price_history(&10).is_empty());
+
+    let start = fixture.env.ledger().sequence();
+    deposit_as_user(&fixture, 1000_0000000);
+    fixture.warp(1800);
+    deposit_as_user(&fixture, 500_0000000);
+    assert_eq!(fixture.vault_client.share_price_history(&10).len(), 1);
+
+    fixture.warp(3600);
+    deposit_as_user(&fixture, 500_0000000);
+    let history = fixture.vault_client.share_price_history(&10);
+    assert_eq!(
+        history,
+        vec![
+            &fixture.env,
+            SharePriceCheckpoint {
+                ledger: start + 2,
+                timestamp: 3600,
+                total_assets: 2000_0000000,
+                total_supply: 2000_0000000,
+            },
+            SharePriceCheckpoint {
+                ledger: start,
+                timestamp: 0,
+                total_assets: 1000_0000000,
+                total_supply: 1000_0000000,
+            },
+        ]
+    );
+    assert_eq!(fixture.vault_client.share_price_history(&1).len(), 1);
+}
+
+#[test]
+fn test_share_price_history_drops_checkpoints_past_max_age() {
+    let fixture = MockPoolFixture::new();
+    // Keep the instance alive across the gaps below
+    fixture.vault_client.set_ttl_config(&TtlConfig {
+        instance_threshold: 120 * crate::ttl::DAY_IN_LEDGERS,
+        instance_extend_to: 120 * crate::ttl::DAY_IN_LEDGERS,
+        persistent_threshold: crate::ttl::PERSISTENT_TTL_THRESHOLD,
+        persistent_extend_to: crate::ttl::PERSISTENT_EXTEND_TO,
+    });
+    deposit_as_user(&fixture, 1000_0000000);
+    let advance = |ledgers: u32, seconds: u64| {
+        fixture.env.ledger().with_mut(|li| {
+            li.sequence_number += ledgers;
+            li.timestamp += seconds;
+        });
+    };
+
+    advance(20 * crate::ttl::DAY_IN_LEDGERS, 20 * 24 * 60 * 60);
+    deposit_as_user(&fixture, 500_0000000);
+    assert_eq!(fixture.vault_client.share_price_history(&10).len(), 2);
+
+    // The first checkpoint ages out, the second is still within the window
+    advance(15 * crate::ttl::DAY_IN_LEDGERS, 15 * 24 * 60 * 60);
+    let history = fixture.vault_client.share_price_history(&10);
+    assert_eq!(history.len(), 1);
+    assert_eq!(history.get(0).unwrap().total_assets, 1500_0000000);
+    assert_eq!(
+        fixture.vault_client.realized_apy(&(30 * 24 * 60 * 60)),
+        None
+    );
+
+    // Once every checkpoint could be archived none of them is read
+    advance(70 * crate::ttl::DAY_IN_LEDGERS, 70 * 24 * 60 * 60);
+    assert!(fixture.vault_client.share_price_history(&10).is_empty());
+}
+
+#[test]
+fn test_realized_apy_annualizes_share_price_growth() {
+    let fixture = MockPoolFixture::new();
+    deposit_as_user(&fixture, 1000_0000000);
+    assert_eq!(
+        fixture.vault_client.realized_apy(&(30 * 24 * 60 * 60)),
+        None
+    );
+
+    // 5% over half a year
+    fixture.warp(365 * 24 * 60 * 60 / 2);
+    let mock_pool_client = MockBlendPoolClient::new(&fixture.env, &fixture.blend_pool);
+    mock_pool_client.set_b_rate(&fixture.usdc_token, &(crate::BLEND_RATE_SCALAR * 105 / 100));
+
+    let apy = fixture
+        .vault_client
+        .realized_apy(&(30 * 24 * 60 * 60))
+        .unwrap();
+    assert!((apy - 1_000_000).abs() <= 1, "apy {}", apy);
+}
+
+#[test]
+fn test_realized_apy_reports_losses() {
+    let fixture = MockPoolFixture::new();
+    deposit_as_user(&fixture, 1000_0000000);
+    fixture.warp(365 * 24 * 60 * 60);
+    let mock_pool_client = MockBlendPoolClient::new(&fixture.env, &fixture.blend_pool);
+    mock_pool_client.set_b_rate(&fixture.usdc_token, &(crate::BLEND_RATE_SCALAR * 98 / 100));
+
+    let apy = fixture.vault_client.realized_apy(&0).unwrap();
+    assert!((apy + 200_000).abs() <= 1, "apy {}", apy);
+}
+
+// ===== Projected APR =====
+
+/// The mainnet USDC reserve from `prompts/0x12-estSupplyApy.md`
+fn mainnet_usdc_reserve(env: &Env) -> Reserve {
+    let mut reserve = build_reserve(
+        Address::generate(env),
+        1,
+        1_072_799_171_533,
+        1_107_697_781_106,
+        (9_500_000, 9_500_000),
+    );
+    reserve.config.util = 8_000_000;
+    reserve.config.max_util = 9_500_000;
+    reserve.config.r_base = 300_000;
+    reserve.config.r_one = 400_000;
+    reserve.config.r_two = 1_200_000;
+    reserve.config.r_three = 50_000_000;
+    reserve.data.ir_mod = 23_921_867;
+    reserve.data.b_supply = 44_831_235_792_545;
+    reserve.data.d_supply = 37_101_864_781_356;
+    reserve
+}
+
+#[test]
+fn test_interest_matches_blend_rate_model() {
+    let env = Env::default();
+    let rates = crate::apr::interest(&mainnet_usdc_reserve(&env), 1_000_000);
+
+    // 85.45% utilized, 27.18% to borrowers and 20.90% to suppliers after the
+    // backstop's 10%
+    assert_eq!(rates.utilization, 8_545_114);
+    assert_eq!(rates.borrow_apr, 2_717_745);
+    assert_eq!(rates.backstop_rate, 1_000_000);
+    assert_eq!(rates.supply_apr, 2_090_109);
+    assert_eq!(rates.total_apr, rates.supply_apr);
+}
+
+#[test]
+fn test_borrow_apr_follows_each_curve_segment() {
+    let env = Env::default();
+    let config = mainnet_usdc_reserve(&env).config;
+    let ir_mod = crate::apr::APR_SCALAR;
+
+    assert_eq!(crate::apr::borrow_apr(&config, 0, ir_mod), 300_000);
+    assert_eq!(crate::apr::borrow_apr(&config, 8_000_000, ir_mod), 700_000);
+    assert_eq!(
+        crate::apr::borrow_apr(&config, 9_500_000, ir_mod),
+        1_900_000
+    );
+    assert_eq!(
+        crate::apr::borrow_apr(&config, 9_750_000, ir_mod),
+        26_900_000
+    );
+    // The rate modifier scales the curve below max utilization
+    assert_eq!(
+        crate::apr::borrow_apr(&config, 8_000_000, ir_mod * 2),
+        1_400_000
+    );
+}
+
+#[test]
+fn test_projected_apr_with_real_blend_emissions() {
+    let fixture = TestFixture::new();
+    fixture.vault_client.mock_all_auths().deposit(
+        &1000_0000000,
+        &fixture.user,
+        &fixture.user,
+        &fixture.user,
+    );
+    assert!(
+        fixture.accrue_emissions(),
+        "failed to accrue Blend emissions"
+    );
+
+    let apr = fixture.vault_client.projected_apr();
+    // Nothing is borrowed, so suppliers only earn emissions
+    assert_eq!(apr.utilization, 0);
+    assert_eq!(apr.supply_apr, 0);
+    assert_eq!(apr.backstop_rate, 0_1000000);
+    assert!(apr.emission_apr > 0);
+    assert_eq!(apr.total_apr, apr.emission_apr);
+}
+
+// ===== Cost Basis =====
+
+fn assert_near(actual: i128, expected: i128) {
+    assert!(
+        (actual - expected).abs() <= 2,
+        "expected {} got {}",
+        expected,
+        actual
+    );
+}
+
+#[test]
+fn test_position_tracks_principal_and_unrealized_yield() {
+    let fixture = MockPoolFixture::new();
+    deposit_as_user(&fixture, 1000_0000000);
+    let position = fixture.vault_client.position_of(&fixture.user);
+    assert_eq!(position.shares, 1000_0000000);
+    assert_eq!(position.principal, 1000_0000000);
+    assert_eq!(position.value, 1000_0000000);
+    assert_eq!(position.earned, 0);
+
+    fixture.grow_share_price();
+    let position = fixture.vault_client.position_of(&fixture.user);
+    assert_eq!(position.principal, 1000_0000000);
+    assert_near(position.value, 1100_0000000);
+    let earnings = fixture.vault_client.earnings_of(&fixture.user);
+    assert_eq!(earnings.realized, 0);
+    assert_near(earnings.unrealized, 100_0000000);
+    assert_eq!(earnings.total, earnings.unrealized);
+}
+
+#[test]
+fn test_redeem_realizes_yield_at_average_cost() {
+    let fixture = MockPoolFixture::new();
+    deposit_as_user(&fixture, 1000_0000000);
+    fixture.grow_share_price();
+
+    fixture
+        .vault_client
+        .redeem(&500_0000000, &fixture.user, &fixture.user, &fixture.user);
+
+    let position = fixture.vault_client.position_of(&fixture.user);
+    assert_eq!(position.shares, 500_0000000);
+    assert_eq!(position.principal, 500_0000000);
+    let earnings = fixture.vault_client.earnings_of(&fixture.user);
+    assert_near(earnings.realized, 50_0000000);
+    assert_near(earnings.unrealized, 50_0000000);
+    assert_near(earnings.total, 100_0000000);
+
+    // Exiting the rest clears the principal
+    let shares = fixture.vault_client.balance(&fixture.user);
+    fixture
+        .vault_client
+        .redeem(&shares, &fixture.user, &fixture.user, &fixture.user);
+    let position = fixture.vault_client.position_of(&fixture.user);
+    assert_eq!(position.principal, 0);
+    assert_eq!(position.value, 0);
+    assert_near(
+        fixture.vault_client.earnings_of(&fixture.user).realized,
+        100_0000000,
+    );
+}
+
+#[test]
+fn test_transfer_moves_weighted_average_principal() {
+    let fixture = MockPoolFixture::new();
+    deposit_as_user(&fixture, 1000_0000000);
+    fixture.grow_share_price();
+    deposit_as_user(&fixture, 1100_0000000);
+    let position = fixture.vault_client.position_of(&fixture.user);
+    assert_eq!(position.principal, 2100_0000000);
+    assert_near(position.shares, 2000_0000000);
+
+    let recipient = Address::generate(&fixture.env);
+    let half = position.shares / 2;
+    fixture
+        .vault_client
+        .transfer(&fixture.user, &recipient, &half);
+
+    let sender = fixture.vault_client.position_of(&fixture.user);
+    let received = fixture.vault_client.position_of(&recipient);
+    assert_near(sender.principal, 1050_0000000);
+    assert_near(received.principal, 1050_0000000);
+    assert_eq!(sender.principal + received.principal, 2100_0000000);
+    assert_near(received.earned, 50_0000000);
+    assert_eq!(fixture.vault_client.earnings_of(&recipient).realized, 0);
+}
+
+#[test]
+fn test_request_redeem_realizes_at_request() {
+    let fixture = MockPoolFixture::new();
+    deposit_as_user(&fixture, 1000_0000000);
+    fixture.grow_share_price();
+
+    fixture
+        .vault_client
+        .request_redeem(&1000_0000000, &fixture.user, &fixture.user);
+
+    let position = fixture.vault_client.position_of(&fixture.user);
+    assert_eq!(position.principal, 0);
+    assert_near(
+        fixture.vault_client.earnings_of(&fixture.user).realized,
+        100_0000000,
+    );
+    // The escrowing vault does not pick up any principal
+    assert_eq!(
+        fixture.vault_client.position_of(&fixture.vault).principal,
+        0
+    );
+}
+
+// ===== Events =====
+
+/// Assert that the last event the vault published during the latest
+/// invocation has exactly these topics and data fields
+fn assert_last_vault_event(fixture: &MockPoolFixture, topics: &[&str], fields: &[(&str, Val)]) {
+    let env = &fixture.env;
+    let mut last = Vec::new(env);
+    for event in env.events().all().iter() {
+        if event.0 == fixture.vault {
+            last = vec![env, event];
+        }
+    }
+    let mut expected_topics = Vec::<Val>::new(env);
+    for topic in topics {
+        expected_topics.push_back(Symbol::new(env, topic).into_val(env));
+    }
+    let mut expected_data = Map::<Symbol, Val>::new(env);
+    for (key, value) in fields {
+        expected_data.set(Symbol::new(env, key), *value);
+    }
+    assert_eq!(
+        last,
+        vec![
+            env,
+            (
+                fixture.vault.clone(),
+                expected_topics,
+                expected_data.into_val(env)
+            )
+        ]
+    );
+}
+
+#[test]
+fn test_deposit_and_mint_events_carry_totals() {
+    let fixture = MockPoolFixture::new();
+    let env = &fixture.env;
+    fixture.warp(1_000);
+
+    deposit_as_user(&fixture, 1000_0000000);
+    assert_last_vault_event(
+        &fixture,
+        &["deposit_event"],
+        &[
+            ("operator", fixture.user.into_val(env)),
+            ("receiver", fixture.user.into_val(env)),
+            ("assets", 1000_0000000i128.into_val(env)),
+            ("shares", 1000_0000000i128.into_val(env)),
+            ("total_assets", 1000_0000000i128.into_val(env)),
+            ("total_supply", 1000_0000000i128.into_val(env)),
+            ("timestamp", 1_000u64.into_val(env)),
+        ],
+    );
+
+    fixture.warp(2_000);
+    fixture
+        .vault_client
+        .mint(&250_0000000, &fixture.user, &fixture.user, &fixture.user);
+    assert_last_vault_event(
+        &fixture,
+        &["mint_event"],
+        &[
+            ("operator", fixture.user.into_val(env)),
+            ("receiver", fixture.user.into_val(env)),
+            ("assets", 250_0000000i128.into_val(env)),
+            ("shares", 250_0000000i128.into_val(env)),
+            ("total_assets", 1250_0000000i128.into_val(env)),
+            ("total_supply", 1250_0000000i128.into_val(env)),
+            ("timestamp", 2_000u64.into_val(env)),
+        ],
+    );
+}
+
+#[test]
+fn test_withdraw_and_redeem_events_carry_totals() {
+    let fixture = MockPoolFixture::new();
+    let env = &fixture.env;
+    let receiver = Address::generate(env);
+    deposit_as_user(&fixture, 1000_0000000);
+    fixture.warp(3_600);
+
+    fixture
+        .vault_client
+        .withdraw(&400_0000000, &receiver, &fixture.user, &fixture.user);
+    assert_last_vault_event(
+        &fixture,
+        &["withdraw_event"],
+        &[
+            ("operator", fixture.user.into_val(env)),
+            ("receiver", receiver.into_val(env)),
+            ("owner", fixture.user.into_val(env)),
+            ("assets", 400_0000000i128.into_val(env)),
+            ("shares", 400_0000000i128.into_val(env)),
+            ("total_assets", 600_0000000i128.into_val(env)),
+            ("total_supply", 600_0000000i128.into_val(env)),
+            ("timestamp", 3_600u64.into_val(env)),
+        ],
+    );
+
+    fixture
+        .vault_client
+        .redeem(&100_0000000, &receiver, &fixture.user, &fixture.user);
+    assert_last_vault_event(
+        &fixture,
+        &["redeem_event"],
+        &[
+            ("operator", fixture.user.into_val(env)),
+            ("receiver", receiver.into_val(env)),
+            ("owner", fixture.user.into_val(env)),
+            ("assets", 100_0000000i128.into_val(env)),
+            ("shares", 100_0000000i128.into_val(env)),
+            ("total_assets", 500_0000000i128.into_val(env)),
+            ("total_supply", 500_0000000i128.into_val(env)),
+            ("timestamp", 3_600u64.into_val(env)),
+        ],
+    );
+}
+
+#[test]
+fn test_compound_event_carries_totals() {
+    let fixture = MockPoolFixture::new();
+    let env = &fixture.env;
+    deposit_as_user(&fixture, 1000_0000000);
+    // The mock pool pays out 1000 BLND per claim and Comet swaps it 1:1
+    fixture.usdc_client.mint(&fixture.comet_pool, &1000_0000000);
+    fixture.warp(86_400);
+
+    fixture.vault_client.compound(&fixture.user);
+    assert_last_vault_event(
+        &fixture,
+        &["compound_event"],
+        &[
+            ("blnd_claimed", 1000_0000000i128.into_val(env)),
+            ("assets_received", 1000_0000000i128.into_val(env)),
+            ("total_assets", 2000_0000000i128.into_val(env)),
+            ("total_supply", 1000_0000000i128.into_val(env)),
+            ("timestamp", 86_400u64.into_val(env)),
+        ],
+    );
+}
+
+#[test]
+fn test_queued_redeem_events_carry_totals() {
+    let fixture = MockPoolFixture::new();
+    let env = &fixture.env;
+    deposit_as_user(&fixture, 1000_0000000);
+    fixture.warp(600);
+
+    let id = fixture
+        .vault_client
+        .request_redeem(&400_0000000, &fixture.user, &fixture.user);
+    // Escrowed shares stay in the supply until the request is fulfilled
+    assert_last_vault_event(
+        &fixture,
+        &["redeem_requested_event"],
+        &[
+            ("id", id.into_val(env)),
+            ("owner", fixture.user.into_val(env)),
+            ("receiver", fixture.user.into_val(env)),
+            ("assets", 400_0000000i128.into_val(env)),
+            ("shares", 400_0000000i128.into_val(env)),
+            ("total_assets", 1000_0000000i128.into_val(env)),
+            ("total_supply", 1000_0000000i128.into_val(env)),
+            ("timestamp", 600u64.into_val(env)),
+        ],
+    );
+
+    fixture.warp(1_200);
+    fixture.vault_client.fulfill_redeem(&1);
+    assert_last_vault_event(
+        &fixture,
+        &["redeem_fulfilled_event"],
+        &[
+            ("id", id.into_val(env)),
+            ("owner", fixture.user.into_val(env)),
+            ("receiver", fixture.user.into_val(env)),
+            ("assets", 400_0000000i128.into_val(env)),
+            ("shares", 400_0000000i128.into_val(env)),
+            ("total_assets", 600_0000000i128.into_val(env)),
+            ("total_supply", 600_0000000i128.into_val(env)),
+            ("timestamp", 1_200u64.into_val(env)),
+        ],
+    );
+}
+
+#[test]
+fn test_zap_events_carry_totals() {
+    let fixture = MockPoolFixture::new();
+    let env = &fixture.env;
+    fixture.fund_zap(500_0000000);
+    fixture.warp(900);
+
+    fixture.vault_client.zap_deposit(
+        &fixture.blnd_token,
+        &500_0000000,
+        &0,
+        &fixture.user,
+        &fixture.user,
+    );
+    assert_last_vault_event(
+        &fixture,
+        &["zap_deposit_event"],
+        &[
+            ("from", fixture.user.into_val(env)),
+            ("receiver", fixture.user.into_val(env)),
+            ("token_in", fixture.blnd_token.into_val(env)),
+            ("amount_in", 500_0000000i128.into_val(env)),
+            ("assets", 500_0000000i128.into_val(env)),
+            ("shares", 500_0000000i128.into_val(env)),
+            ("total_assets", 500_0000000i128.into_val(env)),
+            ("total_supply", 500_0000000i128.into_val(env)),
+            ("timestamp", 900u64.into_val(env)),
+        ],
+    );
+
+    fixture.vault_client.zap_redeem(
+        &200_0000000,
+        &fixture.usdc_token,
+        &0,
+        &fixture.user,
+        &fixture.user,
+    );
+    assert_last_vault_event(
+        &fixture,
+        &["zap_redeem_event"],
+        &[
+            ("owner", fixture.user.into_val(env)),
+            ("receiver", fixture.user.into_val(env)),
+            ("shares", 200_0000000i128.into_val(env)),
+            ("assets", 200_0000000i128.into_val(env)),
+            ("token_out", fixture.usdc_token.into_val(env)),
+            ("amount_out", 200_0000000i128.into_val(env)),
+            ("total_assets", 300_0000000i128.into_val(env)),
+            ("total_supply", 300_0000000i128.into_val(env)),
+            ("timestamp", 900u64.into_val(env)),
+        ],
+    );
+}
+
+#[test]
+fn test_config_changed_events() {
+    let fixture = MockPoolFixture::new();
+    let env = &fixture.env;
+
+    fixture.vault_client.set_idle_ratio(&500);
+    assert_last_vault_event(
+        &fixture,
+        &["config_changed", "idle_ratio"],
+        &[
+            ("previous", 0u32.into_val(env)),
+            ("ratio", 500u32.into_val(env)),
+        ],
+    );
+
+    let pool = fixture.add_pool();
+    assert_last_vault_event(
+        &fixture,
+        &["config_changed", "strategy_added"],
+        &[
+            ("strategy", pool.into_val(env)),
+            (
+                "kind",
+                StrategyKind::Blend(BlendReserve {
+                    reserve_index: 0,
+                    reward_token_id: 1,
+                })
+                .into_val(env),
+            ),
+            ("max_debt", i128::MAX.into_val(env)),
+        ],
+    );
+
+    let weights = vec![env, 6_000u32, 4_000u32];
+    fixture.vault_client.set_weights(&weights);
+    assert_last_vault_event(
+        &fixture,
+        &["config_changed", "weights"],
+        &[("weights", weights.into_val(env))],
+    );
+
+    fixture
+        .vault_client
+        .set_weights(&vec![env, 10_000u32, 0u32]);
+    fixture.vault_client.remove_strategy(&pool);
+    assert_last_vault_event(
+        &fixture,
+        &["config_changed", "strategy_removed"],
+        &[("strategy", pool.into_val(env))],
+    );
+
+    let config = DeleverageConfig {
+        threshold: 11_000,
+        bounty: 50,
+    };
+    fixture.vault_client.set_deleverage_config(&config);
+    assert_last_vault_event(
+        &fixture,
+        &["config_changed", "deleverage_config"],
+        &[("config", config.into_val(env))],
+    );
+}
+
+#[test]
+fn test_strategy_config_changed_events() {
+    let fixture = MockPoolFixture::new();
+    let env = &fixture.env;
+    let strategy = fixture.register_strategy(&fixture.usdc_token);
+
+    fixture
+        .vault_client
+        .add_strategy(&strategy, &false, &500_0000000);
+    assert_last_vault_event(
+        &fixture,
+        &["config_changed", "strategy_added"],
+        &[
+            ("strategy", strategy.into_val(env)),
+            ("kind", StrategyKind::Contract.into_val(env)),
+            ("max_debt", 500_0000000i128.into_val(env)),
+        ],
+    );
+
+    fixture.vault_client.set_max_debt(&strategy, &200_0000000);
+    assert_last_vault_event(
+        &fixture,
+        &["config_changed", "max_debt"],
+        &[
+            ("strategy", strategy.into_val(env)),
+            ("previous", 500_0000000i128.into_val(env)),
+            ("max_debt", 200_0000000i128.into_val(env)),
+        ],
+    );
+
+    fixture.vault_client.remove_strategy(&strategy);
+    assert_last_vault_event(
+        &fixture,
+        &["config_changed", "strategy_removed"],
+        &[("strategy", strategy.into_val(env))],
+    );
+}
@@ -0,0 +1,235 @@
+//! Fixtures shared by the contract tests and the simulation crate
+
+use blend_contract_sdk::{
+    pool,
+    testutils::{default_reserve_config, BlendFixture},
+};
+use sep_41_token::testutils::{MockTokenClient, MockTokenWASM};
+use soroban_sdk::{
+    testutils::{Address as _, BytesN as _, Ledger},
+    vec, Address, BytesN, Env, String, Vec,
+};
+
+use crate::{mocks::MockOracle, BlendVaultContract, BlendVaultContractClient};
+
+pub const DAY_IN_SECONDS: u64 = 60 * 60 * 24;
+pub const WEEK_IN_SECONDS: u64 = DAY_IN_SECONDS * 7;
+const EMITTER_WAIT_SECONDS: u64 = WEEK_IN_SECONDS * 3;
+const BACKSTOP_WAIT_SECONDS: u64 = 60;
+const GULP_DELAY_SECONDS: u64 = DAY_IN_SECONDS + BACKSTOP_WAIT_SECONDS;
+const EMISSION_RETRY_DELAY_SECONDS: u64 = 60 * 60;
+
+/// Ledger time every `TestFixture` starts at
+pub const START_TIMESTAMP: u64 = 1_700_000_000;
+
+/// The vault on a real Blend pool deployed through `BlendFixture`, priced by a
+/// [`MockOracle`] so the pool also accepts borrows. BLND is swapped on the
+/// backstop's Comet pool unless `new_with_comet` builds another one.
+pub struct TestFixture<'a> {
+    pub env: Env,
+    pub deployer: Address,
+    pub user: Address,
+    pub usdc_token: Address,
+    pub usdc_client: MockTokenClient<'a>,
+    pub blnd_token: Address,
+    pub blnd_client: MockTokenClient<'a>,
+    pub blend_fixture: BlendFixture<'a>,
+    pub oracle: Address,
+    pub blend_pool: Address,
+    pub comet_pool: Address,
+    pub vault: Address,
+    pub vault_client: BlendVaultContractClient<'a>,
+    pub usdc_reserve_index: u32,
+    pub blnd_reserve_token_id: u32,
+}
+
+impl<'a> TestFixture<'a> {
+    pub fn new() -> Self {
+        Self::new_with_comet(|_, _, _, _, blend_fixture| {
+            blend_fixture.backstop_token.address.clone()
+        })
+    }
+
+    pub fn new_with_comet<F>(build_comet: F) -> Self
+    where
+        F: FnOnce(&Env, &Address, &Address, &Address, &BlendFixture) -> Address,
+    {
+        let env = Env::default();
+        env.mock_all_auths_allowing_non_root_auth();
+        env.ledger().with_mut(|li| {
+            li.timestamp = START_TIMESTAMP;
+            li.sequence_number = 100;
+            li.protocol_version = 23;
+        });
+
+        let deployer = Address::generate(&env);
+        let user = Address::generate(&env);
+
+        let usdc_token = env.register_contract_wasm(None, MockTokenWASM);
+        let usdc_client = MockTokenClient::new(&env, &usdc_token);
+        usdc_client.initialize(
+            &deployer,
+            &7,
+            &String::from_str(&env, "USD Coin"),
+            &String::from_str(&env, "USDC"),
+        );
+
+        let blnd_token = env.register_contract_wasm(None, MockTokenWASM);
+        let blnd_client = MockTokenClient::new(&env, &blnd_token);
+        blnd_client.initialize(
+            &deployer,
+            &7,
+            &String::from_str(&env, "Blend Token"),
+            &String::from_str(&env, "BLND"),
+        );
+
+        let blend_fixture = BlendFixture::deploy(&env, &deployer, &blnd_token, &usdc_token);
+        let oracle = env.register(MockOracle, ());
+
+        let blend_pool = blend_fixture.pool_factory.mock_all_auths().deploy(
+            &deployer,
+            &String::from_str(&env, "Test Pool"),
+            &BytesN::<32>::random(&env),
+            &oracle,
+            &0_1000000,
+            &4,
+            &1_0000000,
+        );
+        let pool_client = pool::Client::new(&env, &blend_pool);
+
+        let usdc_reserve_index = 0u32;
+        let mut usdc_reserve_config = default_reserve_config();
+        usdc_reserve_config.index = usdc_reserve_index;
+        pool_client
+            .mock_all_auths()
+            .queue_set_reserve(&usdc_token, &usdc_reserve_config);
+        pool_client.mock_all_auths().set_reserve(&usdc_token);
+
+        let blnd_reserve_index = 1u32;
+        let mut blnd_reserve_config = default_reserve_config();
+        blnd_reserve_config.index = blnd_reserve_index;
+        pool_client
+            .mock_all_auths()
+            .queue_set_reserve(&blnd_token, &blnd_reserve_config);
+        pool_client.mock_all_auths().set_reserve(&blnd_token);
+
+        let emission_metadata = vec![
+            &env,
+            pool::ReserveEmissionMetadata {
+                res_index: usdc_reserve_index,
+                res_type: 1,
+                share: 1_0000000,
+            },
+        ];
+        pool_client
+            .mock_all_auths()
+            .set_emissions_config(&emission_metadata);
+
+        blend_fixture
+            .backstop
+            .mock_all_auths()
+            .deposit(&deployer, &blend_pool, &50_000_0000000);
+        blend_fixture
+            .backstop
+            .mock_all_auths()
+            .add_reward(&blend_pool, &Option::<Address>::None);
+        blend_fixture.backstop.mock_all_auths().drop();
+        blend_fixture.backstop.mock_all_auths().distribute();
+        pool_client.mock_all_auths().set_status(&3);
+        pool_client.mock_all_auths().update_status();
+
+        let comet_pool = build_comet(&env, &deployer, &blnd_token, &usdc_token, &blend_fixture);
+        let blnd_reserve_token_id = usdc_reserve_index * 2 + 1;
+        let vault = env.register(BlendVaultContract, ());
+        let vault_client = BlendVaultContractClient::new(&env, &vault);
+        vault_client.initialize(
+            &usdc_token,
+            &0,
+            &blend_pool,
+            &blnd_token,
+            &comet_pool,
+            &deployer,
+            &Some(String::from_str(&env, "BLEND VAULT")),
+            &Some(String::from_str(&env, "BV")),
+        );
+
+        usdc_client.mint(&user, &1_000_000_0000000);
+        blnd_client.mint(&user, &1_000_000_0000000);
+        usdc_client.approve(&user, &vault, &i128::MAX, &200);
+
+        Self {
+            env,
+            deployer,
+            user,
+            usdc_token,
+            usdc_client,
+            blnd_token,
+            blnd_client,
+            blend_fixture,
+            oracle,
+            blend_pool,
+            comet_pool,
+            vault,
+            vault_client,
+            usdc_reserve_index,
+            blnd_reserve_token_id,
+        }
+    }
+
+    pub fn pool_client(&self) -> pool::Client<'a> {
+        pool::Client::new(&self.env, &self.blend_pool)
+    }
+
+    pub fn advance_time(&self, seconds: u64) {
+        let new_ts = self.env.ledger().timestamp() + seconds;
+        self.env.ledger().with_mut(|li| {
+            li.timestamp = new_ts;
+            li.sequence_number += 1;
+        });
+    }
+
+    /// Push a round of BLND emissions from the emitter through the backstop
+    /// into the pool's reserves. Moves the clock forward by a little over
+    /// three weeks and returns whether the pool received anything.
+    pub fn accrue_emissions(&self) -> bool {
+        let mut emitted = self.run_emission_cycle();
+        if emitted == 0 {
+            self.advance_time(EMISSION_RETRY_DELAY_SECONDS);
+            emitted = self.run_emission_cycle();
+        }
+        if emitted == 0 {
+            return false;
+        }
+        self.advance_time(GULP_DELAY_SECONDS);
+        let gulped = self.pool_client().mock_all_auths().gulp_emissions();
+        if gulped > 0 {
+            self.advance_time(BACKSTOP_WAIT_SECONDS);
+            true
+        } else {
+            false
+        }
+    }
+
+    fn run_emission_cycle(&self) -> i128 {
+        self.advance_time(EMITTER_WAIT_SECONDS);
+        self.blend_fixture.emitter.mock_all_auths().distribute();
+        self.advance_time(BACKSTOP_WAIT_SECONDS);
+        self.blend_fixture.backstop.mock_all_auths().distribute()
+    }
+
+    pub fn claim_rewards(&self, reserve_ids: &[u32]) -> i128 {
+        let mut ids = Vec::new(&self.env);
+        for id in reserve_ids {
+            ids.push_back(*id);
+        }
+        self.pool_client()
+            .mock_all_auths()
+            .claim(&self.vault, &ids, &self.vault)
+    }
+}
+
+impl Default for TestFixture<'_> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
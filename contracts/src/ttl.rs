@@ -0,0 +1,121 @@
+use soroban_sdk::{contracttype, Address, Env, IntoVal, Val};
+use stellar_tokens::fungible::StorageKey as FungibleStorageKey;
+
+use crate::{checkpoints, history, oracle, queue, DataKey};
+
+pub(crate) const DAY_IN_LEDGERS: u32 = 17280;
+pub(crate) const INSTANCE_TTL_THRESHOLD: u32 = 7 * DAY_IN_LEDGERS;
+pub(crate) const INSTANCE_EXTEND_TO: u32 = 30 * DAY_IN_LEDGERS;
+pub(crate) const PERSISTENT_TTL_THRESHOLD: u32 = 14 * DAY_IN_LEDGERS;
+pub(crate) const PERSISTENT_EXTEND_TO: u32 = 60 * DAY_IN_LEDGERS;
+
+/// Ledger counts used when extending storage TTLs, an entry is extended to
+/// `*_extend_to` once its remaining TTL drops below `*_threshold`
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct TtlConfig {
+    pub instance_threshold: u32,
+    pub instance_extend_to: u32,
+    pub persistent_threshold: u32,
+    pub persistent_extend_to: u32,
+}
+
+impl Default for TtlConfig {
+    fn default() -> Self {
+        TtlConfig {
+            instance_threshold: INSTANCE_TTL_THRESHOLD,
+            instance_extend_to: INSTANCE_EXTEND_TO,
+            persistent_threshold: PERSISTENT_TTL_THRESHOLD,
+            persistent_extend_to: PERSISTENT_EXTEND_TO,
+        }
+    }
+}
+
+pub(crate) fn config(e: &Env) -> TtlConfig {
+    e.storage()
+        .instance()
+        .get(&DataKey::TtlConfig)
+        .unwrap_or_default()
+}
+
+pub(crate) fn set_config(e: &Env, config: &TtlConfig) {
+    let max_ttl = e.storage().max_ttl();
+    if config.instance_threshold > config.instance_extend_to
+        || config.persistent_threshold > config.persistent_extend_to
+        || config.instance_extend_to > max_ttl
+        || config.persistent_extend_to > max_ttl
+    {
+        panic!("Invalid TTL config");
+    }
+    e.storage().instance().set(&DataKey::TtlConfig, config);
+}
+
+/// Extend the contract instance (and code) TTL
+pub(crate) fn extend_instance(e: &Env) {
+    let config = config(e);
+    e.storage()
+        .instance()
+        .extend_ttl(config.instance_threshold, config.instance_extend_to);
+}
+
+fn extend_if_present<K: IntoVal<Env, Val>>(e: &Env, key: &K, config: &TtlConfig) {
+    if e.storage().persistent().has(key) {
+        e.storage().persistent().extend_ttl(
+            key,
+            config.persistent_threshold,
+            config.persistent_extend_to,
+        );
+    }
+}
+
+/// Extend a single persistent entry that was just written
+pub(crate) fn extend_persistent<K: IntoVal<Env, Val>>(e: &Env, key: &K) {
+    let config = config(e);
+    e.storage().persistent().extend_ttl(
+        key,
+        config.persistent_threshold,
+        config.persistent_extend_to,
+    );
+}
+
+/// Extend every persistent entry the vault keeps for `account`
+pub(crate) fn extend_account(e: &Env, account: &Address) {
+    let config = config(e);
+    extend_if_present(e, &FungibleStorageKey::Balance(account.clone()), &config);
+    let depositor_key = DataKey::IsDepositor(account.clone());
+    if let Some(index) = e.storage().persistent().get::<_, u32>(&depositor_key) {
+        extend_if_present(e, &depositor_key, &config);
+        extend_if_present(e, &DataKey::Depositor(index), &config);
+    }
+    extend_if_present(e, &DataKey::Points(account.clone()), &config);
+    extend_if_present(e, &DataKey::PendingRedeem(account.clone()), &config);
+    extend_if_present(e, &DataKey::CostBasis(account.clone()), &config);
+    let checkpoints = checkpoints::range_of(e, account);
+    if checkpoints.end > 0 {
+        extend_if_present(e, &DataKey::BalanceCheckpoints(account.clone()), &config);
+        extend_if_present(
+            e,
+            &DataKey::BalanceCheckpoint(account.clone(), checkpoints.end - 1),
+            &config,
+        );
+    }
+}
+
+/// Extend the persistent entries shared by all accounts
+pub(crate) fn extend_shared(e: &Env) {
+    let config = config(e);
+    let checkpoints = checkpoints::supply_range(e);
+    if checkpoints.end > 0 {
+        extend_if_present(e, &DataKey::SupplyCheckpoints, &config);
+        extend_if_present(e, &DataKey::SupplyCheckpoint(checkpoints.end - 1), &config);
+    }
+    if let Some(key) = oracle::latest_key(e) {
+        extend_if_present(e, &key, &config);
+    }
+    if let Some(key) = history::latest_key(e) {
+        extend_if_present(e, &key, &config);
+    }
+    for id in queue::front(e) {
+        extend_if_present(e, &DataKey::RedeemRequest(id), &config);
+    }
+}
@@ -14,14 +14,15 @@ NC='\033[0m'
 NETWORK="mainnet"
 SOURCE_ACCOUNT="${STELLAR_ACCOUNT:-james}"  # Set via environment variable
 
-USDC_ADDRESS="${USDC_ADDRESS:-CCW67TSZV3SSS2HXMBQ5JFGCKJNXKZM7UQUWUZPUTHXSTZLEO7SJMI75}"
+ASSET_ADDRESS="${ASSET_ADDRESS:-CCW67TSZV3SSS2HXMBQ5JFGCKJNXKZM7UQUWUZPUTHXSTZLEO7SJMI75}"  # USDC by default
 BLEND_POOL="${BLEND_POOL:-CCCCIQSDILITHMM7PBSLVDT5MISSY7R26MNZXCX4H7J5JQ5FPIYOGYFS}"
-USDC_RESERVE_INDEX="${USDC_RESERVE_INDEX:-1}"  # USDC is at index 1 in Blend pool reserve list
 BLND_TOKEN="${BLND_TOKEN:-CD25MNVTZDL4Y3XBCPCJXGXATV5WUHHOWMYFF4YBEGU5FCPGMYTVG5JY}"
-BLND_RESERVE_INDEX="${BLND_RESERVE_INDEX:-3}"  # reserve_token_id for USDC supply = 1*2+1 = 3
+VAULT_NAME="${VAULT_NAME:-Blend Vault USDC}"
+VAULT_SYMBOL="${VAULT_SYMBOL:-bvUSDC}"
 COMET_POOL="${COMET_POOL:-CAS3FL6TLZKDGGSISDBWGGPXT3NRR4DYTZD7YOD3HMYO6LTJUVGRVEAM}"
+OWNER="${OWNER:-}"  # Vault admin, defaults to the deployer account address
 
-DECIMALS_OFFSET=0  # Same decimals as USDC (7)
+DECIMALS_OFFSET=0  # Same decimals as the asset
 
 # WASM output path
 WASM_PATH="target/wasm32v1-none/release/blend_vault.wasm"
@@ -32,7 +33,7 @@ WASM_PATH="target/wasm32v1-none/release/blend_vault.wasm"
 
 # Step 2: Build the contract
 echo -e "${YELLOW}Step 2: Building contract for WASM target...${NC}"
-stellar contract build
+stellar contract build --package blend-vault
 
 # Verify WASM file exists
 if [ ! -f "$WASM_PATH" ]; then
@@ -70,21 +71,25 @@ fi
 # Step 4: Validate configuration
 echo -e "${YELLOW}Step 4: Validating configuration...${NC}"
 
+if [ -z "$OWNER" ]; then
+    OWNER=$(stellar keys address "$SOURCE_ACCOUNT")
+fi
+
 if [ -z "$COMET_POOL" ]; then
     echo -e "${RED}Error: COMET_POOL address not set${NC}"
-    echo "Please set the Comet pool address for BLND-USDC swaps"
+    echo "Please set the Comet pool address for swapping BLND into the asset"
     exit 1
 fi
 
 echo "Configuration:"
 echo "  Network: $NETWORK"
 echo "  Source Account: $SOURCE_ACCOUNT"
-echo "  USDC Address: $USDC_ADDRESS"
+echo "  Asset Address: $ASSET_ADDRESS"
 echo "  Blend Pool: $BLEND_POOL"
-echo "  USDC Reserve Index: $USDC_RESERVE_INDEX"
 echo "  BLND Token: $BLND_TOKEN"
-echo "  BLND Reserve Index: $BLND_RESERVE_INDEX"
+echo "  Name / Symbol: $VAULT_NAME / $VAULT_SYMBOL"
 echo "  Comet Pool: $COMET_POOL"
+echo "  Owner: $OWNER"
 echo "  Decimals Offset: $DECIMALS_OFFSET"
 echo ""
 
@@ -125,13 +130,13 @@ echo ""
 #if [[ ! $REPLY =~ ^[Yy]$ ]]; then
 #    echo "Initialization skipped. You can initialize later with:"
 #    echo "  stellar contract invoke --id $CONTRACT_ID --network $NETWORK -- initialize \\"
-#    echo "    --asset $USDC_ADDRESS \\"
+#    echo "    --asset $ASSET_ADDRESS \\"
 #    echo "    --decimals_offset $DECIMALS_OFFSET \\"
 #    echo "    --blend_pool $BLEND_POOL \\"
-#    echo "    --usdc_reserve_index $USDC_RESERVE_INDEX \\"
 #    echo "    --blnd_token $BLND_TOKEN \\"
-#    echo "    --blnd_reserve_index $BLND_RESERVE_INDEX \\"
-#    echo "    --comet_pool $COMET_POOL"
+#    echo "    --comet_pool $COMET_POOL \\"
+#    echo "    --owner $OWNER \\"
+#    echo "    --name \"$VAULT_NAME\" --symbol $VAULT_SYMBOL"
 #    exit 0
 #fi
 
@@ -144,13 +149,14 @@ stellar contract invoke \
     --source "$SOURCE_ACCOUNT" \
     --network "$NETWORK" \
     -- initialize \
-    --asset "$USDC_ADDRESS" \
+    --asset "$ASSET_ADDRESS" \
     --decimals_offset "$DECIMALS_OFFSET" \
     --blend_pool "$BLEND_POOL" \
-    --usdc_reserve_index "$USDC_RESERVE_INDEX" \
     --blnd_token "$BLND_TOKEN" \
-    --blnd_reserve_index "$BLND_RESERVE_INDEX" \
-    --comet_pool "$COMET_POOL"
+    --comet_pool "$COMET_POOL" \
+    --owner "$OWNER" \
+    --name "$VAULT_NAME" \
+    --symbol "$VAULT_SYMBOL"
 
 echo -e "${GREEN}✓ Contract initialized${NC}"
 
@@ -170,12 +176,13 @@ Deployer: $SOURCE_ACCOUNT
 Contract ID: $CONTRACT_ID
 
 Configuration:
-  USDC Address: $USDC_ADDRESS
+  Asset Address: $ASSET_ADDRESS
   Blend Pool: $BLEND_POOL
-  USDC Reserve Index: $USDC_RESERVE_INDEX
   BLND Token: $BLND_TOKEN
-  BLND Reserve Index: $BLND_RESERVE_INDEX
+  Name: $VAULT_NAME
+  Symbol: $VAULT_SYMBOL
   Comet Pool: $COMET_POOL
+  Owner: $OWNER
   Decimals Offset: $DECIMALS_OFFSET
 
 Stellar Expert: https://stellar.expert/explorer/public/contract/$CONTRACT_ID
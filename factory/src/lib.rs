@@ -0,0 +1,237 @@
+#![no_std]
+
+//! Factory for Blend Vaults.
+//!
+//! Stores the vault WASM hash and deploys one vault per asset and Blend pool,
+//! calling `initialize` in the same invocation as the deployment so nobody can
+//! initialize a freshly deployed vault with their own configuration first.
+//! Every deployed vault is kept in an indexed registry.
+
+use soroban_sdk::{
+    contract, contractclient, contractevent, contractimpl, contracttype, Address, BytesN, Env,
+    String, Vec,
+};
+use stellar_access::ownable::{self as ownable, Ownable};
+use stellar_macros::{default_impl, only_owner};
+
+const DAY_IN_LEDGERS: u32 = 17280;
+const INSTANCE_TTL_THRESHOLD: u32 = 7 * DAY_IN_LEDGERS;
+const INSTANCE_EXTEND_TO: u32 = 30 * DAY_IN_LEDGERS;
+const PERSISTENT_TTL_THRESHOLD: u32 = 14 * DAY_IN_LEDGERS;
+const PERSISTENT_EXTEND_TO: u32 = 60 * DAY_IN_LEDGERS;
+/// Upper bound on entries returned by a single page, keeps reads within budget.
+const MAX_PAGE_SIZE: u32 = 200;
+
+#[contract]
+pub struct BlendVaultFactory;
+
+// EVENTS
+
+#[contractevent]
+pub struct VaultDeployedEvent {
+    pub index: u32,
+    pub vault: Address,
+    pub asset: Address,
+    pub blend_pool: Address,
+    pub owner: Address,
+}
+
+#[contractevent]
+pub struct VaultWasmUpdatedEvent {
+    pub wasm_hash: BytesN<32>,
+}
+
+// STORAGE KEYS
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum DataKey {
+    VaultWasmHash,
+    VaultCount,
+    Vault(u32),
+    VaultIndex(Address),
+}
+
+/// BLND emissions the vault claims and compounds
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct RewardConfig {
+    pub blnd_token: Address,
+}
+
+/// Where claimed BLND is swapped back into the vault's asset
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct SwapRoute {
+    pub comet_pool: Address,
+}
+
+/// A vault deployed by the factory
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct VaultInfo {
+    pub vault: Address,
+    pub asset: Address,
+    pub blend_pool: Address,
+    pub deployed_at: u64,
+}
+
+#[contractclient(name = "BlendVaultClient")]
+pub trait BlendVaultInterface {
+    fn initialize(
+        env: Env,
+        asset: Address,
+        decimals_offset: u32,
+        blend_pool: Address,
+        blnd_token: Address,
+        comet_pool: Address,
+        owner: Address,
+        name: Option<String>,
+        symbol: Option<String>,
+    );
+}
+
+#[contractimpl]
+impl BlendVaultFactory {
+    /// ### Arguments
+    /// * `owner` - May update the vault WASM hash and deploy vaults
+    /// * `vault_wasm_hash` - Hash of the uploaded Blend Vault WASM
+    pub fn __constructor(e: &Env, owner: Address, vault_wasm_hash: BytesN<32>) {
+        ownable::set_owner(e, &owner);
+        e.storage()
+            .instance()
+            .set(&DataKey::VaultWasmHash, &vault_wasm_hash);
+    }
+
+    pub fn vault_wasm_hash(e: &Env) -> BytesN<32> {
+        e.storage()
+            .instance()
+            .get(&DataKey::VaultWasmHash)
+            .expect("Vault WASM hash not set")
+    }
+
+    /// Use a new vault WASM for future deployments, existing vaults are unaffected
+    #[only_owner]
+    pub fn set_vault_wasm_hash(e: &Env, wasm_hash: BytesN<32>) {
+        e.storage()
+            .instance()
+            .set(&DataKey::VaultWasmHash, &wasm_hash);
+        VaultWasmUpdatedEvent { wasm_hash }.publish(e);
+    }
+
+    /// Deploy and initialize a vault in one invocation, returning its address.
+    /// ### Arguments
+    /// * `asset` - The underlying asset address
+    /// * `decimals_offset` - Extra share decimals over the asset's decimals
+    /// * `blend_pool` - The Blend pool contract address, the vault reads the
+    ///   asset's reserve index from it
+    /// * `reward` - BLND emissions to claim and compound
+    /// * `swap` - Comet pool used to swap BLND into the asset
+    /// * `owner` - The vault admin
+    /// * `name` - Share token name, derived from the asset symbol when `None`
+    /// * `symbol` - Share token symbol, derived from the asset symbol when `None`
+    /// * `salt` - Deployment salt, the vault address is derived from the factory and salt
+    #[only_owner]
+    pub fn deploy_vault(
+        e: &Env,
+        asset: Address,
+        decimals_offset: u32,
+        blend_pool: Address,
+        reward: RewardConfig,
+        swap: SwapRoute,
+        owner: Address,
+        name: Option<String>,
+        symbol: Option<String>,
+        salt: BytesN<32>,
+    ) -> Address {
+        let vault = e
+            .deployer()
+            .with_current_contract(salt)
+            .deploy_v2(Self::vault_wasm_hash(e), ());
+        BlendVaultClient::new(e, &vault).initialize(
+            &asset,
+            &decimals_offset,
+            &blend_pool,
+            &reward.blnd_token,
+            &swap.comet_pool,
+            &owner,
+            &name,
+            &symbol,
+        );
+
+        let index = Self::vault_count(e);
+        let info = VaultInfo {
+            vault: vault.clone(),
+            asset: asset.clone(),
+            blend_pool: blend_pool.clone(),
+            deployed_at: e.ledger().timestamp(),
+        };
+        let entry_key = DataKey::Vault(index);
+        let index_key = DataKey::VaultIndex(vault.clone());
+        e.storage().persistent().set(&entry_key, &info);
+        e.storage().persistent().set(&index_key, &index);
+        e.storage().persistent().extend_ttl(
+            &entry_key,
+            PERSISTENT_TTL_THRESHOLD,
+            PERSISTENT_EXTEND_TO,
+        );
+        e.storage().persistent().extend_ttl(
+            &index_key,
+            PERSISTENT_TTL_THRESHOLD,
+            PERSISTENT_EXTEND_TO,
+        );
+        e.storage()
+            .instance()
+            .set(&DataKey::VaultCount, &(index + 1));
+        e.storage()
+            .instance()
+            .extend_ttl(INSTANCE_TTL_THRESHOLD, INSTANCE_EXTEND_TO);
+
+        VaultDeployedEvent {
+            index,
+            vault: vault.clone(),
+            asset,
+            blend_pool,
+            owner,
+        }
+        .publish(e);
+        vault
+    }
+
+    /// Number of vaults deployed by the factory
+    pub fn vault_count(e: &Env) -> u32 {
+        e.storage()
+            .instance()
+            .get(&DataKey::VaultCount)
+            .unwrap_or(0)
+    }
+
+    pub fn get_vault(e: &Env, index: u32) -> Option<VaultInfo> {
+        e.storage().persistent().get(&DataKey::Vault(index))
+    }
+
+    /// Deployed vaults in deployment order from `start`, at most `limit` entries
+    pub fn get_vaults(e: &Env, start: u32, limit: u32) -> Vec<VaultInfo> {
+        let end = start
+            .saturating_add(limit.min(MAX_PAGE_SIZE))
+            .min(Self::vault_count(e));
+        let mut vaults = Vec::new(e);
+        for index in start..end {
+            if let Some(info) = Self::get_vault(e, index) {
+                vaults.push_back(info);
+            }
+        }
+        vaults
+    }
+
+    /// Whether `vault` was deployed by this factory
+    pub fn is_vault(e: &Env, vault: Address) -> bool {
+        e.storage().persistent().has(&DataKey::VaultIndex(vault))
+    }
+}
+
+#[default_impl]
+#[contractimpl]
+impl Ownable for BlendVaultFactory {}
+
+#[cfg(test)]
+mod test;
@@ -0,0 +1,192 @@
+// Amounts are written as whole units and 7 decimals, e.g. 1000_0000000
+#![allow(clippy::inconsistent_digit_grouping)]
+
+extern crate std;
+
+use blend_vault::mocks::{RealisticMockBlendPool, RealisticMockBlendPoolClient};
+use soroban_sdk::{
+    testutils::Address as _,
+    token::{StellarAssetClient, TokenClient},
+    Address, BytesN, Env,
+};
+
+use super::*;
+
+/// The vault as deployed, from `stellar contract build`. The factory can only
+/// deploy uploaded code, so these tests need the WASM rather than the crate.
+mod vault {
+    soroban_sdk::contractimport!(file = "../target/wasm32v1-none/release/blend_vault.wasm");
+}
+
+struct FactoryFixture<'a> {
+    env: Env,
+    admin: Address,
+    user: Address,
+    usdc_token: Address,
+    blnd_token: Address,
+    blend_pool: Address,
+    factory: BlendVaultFactoryClient<'a>,
+}
+
+impl<'a> FactoryFixture<'a> {
+    fn new() -> Self {
+        let env = Env::default();
+        env.mock_all_auths();
+
+        let admin = Address::generate(&env);
+        let user = Address::generate(&env);
+        let usdc_token = env
+            .register_stellar_asset_contract_v2(admin.clone())
+            .address();
+        let blnd_token = env
+            .register_stellar_asset_contract_v2(admin.clone())
+            .address();
+        StellarAssetClient::new(&env, &usdc_token).mint(&user, &1_000_000_0000000);
+
+        let blend_pool = env.register(RealisticMockBlendPool, ());
+        RealisticMockBlendPoolClient::new(&env, &blend_pool).set_reward_token(&blnd_token);
+
+        let wasm_hash = env.deployer().upload_contract_wasm(vault::WASM);
+        let factory = env.register(BlendVaultFactory, (&admin, &wasm_hash));
+        let factory = BlendVaultFactoryClient::new(&env, &factory);
+
+        Self {
+            env,
+            admin,
+            user,
+            usdc_token,
+            blnd_token,
+            blend_pool,
+            factory,
+        }
+    }
+
+    fn try_deploy(&self, salt: u8) -> Option<Address> {
+        self.factory
+            .try_deploy_vault(
+                &self.usdc_token,
+                &0,
+                &self.blend_pool,
+                &RewardConfig {
+                    blnd_token: self.blnd_token.clone(),
+                },
+                &SwapRoute {
+                    comet_pool: Address::generate(&self.env),
+                },
+                &self.admin,
+                &None,
+                &None,
+                &BytesN::from_array(&self.env, &[salt; 32]),
+            )
+            .ok()
+            .map(Result::unwrap)
+    }
+
+    fn deploy(&self, salt: u8) -> Address {
+        self.try_deploy(salt).expect("Vault deployment failed")
+    }
+}
+
+#[test]
+fn test_factory_deploys_initialized_vault() {
+    let fixture = FactoryFixture::new();
+    let vault = fixture.deploy(1);
+
+    let vault_client = vault::Client::new(&fixture.env, &vault);
+    assert!(vault_client.is_initialized());
+    assert_eq!(vault_client.query_asset(), fixture.usdc_token);
+    assert_eq!(vault_client.get_owner(), Some(fixture.admin.clone()));
+    let primary = vault_client.get_strategies().get(0).unwrap();
+    assert_eq!(primary.strategy, fixture.blend_pool);
+    assert_eq!(
+        primary.kind,
+        vault::StrategyKind::Blend(vault::BlendReserve {
+            reserve_index: 0,
+            reward_token_id: 1,
+        })
+    );
+
+    // Deployed vaults work like any other
+    TokenClient::new(&fixture.env, &fixture.usdc_token).approve(
+        &fixture.user,
+        &vault,
+        &i128::MAX,
+        &200,
+    );
+    vault_client.deposit(&100_0000000, &fixture.user, &fixture.user, &fixture.user);
+    assert_eq!(vault_client.total_assets(), 100_0000000);
+}
+
+#[test]
+fn test_factory_vaults_derive_metadata_from_asset() {
+    let fixture = FactoryFixture::new();
+    let vault_client = vault::Client::new(&fixture.env, &fixture.deploy(1));
+
+    let asset_symbol = TokenClient::new(&fixture.env, &fixture.usdc_token).symbol();
+    assert_eq!(
+        std::format!("{}", vault_client.name()),
+        std::format!("Blend Vault {}", asset_symbol)
+    );
+    assert_eq!(
+        std::format!("{}", vault_client.symbol()),
+        std::format!("bv{}", asset_symbol)
+    );
+}
+
+#[test]
+fn test_factory_vault_cannot_be_reinitialized() {
+    let fixture = FactoryFixture::new();
+    let vault = fixture.deploy(1);
+
+    let attacker = Address::generate(&fixture.env);
+    let result = vault::Client::new(&fixture.env, &vault).try_initialize(
+        &fixture.usdc_token,
+        &0,
+        &fixture.blend_pool,
+        &fixture.blnd_token,
+        &attacker,
+        &attacker,
+        &None,
+        &None,
+    );
+    assert!(result.is_err());
+}
+
+#[test]
+fn test_factory_registry_tracks_deployments() {
+    let fixture = FactoryFixture::new();
+    assert_eq!(fixture.factory.vault_count(), 0);
+
+    let first = fixture.deploy(1);
+    let second = fixture.deploy(2);
+
+    let factory = &fixture.factory;
+    assert_eq!(factory.vault_count(), 2);
+    let vaults = factory.get_vaults(&0, &10);
+    assert_eq!(vaults.len(), 2);
+    assert_eq!(vaults.get(0).unwrap().vault, first);
+    assert_eq!(vaults.get(1).unwrap().vault, second);
+    assert_eq!(vaults.get(1).unwrap().asset, fixture.usdc_token);
+    assert_eq!(vaults.get(1).unwrap().blend_pool, fixture.blend_pool);
+    assert_eq!(factory.get_vault(&1).unwrap().vault, second);
+    assert!(factory.is_vault(&first));
+    assert!(!factory.is_vault(&fixture.blend_pool));
+}
+
+#[test]
+fn test_factory_rejects_reused_salt() {
+    let fixture = FactoryFixture::new();
+    fixture.deploy(1);
+
+    assert_eq!(fixture.try_deploy(1), None);
+    assert_eq!(fixture.factory.vault_count(), 1);
+}
+
+#[test]
+fn test_only_owner_deploys_vaults() {
+    let fixture = FactoryFixture::new();
+    fixture.env.set_auths(&[]);
+
+    assert_eq!(fixture.try_deploy(1), None);
+    assert_eq!(fixture.factory.vault_count(), 0);
+}
@@ -0,0 +1,36 @@
+use std::fmt;
+
+#[derive(Debug)]
+pub enum Error {
+    /// Input is not valid JSON or not shaped like decoded events
+    Json(serde_json::Error),
+    /// An event lacks a topic the indexer needs
+    MissingTopic { event: String, index: usize },
+    /// An event's data lacks a field the indexer needs
+    MissingField { event: String, field: &'static str },
+    /// A value that should be an integer amount is not one
+    InvalidAmount(String),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Json(err) => write!(f, "invalid JSON: {}", err),
+            Error::MissingTopic { event, index } => {
+                write!(f, "{} event is missing topic {}", event, index)
+            }
+            Error::MissingField { event, field } => {
+                write!(f, "{} event is missing field `{}`", event, field)
+            }
+            Error::InvalidAmount(value) => write!(f, "invalid amount: {}", value),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<serde_json::Error> for Error {
+    fn from(err: serde_json::Error) -> Self {
+        Error::Json(err)
+    }
+}
@@ -0,0 +1,153 @@
+use serde::Deserialize;
+use serde_json::Value;
+
+use crate::Error;
+
+/// One vault contract event as returned by the RPC `getEvents` method, with
+/// its topics and data decoded from XDR to JSON. Addresses and symbols are
+/// strings, maps are objects and i128 amounts are numbers or decimal strings.
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+pub struct DecodedEvent {
+    pub ledger: u32,
+    /// Close time of the ledger, in seconds
+    pub timestamp: u64,
+    pub topics: Vec<Value>,
+    pub data: Value,
+}
+
+/// Vault totals reported by an event, the share price is
+/// `total_assets / total_supply`
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Totals {
+    pub total_assets: i128,
+    pub total_supply: i128,
+}
+
+/// The part of a decoded event the indexer acts on
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Event {
+    /// Share token mint
+    Mint { to: String, amount: i128 },
+    /// Share token burn
+    Burn { from: String, amount: i128 },
+    /// Share token transfer
+    Transfer {
+        from: String,
+        to: String,
+        amount: i128,
+    },
+    /// Deposit, mint or zap deposit, which registers `receiver` as a
+    /// depositor. Events published before the vault reported totals carry
+    /// none.
+    Deposit {
+        receiver: String,
+        totals: Option<Totals>,
+    },
+    /// Any other vault operation that reports the totals after it
+    Operation { totals: Option<Totals> },
+    /// Points epoch reset by the owner
+    PointsEpochReset { epoch: u32 },
+    /// Events with no effect on the indexed state, such as `config_changed`
+    Other,
+}
+
+impl Event {
+    pub fn decode(event: &DecodedEvent) -> Result<Self, Error> {
+        let Some(name) = event.topics.first().and_then(Value::as_str) else {
+            return Ok(Event::Other);
+        };
+        Ok(match name {
+            "mint" => Event::Mint {
+                to: topic(event, name, 1)?,
+                amount: token_amount(&event.data)?,
+            },
+            "burn" => Event::Burn {
+                from: topic(event, name, 1)?,
+                amount: token_amount(&event.data)?,
+            },
+            "transfer" => Event::Transfer {
+                from: topic(event, name, 1)?,
+                to: topic(event, name, 2)?,
+                amount: token_amount(&event.data)?,
+            },
+            "deposit_event" | "mint_event" | "zap_deposit_event" => Event::Deposit {
+                receiver: address_field(&event.data, name, "receiver")?,
+                totals: totals(&event.data)?,
+            },
+            "withdraw_event"
+            | "redeem_event"
+            | "zap_redeem_event"
+            | "compound_event"
+            | "redeem_requested_event"
+            | "redeem_fulfilled_event" => Event::Operation {
+                totals: totals(&event.data)?,
+            },
+            "points_epoch_reset_event" => Event::PointsEpochReset {
+                epoch: amount(data_field(&event.data, name, "epoch")?)? as u32,
+            },
+            _ => Event::Other,
+        })
+    }
+}
+
+/// Parse an i128 given as a JSON number or decimal string
+pub(crate) fn amount(value: &Value) -> Result<i128, Error> {
+    let parsed = match value {
+        Value::Number(number) => number
+            .as_i64()
+            .map(i128::from)
+            .or_else(|| number.as_u64().map(i128::from)),
+        Value::String(string) => string.parse().ok(),
+        _ => None,
+    };
+    parsed.ok_or_else(|| Error::InvalidAmount(value.to_string()))
+}
+
+/// Token events carry the amount on its own or in a map next to a muxed id
+fn token_amount(data: &Value) -> Result<i128, Error> {
+    match data.get("amount") {
+        Some(value) => amount(value),
+        None => amount(data),
+    }
+}
+
+fn topic(event: &DecodedEvent, name: &str, index: usize) -> Result<String, Error> {
+    event
+        .topics
+        .get(index)
+        .and_then(Value::as_str)
+        .map(str::to_string)
+        .ok_or_else(|| Error::MissingTopic {
+            event: name.to_string(),
+            index,
+        })
+}
+
+fn data_field<'a>(data: &'a Value, name: &str, field: &'static str) -> Result<&'a Value, Error> {
+    data.get(field).ok_or_else(|| Error::MissingField {
+        event: name.to_string(),
+        field,
+    })
+}
+
+fn address_field(data: &Value, name: &str, field: &'static str) -> Result<String, Error> {
+    data.get(field)
+        .and_then(Value::as_str)
+        .map(str::to_string)
+        .ok_or_else(|| Error::MissingField {
+            event: name.to_string(),
+            field,
+        })
+}
+
+fn totals(data: &Value) -> Result<Option<Totals>, Error> {
+    let (Some(total_assets), Some(total_supply)) =
+        (data.get("total_assets"), data.get("total_supply"))
+    else {
+        return Ok(None);
+    };
+    Ok(Some(Totals {
+        total_assets: amount(total_assets)?,
+        total_supply: amount(total_supply)?,
+    }))
+}
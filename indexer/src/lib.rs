@@ -0,0 +1,44 @@
+//! Rebuilds Blend Vault state off-chain from the vault's contract events.
+//!
+//! The indexer replays decoded events in order and keeps per-account share
+//! balances, the depositor registry, participation points and the share
+//! price reported by every vault operation. The result can be reconciled
+//! against the contract's `get_depositors_snapshot`.
+
+use std::collections::BTreeMap;
+
+use serde_json::Value;
+
+mod error;
+mod event;
+mod state;
+
+pub use error::Error;
+pub use event::{DecodedEvent, Event, Totals};
+pub use state::{Indexer, Mismatch, PointsEpoch, PricePoint, Summary};
+
+/// Parse decoded events given either as one JSON array or as one JSON object
+/// per line
+pub fn read_events(input: &str) -> Result<Vec<DecodedEvent>, Error> {
+    if input.trim_start().starts_with('[') {
+        return Ok(serde_json::from_str(input)?);
+    }
+    input
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| serde_json::from_str(line).map_err(Error::from))
+        .collect()
+}
+
+/// Parse the output of `get_depositors_snapshot`, a JSON object from
+/// address to share balance
+pub fn read_snapshot(input: &str) -> Result<BTreeMap<String, i128>, Error> {
+    let snapshot: BTreeMap<String, Value> = serde_json::from_str(input)?;
+    snapshot
+        .into_iter()
+        .map(|(account, balance)| Ok((account, event::amount(&balance)?)))
+        .collect()
+}
+
+#[cfg(test)]
+mod test;
@@ -0,0 +1,74 @@
+use std::{
+    fs,
+    io::{self, Read},
+    process::ExitCode,
+};
+
+use blend_vault_indexer::{read_events, read_snapshot, Indexer};
+
+const USAGE: &str =
+    "usage: blend-vault-indexer [EVENTS_FILE | -] --decimals DECIMALS [--snapshot SNAPSHOT_FILE]
+
+Replays decoded vault events (a JSON array or one JSON object per line,
+read from stdin when no file or `-` is given) and prints the rebuilt state
+as JSON. DECIMALS is the vault's decimals(), the scale of its share price.
+With --snapshot, the depositor balances are reconciled against the
+output of get_depositors_snapshot and the exit code is 1 on any mismatch.";
+
+fn read_input(path: Option<&str>) -> io::Result<String> {
+    match path {
+        None | Some("-") => {
+            let mut input = String::new();
+            io::stdin().read_to_string(&mut input)?;
+            Ok(input)
+        }
+        Some(path) => fs::read_to_string(path),
+    }
+}
+
+fn run() -> Result<bool, Box<dyn std::error::Error>> {
+    let mut events_path = None;
+    let mut decimals = None;
+    let mut snapshot_path = None;
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--decimals" => decimals = Some(args.next().ok_or(USAGE)?.parse::<u32>()?),
+            "--snapshot" => snapshot_path = Some(args.next().ok_or(USAGE)?),
+            "-h" | "--help" => {
+                println!("{}", USAGE);
+                return Ok(true);
+            }
+            _ if events_path.is_none() => events_path = Some(arg),
+            _ => return Err(USAGE.into()),
+        }
+    }
+
+    let decimals = decimals.ok_or(USAGE)?;
+    let events = read_events(&read_input(events_path.as_deref())?)?;
+    let indexer = Indexer::from_events(decimals, &events)?;
+    println!("{}", serde_json::to_string_pretty(&indexer.summary())?);
+
+    let Some(snapshot_path) = snapshot_path else {
+        return Ok(true);
+    };
+    let mismatches = indexer.reconcile(&read_snapshot(&fs::read_to_string(snapshot_path)?)?);
+    for mismatch in &mismatches {
+        eprintln!(
+            "{}: indexed {} but snapshot has {}",
+            mismatch.account, mismatch.indexed, mismatch.snapshot
+        );
+    }
+    Ok(mismatches.is_empty())
+}
+
+fn main() -> ExitCode {
+    match run() {
+        Ok(true) => ExitCode::SUCCESS,
+        Ok(false) => ExitCode::FAILURE,
+        Err(err) => {
+            eprintln!("{}", err);
+            ExitCode::from(2)
+        }
+    }
+}
@@ -0,0 +1,273 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use serde::Serialize;
+
+use crate::{DecodedEvent, Error, Event, Totals};
+
+/// Vault totals reported by one event
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize)]
+pub struct PricePoint {
+    pub ledger: u32,
+    pub timestamp: u64,
+    pub total_assets: i128,
+    pub total_supply: i128,
+}
+
+impl PricePoint {
+    /// Assets per share with `decimals` decimals, `None` without shares
+    pub fn price(&self, decimals: u32) -> Option<i128> {
+        if self.total_supply <= 0 {
+            return None;
+        }
+        Some(self.total_assets * 10_i128.pow(decimals) / self.total_supply)
+    }
+}
+
+/// Current points epoch and when it started, mirroring the vault's
+/// `points_epoch`
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, Serialize)]
+pub struct PointsEpoch {
+    pub epoch: u32,
+    pub start: u64,
+}
+
+/// Share-seconds accrued within an epoch, stored the way the vault stores them
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+struct AccountPoints {
+    epoch: u32,
+    points: i128,
+    last_update: u64,
+}
+
+/// An account whose indexed balance differs from the on-chain snapshot
+#[derive(Clone, Debug, Eq, PartialEq, Serialize)]
+pub struct Mismatch {
+    pub account: String,
+    pub indexed: i128,
+    pub snapshot: i128,
+}
+
+/// Indexed state at the last applied event
+#[derive(Clone, Debug, Serialize)]
+pub struct Summary {
+    pub decimals: u32,
+    pub ledger: u32,
+    pub timestamp: u64,
+    pub total_supply: i128,
+    pub balances: BTreeMap<String, i128>,
+    pub depositors: Vec<String>,
+    pub points_epoch: PointsEpoch,
+    pub points: BTreeMap<String, i128>,
+    pub total_points: i128,
+    pub share_prices: Vec<PricePoint>,
+}
+
+/// Vault state rebuilt from its event stream. Events must be applied in the
+/// order they were published.
+#[derive(Clone, Debug, Default)]
+pub struct Indexer {
+    decimals: u32,
+    ledger: u32,
+    timestamp: u64,
+    balances: BTreeMap<String, i128>,
+    total_supply: i128,
+    depositors: Vec<String>,
+    is_depositor: BTreeSet<String>,
+    share_prices: Vec<PricePoint>,
+    epoch: PointsEpoch,
+    points: BTreeMap<String, AccountPoints>,
+    total_points: Option<AccountPoints>,
+}
+
+impl Indexer {
+    /// An empty state for a vault whose share token has `decimals` decimals,
+    /// as returned by the vault's `decimals()`
+    pub fn new(decimals: u32) -> Self {
+        Self {
+            decimals,
+            ..Self::default()
+        }
+    }
+
+    /// Build the state from a whole stream
+    pub fn from_events(decimals: u32, events: &[DecodedEvent]) -> Result<Self, Error> {
+        let mut indexer = Self::new(decimals);
+        for event in events {
+            indexer.apply(event)?;
+        }
+        Ok(indexer)
+    }
+
+    pub fn apply(&mut self, event: &DecodedEvent) -> Result<(), Error> {
+        self.ledger = event.ledger;
+        self.timestamp = event.timestamp;
+        match Event::decode(event)? {
+            Event::Mint { to, amount } => {
+                self.accrue_account(&to);
+                self.accrue_total();
+                *self.balances.entry(to).or_default() += amount;
+                self.total_supply += amount;
+            }
+            Event::Burn { from, amount } => {
+                self.accrue_account(&from);
+                self.accrue_total();
+                *self.balances.entry(from).or_default() -= amount;
+                self.total_supply -= amount;
+            }
+            Event::Transfer { from, to, amount } => {
+                self.accrue_account(&from);
+                self.accrue_account(&to);
+                *self.balances.entry(from).or_default() -= amount;
+                *self.balances.entry(to).or_default() += amount;
+            }
+            Event::Deposit { receiver, totals } => {
+                if self.is_depositor.insert(receiver.clone()) {
+                    self.depositors.push(receiver);
+                }
+                self.record_price(event, totals);
+            }
+            Event::Operation { totals } => self.record_price(event, totals),
+            Event::PointsEpochReset { epoch } => {
+                self.epoch = PointsEpoch {
+                    epoch,
+                    start: event.timestamp,
+                };
+            }
+            Event::Other => {}
+        }
+        Ok(())
+    }
+
+    pub fn balance(&self, account: &str) -> i128 {
+        self.balances.get(account).copied().unwrap_or(0)
+    }
+
+    pub fn total_supply(&self) -> i128 {
+        self.total_supply
+    }
+
+    /// Addresses registered as depositors, in registration order
+    pub fn depositors(&self) -> &[String] {
+        &self.depositors
+    }
+
+    /// Decimals of the vault's share token, the scale of `share_price`
+    pub fn decimals(&self) -> u32 {
+        self.decimals
+    }
+
+    pub fn share_prices(&self) -> &[PricePoint] {
+        &self.share_prices
+    }
+
+    /// Assets per share at `point`, with the vault's decimals like its
+    /// on-chain share price
+    pub fn share_price(&self, point: &PricePoint) -> Option<i128> {
+        point.price(self.decimals)
+    }
+
+    pub fn points_epoch(&self) -> PointsEpoch {
+        self.epoch
+    }
+
+    /// Share-seconds of `account` in the current epoch up to the last event
+    pub fn points_of(&self, account: &str) -> i128 {
+        self.accrued(self.points.get(account).copied(), self.balance(account))
+            .points
+    }
+
+    pub fn total_points(&self) -> i128 {
+        self.accrued(self.total_points, self.total_supply).points
+    }
+
+    /// What `get_depositors_snapshot` returns for this state: every
+    /// depositor with a non-zero balance
+    pub fn depositors_snapshot(&self) -> BTreeMap<String, i128> {
+        self.depositors
+            .iter()
+            .map(|depositor| (depositor.clone(), self.balance(depositor)))
+            .filter(|(_, balance)| *balance > 0)
+            .collect()
+    }
+
+    /// Compare against a snapshot read from the contract, returning every
+    /// account the two disagree on
+    pub fn reconcile(&self, snapshot: &BTreeMap<String, i128>) -> Vec<Mismatch> {
+        let indexed = self.depositors_snapshot();
+        let accounts: BTreeSet<&String> = indexed.keys().chain(snapshot.keys()).collect();
+        accounts
+            .into_iter()
+            .filter_map(|account| {
+                let indexed = indexed.get(account).copied().unwrap_or(0);
+                let snapshot = snapshot.get(account).copied().unwrap_or(0);
+                (indexed != snapshot).then(|| Mismatch {
+                    account: account.clone(),
+                    indexed,
+                    snapshot,
+                })
+            })
+            .collect()
+    }
+
+    pub fn summary(&self) -> Summary {
+        Summary {
+            decimals: self.decimals,
+            ledger: self.ledger,
+            timestamp: self.timestamp,
+            total_supply: self.total_supply,
+            balances: self
+                .balances
+                .iter()
+                .filter(|(_, balance)| **balance != 0)
+                .map(|(account, balance)| (account.clone(), *balance))
+                .collect(),
+            depositors: self.depositors.clone(),
+            points_epoch: self.epoch,
+            points: self
+                .balances
+                .keys()
+                .chain(self.points.keys())
+                .map(|account| (account.clone(), self.points_of(account)))
+                .filter(|(_, points)| *points != 0)
+                .collect(),
+            total_points: self.total_points(),
+            share_prices: self.share_prices.clone(),
+        }
+    }
+
+    fn record_price(&mut self, event: &DecodedEvent, totals: Option<Totals>) {
+        if let Some(totals) = totals {
+            self.share_prices.push(PricePoint {
+                ledger: event.ledger,
+                timestamp: event.timestamp,
+                total_assets: totals.total_assets,
+                total_supply: totals.total_supply,
+            });
+        }
+    }
+
+    /// Bring stored points forward to the current event, `balance` must be
+    /// the balance held since the last update
+    fn accrued(&self, stored: Option<AccountPoints>, balance: i128) -> AccountPoints {
+        let now = self.timestamp;
+        let (points, since) = match stored {
+            Some(stored) if stored.epoch == self.epoch.epoch => (stored.points, stored.last_update),
+            Some(stored) => (0, stored.last_update.max(self.epoch.start)),
+            None => (0, now),
+        };
+        AccountPoints {
+            epoch: self.epoch.epoch,
+            points: points + balance * now.saturating_sub(since) as i128,
+            last_update: now,
+        }
+    }
+
+    fn accrue_account(&mut self, account: &str) {
+        let updated = self.accrued(self.points.get(account).copied(), self.balance(account));
+        self.points.insert(account.to_string(), updated);
+    }
+
+    fn accrue_total(&mut self) {
+        self.total_points = Some(self.accrued(self.total_points, self.total_supply));
+    }
+}
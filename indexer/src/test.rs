@@ -0,0 +1,191 @@
+use super::*;
+
+const EVENTS: &str = include_str!("../fixtures/events.json");
+const SNAPSHOT: &str = include_str!("../fixtures/depositors_snapshot.json");
+
+const ALICE: &str = "GAUITM3KJG7ONJ6NBE3EI7KIDSVVGPRQASIW3LMZRBXGSS7HHOJV6U3H";
+const BOB: &str = "GBAX32UAZ63QJXM5OKOJKR3Z3A5SCGBBAVXF44G4F6KNEMPWCZ3CRBUZ";
+const CAROL: &str = "GC3FO5UHDEMINGIPQUMI3CEFYARJHIFGY5V5ZTS6MIA52OTHMCLZGVZC";
+const VAULT: &str = "CDQ63Q5FZHTRDYW2LRULYEDAGV2JPTS2KSLUV6E3CPA5VS3WXKD53OZF";
+
+/// The fixture vault's `decimals()`
+const DECIMALS: u32 = 7;
+const HOUR: u64 = 3600;
+const START: u64 = 1_700_000_000;
+
+fn fixture() -> Indexer {
+    Indexer::from_events(DECIMALS, &read_events(EVENTS).unwrap()).unwrap()
+}
+
+#[test]
+fn test_rebuilds_balances_and_supply() {
+    let indexer = fixture();
+    assert_eq!(indexer.balance(ALICE), 5_000_000_000);
+    assert_eq!(indexer.balance(BOB), 4_901_960_784);
+    assert_eq!(indexer.balance(CAROL), 1_000_000_000);
+    // Escrowed shares left the vault when the queued redemption was fulfilled
+    assert_eq!(indexer.balance(VAULT), 0);
+    assert_eq!(indexer.total_supply(), 10_901_960_784);
+    assert_eq!(
+        indexer.total_supply(),
+        [ALICE, BOB, CAROL]
+            .iter()
+            .map(|a| indexer.balance(a))
+            .sum::<i128>()
+    );
+}
+
+#[test]
+fn test_registers_depositors_in_order() {
+    let indexer = fixture();
+    // Carol only received shares by transfer, like the contract's registry
+    assert_eq!(indexer.depositors(), [ALICE.to_string(), BOB.to_string()]);
+}
+
+#[test]
+fn test_records_share_price_history() {
+    let indexer = fixture();
+    let prices = indexer.share_prices();
+    assert_eq!(prices.len(), 6);
+    assert_eq!(
+        prices[0],
+        PricePoint {
+            ledger: 1000,
+            timestamp: START,
+            total_assets: 10_000_000_000,
+            total_supply: 10_000_000_000,
+        }
+    );
+    assert_eq!(indexer.share_price(&prices[0]), Some(10_000_000));
+    // Compound raises the price without touching the supply
+    assert_eq!(prices[2].timestamp, START + 3 * HOUR);
+    assert_eq!(prices[2].total_assets, 15_300_000_000);
+    assert_eq!(indexer.share_price(&prices[2]), Some(10_267_105));
+    let last = prices.last().unwrap();
+    assert_eq!(last.ledger, 7480);
+    assert_eq!(last.total_supply, indexer.total_supply());
+    assert_eq!(last.total_assets, 11_193_157_895);
+}
+
+#[test]
+fn test_share_price_uses_vault_decimals() {
+    // A vault over a 6 decimal asset reports its share price with 6 decimals
+    let indexer = Indexer::from_events(6, &read_events(EVENTS).unwrap()).unwrap();
+    let prices = indexer.share_prices();
+    assert_eq!(indexer.decimals(), 6);
+    assert_eq!(indexer.share_price(&prices[0]), Some(1_000_000));
+    assert_eq!(indexer.share_price(&prices[2]), Some(1_026_710));
+    assert_eq!(indexer.summary().decimals, 6);
+}
+
+#[test]
+fn test_points_restart_with_the_epoch() {
+    let indexer = fixture();
+    assert_eq!(
+        indexer.points_epoch(),
+        PointsEpoch {
+            epoch: 1,
+            start: START + 6 * HOUR,
+        }
+    );
+    // Three hours at 500 shares since the reset
+    assert_eq!(indexer.points_of(ALICE), 54_000_000_000_000);
+    // Two hours at 100 shares since the transfer from Bob
+    assert_eq!(indexer.points_of(CAROL), 7_200_000_000_000);
+    assert_eq!(indexer.points_of(BOB), 63_741_176_467_200);
+    assert_eq!(indexer.points_of(VAULT), 3_600_000_000_000);
+    assert_eq!(indexer.total_points(), 128_541_176_467_200);
+}
+
+#[test]
+fn test_reconciles_with_depositors_snapshot() {
+    let indexer = fixture();
+    let snapshot = read_snapshot(SNAPSHOT).unwrap();
+    assert_eq!(indexer.depositors_snapshot(), snapshot);
+    assert!(indexer.reconcile(&snapshot).is_empty());
+}
+
+#[test]
+fn test_reconcile_reports_drift() {
+    let indexer = fixture();
+    let mut snapshot = read_snapshot(SNAPSHOT).unwrap();
+    snapshot.insert(BOB.to_string(), 4_000_000_000);
+    snapshot.insert(CAROL.to_string(), 1_000_000_000);
+    snapshot.remove(ALICE);
+
+    assert_eq!(
+        indexer.reconcile(&snapshot),
+        vec![
+            Mismatch {
+                account: ALICE.to_string(),
+                indexed: 5_000_000_000,
+                snapshot: 0,
+            },
+            Mismatch {
+                account: BOB.to_string(),
+                indexed: 4_901_960_784,
+                snapshot: 4_000_000_000,
+            },
+            Mismatch {
+                account: CAROL.to_string(),
+                indexed: 0,
+                snapshot: 1_000_000_000,
+            },
+        ]
+    );
+}
+
+#[test]
+fn test_reads_json_lines() {
+    let events = read_events(EVENTS).unwrap();
+    let lines: Vec<String> = events
+        .iter()
+        .map(|event| {
+            serde_json::json!({
+                "ledger": event.ledger,
+                "timestamp": event.timestamp,
+                "topics": event.topics,
+                "data": event.data,
+            })
+            .to_string()
+        })
+        .collect();
+    assert_eq!(read_events(&lines.join("\n")).unwrap(), events);
+}
+
+#[test]
+fn test_events_without_totals_still_register_depositors() {
+    let events = read_events(&format!(
+        r#"[
+            {{"ledger": 1, "timestamp": 10, "topics": ["mint", "{alice}"], "data": 100}},
+            {{"ledger": 1, "timestamp": 10, "topics": ["deposit_event"],
+              "data": {{"operator": "{alice}", "receiver": "{alice}", "assets": 100, "shares": 100}}}}
+        ]"#,
+        alice = ALICE
+    ))
+    .unwrap();
+    let indexer = Indexer::from_events(DECIMALS, &events).unwrap();
+    assert_eq!(indexer.depositors(), [ALICE.to_string()]);
+    assert_eq!(indexer.balance(ALICE), 100);
+    assert!(indexer.share_prices().is_empty());
+}
+
+#[test]
+fn test_rejects_malformed_token_events() {
+    let events = read_events(
+        r#"[{"ledger": 1, "timestamp": 10, "topics": ["transfer", "GA"], "data": "5"}]"#,
+    )
+    .unwrap();
+    assert!(matches!(
+        Indexer::from_events(DECIMALS, &events),
+        Err(Error::MissingTopic { index: 2, .. })
+    ));
+
+    let events =
+        read_events(r#"[{"ledger": 1, "timestamp": 10, "topics": ["mint", "GA"], "data": "x"}]"#)
+            .unwrap();
+    assert!(matches!(
+        Indexer::from_events(DECIMALS, &events),
+        Err(Error::InvalidAmount(_))
+    ));
+}
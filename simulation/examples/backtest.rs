@@ -0,0 +1,65 @@
+//! About six and a half months of a vault with ten depositors: a borrower
+//! keeping the pool around half utilized, weekly compounds, a BLND crash and
+//! partial recovery on Comet, and four emission rounds, each of which moves
+//! the clock a little over three weeks on top of the sixteen scripted weeks.
+//! Writes `vault.csv` and `users.csv` to the given directory, the current one
+//! by default.
+//!
+//! cargo run -p blend-vault-simulation --example backtest -- out/
+
+#![allow(clippy::inconsistent_digit_grouping)]
+
+use std::{fs::File, io::BufWriter, path::PathBuf};
+
+use blend_vault_simulation::{run, Scenario, DAY_IN_SECONDS, WEEK_IN_SECONDS};
+
+const USERS: usize = 10;
+const WEEKS: usize = 16;
+
+fn main() -> std::io::Result<()> {
+    let dir = PathBuf::from(std::env::args().nth(1).unwrap_or_else(|| ".".into()));
+    std::fs::create_dir_all(&dir)?;
+
+    let mut scenario = Scenario::new(USERS);
+    scenario.compound_every(WEEK_IN_SECONDS);
+    for user in 0..USERS {
+        scenario.deposit(user, (user as i128 + 1) * 1_000_0000000);
+    }
+    scenario.borrow(25_000_0000000);
+
+    for week in 0..WEEKS {
+        if week % 4 == 0 {
+            scenario.accrue_emissions();
+        }
+        if week == WEEKS / 2 {
+            scenario.comet_shock(-4_000);
+        }
+        if week == WEEKS / 2 + 4 {
+            scenario.comet_shock(3_000);
+        }
+        // A top-up met by more borrowing, then an exit and a partial withdrawal
+        if week == 4 {
+            scenario.deposit(0, 20_000_0000000).borrow(10_000_0000000);
+        }
+        if week == 10 {
+            scenario.redeem_all(USERS - 1).withdraw(0, 5_000_0000000);
+        }
+        for _ in 0..7 {
+            scenario.advance(DAY_IN_SECONDS);
+        }
+    }
+
+    let report = run(&scenario);
+    report.write_vault_csv(&mut BufWriter::new(File::create(dir.join("vault.csv"))?))?;
+    report.write_users_csv(&mut BufWriter::new(File::create(dir.join("users.csv"))?))?;
+
+    let last = report.last();
+    println!(
+        "{} steps, share price {} after {} days, {} paid in swap fees",
+        report.vault.len(),
+        last.share_price,
+        (last.timestamp - report.vault[0].timestamp) / DAY_IN_SECONDS,
+        last.total_swap_fees
+    );
+    Ok(())
+}
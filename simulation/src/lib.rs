@@ -0,0 +1,24 @@
+//! Deterministic backtests of Blend Vault economics.
+//!
+//! A [`Scenario`] scripts months of operation: deposits and withdrawals from
+//! many users, a borrower whose interest grows the pool's `b_rate`, emission
+//! rounds through `accrue_emissions`, compounds on a schedule and price
+//! shocks on Comet. [`run`] replays it against the real Blend contracts of
+//! [`TestFixture`] and returns a [`Report`] with the share price, the fees
+//! paid by compounding and every user's returns, ready to write out as CSV.
+
+// Amounts are written as whole units and 7 decimals, e.g. 1000_0000000
+#![allow(clippy::inconsistent_digit_grouping)]
+
+mod report;
+mod scenario;
+mod simulation;
+
+pub use blend_vault::mocks::{MockOracle, MockOracleClient, MOCK_ORACLE_DEFAULT_PRICE};
+pub use blend_vault::testutils::{TestFixture, DAY_IN_SECONDS, START_TIMESTAMP, WEEK_IN_SECONDS};
+pub use report::{Report, UserSample, VaultSample};
+pub use scenario::{Scenario, Step};
+pub use simulation::{run, Simulation, PRICE_SCALAR};
+
+#[cfg(test)]
+mod test;
@@ -0,0 +1,111 @@
+use std::io::{self, Write};
+
+/// Vault state after one step
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct VaultSample {
+    pub step: usize,
+    /// `Step::name`, or `compound` for a scheduled compound
+    pub action: &'static str,
+    pub timestamp: u64,
+    pub total_assets: i128,
+    pub total_supply: i128,
+    /// Assets one whole share redeems for
+    pub share_price: i128,
+    /// The USDC reserve's `b_rate`, 12 decimals
+    pub b_rate: i128,
+    /// USDC per BLND on Comet before fees, 7 decimals
+    pub comet_price: i128,
+    /// BLND the vault sold in this step's compound
+    pub blnd_compounded: i128,
+    /// USDC the compound reinvested
+    pub assets_compounded: i128,
+    /// Comet swap fee and price impact paid by this step's compound, the
+    /// BLND valued at the pre-swap price minus the USDC received
+    pub swap_fees: i128,
+    pub total_swap_fees: i128,
+}
+
+/// One user's position, sampled at the end of every `Step::Advance`
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct UserSample {
+    pub step: usize,
+    pub timestamp: u64,
+    pub user: usize,
+    pub shares: i128,
+    pub value: i128,
+    /// Assets deposited and withdrawn over the whole run
+    pub deposited: i128,
+    pub withdrawn: i128,
+    pub realized: i128,
+    pub unrealized: i128,
+    /// Total earnings over total deposited, in basis points
+    pub return_bps: i128,
+}
+
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct Report {
+    pub vault: Vec<VaultSample>,
+    pub users: Vec<UserSample>,
+}
+
+impl Report {
+    pub fn write_vault_csv(&self, out: &mut impl Write) -> io::Result<()> {
+        writeln!(
+            out,
+            "step,action,timestamp,total_assets,total_supply,share_price,b_rate,comet_price,\
+             blnd_compounded,assets_compounded,swap_fees,total_swap_fees"
+        )?;
+        for s in &self.vault {
+            writeln!(
+                out,
+                "{},{},{},{},{},{},{},{},{},{},{},{}",
+                s.step,
+                s.action,
+                s.timestamp,
+                s.total_assets,
+                s.total_supply,
+                s.share_price,
+                s.b_rate,
+                s.comet_price,
+                s.blnd_compounded,
+                s.assets_compounded,
+                s.swap_fees,
+                s.total_swap_fees
+            )?;
+        }
+        Ok(())
+    }
+
+    pub fn write_users_csv(&self, out: &mut impl Write) -> io::Result<()> {
+        writeln!(
+            out,
+            "step,timestamp,user,shares,value,deposited,withdrawn,realized,unrealized,return_bps"
+        )?;
+        for s in &self.users {
+            writeln!(
+                out,
+                "{},{},{},{},{},{},{},{},{},{}",
+                s.step,
+                s.timestamp,
+                s.user,
+                s.shares,
+                s.value,
+                s.deposited,
+                s.withdrawn,
+                s.realized,
+                s.unrealized,
+                s.return_bps
+            )?;
+        }
+        Ok(())
+    }
+
+    /// The samples of one user, oldest first
+    pub fn user(&self, user: usize) -> impl Iterator<Item = &UserSample> {
+        self.users.iter().filter(move |s| s.user == user)
+    }
+
+    pub fn last(&self) -> &VaultSample {
+        self.vault.last().expect("no steps were run")
+    }
+}
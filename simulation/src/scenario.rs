@@ -0,0 +1,108 @@
+/// One scripted action. Users are indices into the scenario's user list,
+/// amounts are raw units with 7 decimals.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Step {
+    /// Mint `assets` USDC to the user and deposit all of it
+    Deposit { user: usize, assets: i128 },
+    /// Withdraw `assets` USDC of the user's position
+    Withdraw { user: usize, assets: i128 },
+    /// Redeem every share the user holds
+    RedeemAll { user: usize },
+    /// The pool's borrower posts BLND collateral and borrows `assets` USDC.
+    /// Interest on the debt is what grows the USDC reserve's `b_rate`.
+    Borrow { assets: i128 },
+    /// The borrower repays `assets` USDC of debt
+    Repay { assets: i128 },
+    /// Run `TestFixture::accrue_emissions`, which moves the clock forward by
+    /// a little over three weeks
+    AccrueEmissions,
+    /// Compound as the keeper, outside of any schedule
+    Compound,
+    /// Move BLND's price on Comet by `bps` basis points, negative for a
+    /// drop, by trading against the pool
+    CometShock { bps: i32 },
+    /// Move the clock forward
+    Advance { seconds: u64 },
+}
+
+impl Step {
+    /// Label used for the step in the CSV output
+    pub fn name(&self) -> &'static str {
+        match self {
+            Step::Deposit { .. } => "deposit",
+            Step::Withdraw { .. } => "withdraw",
+            Step::RedeemAll { .. } => "redeem_all",
+            Step::Borrow { .. } => "borrow",
+            Step::Repay { .. } => "repay",
+            Step::AccrueEmissions => "accrue_emissions",
+            Step::Compound => "compound",
+            Step::CometShock { .. } => "comet_shock",
+            Step::Advance { .. } => "advance",
+        }
+    }
+}
+
+/// A script of steps run in order against a fresh fixture
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct Scenario {
+    pub users: usize,
+    pub steps: Vec<Step>,
+    /// Seconds between keeper compounds, `None` to only compound on
+    /// `Step::Compound`
+    pub compound_interval: Option<u64>,
+}
+
+impl Scenario {
+    pub fn new(users: usize) -> Self {
+        Self {
+            users,
+            ..Default::default()
+        }
+    }
+
+    pub fn compound_every(&mut self, seconds: u64) -> &mut Self {
+        self.compound_interval = Some(seconds);
+        self
+    }
+
+    pub fn step(&mut self, step: Step) -> &mut Self {
+        self.steps.push(step);
+        self
+    }
+
+    pub fn deposit(&mut self, user: usize, assets: i128) -> &mut Self {
+        self.step(Step::Deposit { user, assets })
+    }
+
+    pub fn withdraw(&mut self, user: usize, assets: i128) -> &mut Self {
+        self.step(Step::Withdraw { user, assets })
+    }
+
+    pub fn redeem_all(&mut self, user: usize) -> &mut Self {
+        self.step(Step::RedeemAll { user })
+    }
+
+    pub fn borrow(&mut self, assets: i128) -> &mut Self {
+        self.step(Step::Borrow { assets })
+    }
+
+    pub fn repay(&mut self, assets: i128) -> &mut Self {
+        self.step(Step::Repay { assets })
+    }
+
+    pub fn accrue_emissions(&mut self) -> &mut Self {
+        self.step(Step::AccrueEmissions)
+    }
+
+    pub fn compound(&mut self) -> &mut Self {
+        self.step(Step::Compound)
+    }
+
+    pub fn comet_shock(&mut self, bps: i32) -> &mut Self {
+        self.step(Step::CometShock { bps })
+    }
+
+    pub fn advance(&mut self, seconds: u64) -> &mut Self {
+        self.step(Step::Advance { seconds })
+    }
+}
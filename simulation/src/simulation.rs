@@ -0,0 +1,289 @@
+use blend_contract_sdk::pool;
+use blend_vault::CometPoolClient;
+use sep_41_token::testutils::MockTokenClient;
+use soroban_sdk::{testutils::Address as _, vec, Address};
+
+use crate::{Report, Scenario, Step, TestFixture, UserSample, VaultSample};
+
+/// Fixed point scale of share and Comet prices
+pub const PRICE_SCALAR: i128 = 1_0000000;
+
+const REQUEST_TYPE_SUPPLY_COLLATERAL: u32 = 2;
+const REQUEST_TYPE_BORROW: u32 = 4;
+const REQUEST_TYPE_REPAY: u32 = 5;
+
+/// BLND collateral posted per unit borrowed, both priced at 1 by the oracle
+const COLLATERAL_RATIO: i128 = 2;
+/// Comet rejects swaps of more than half its balance, shocks are split
+/// into trades of at most a third
+const MAX_SHOCK_SWAP_DIVISOR: i128 = 3;
+const MAX_SHOCK_SWAPS: usize = 32;
+
+/// Runs scenarios against a [`TestFixture`] and collects the samples.
+/// Everything is driven by the ledger clock and the fixture's seeded
+/// addresses, so the same scenario always produces the same report.
+pub struct Simulation<'a> {
+    pub fixture: TestFixture<'a>,
+    pub users: Vec<Address>,
+    /// Borrows from the pool to drive utilization and interest
+    pub borrower: Address,
+    /// Trades against Comet for price shocks
+    pub trader: Address,
+    deposited: Vec<i128>,
+    withdrawn: Vec<i128>,
+    total_swap_fees: i128,
+    next_compound: Option<u64>,
+    report: Report,
+}
+
+/// Run `scenario` on a fresh fixture
+pub fn run(scenario: &Scenario) -> Report {
+    let mut simulation = Simulation::new(scenario.users);
+    simulation.run(scenario);
+    simulation.into_report()
+}
+
+impl<'a> Simulation<'a> {
+    pub fn new(users: usize) -> Self {
+        let fixture = TestFixture::new();
+        let borrower = Address::generate(&fixture.env);
+        let trader = Address::generate(&fixture.env);
+        Self {
+            users: (0..users)
+                .map(|_| Address::generate(&fixture.env))
+                .collect(),
+            borrower,
+            trader,
+            deposited: std::vec![0; users],
+            withdrawn: std::vec![0; users],
+            total_swap_fees: 0,
+            next_compound: None,
+            report: Report::default(),
+            fixture,
+        }
+    }
+
+    pub fn run(&mut self, scenario: &Scenario) {
+        if let Some(interval) = scenario.compound_interval {
+            self.next_compound = Some(self.now() + interval);
+        }
+        for (index, step) in scenario.steps.iter().enumerate() {
+            let (blnd, assets, fees) = self.apply(step);
+            self.record_vault(index, step.name(), blnd, assets, fees);
+            if let (Some(interval), Some(due)) = (scenario.compound_interval, self.next_compound) {
+                if self.now() >= due {
+                    let (blnd, assets, fees) = self.compound();
+                    self.record_vault(index, "compound", blnd, assets, fees);
+                    self.next_compound = Some(self.now() + interval);
+                }
+            }
+            if matches!(step, Step::Advance { .. }) {
+                self.record_users(index);
+            }
+        }
+    }
+
+    pub fn report(&self) -> &Report {
+        &self.report
+    }
+
+    pub fn into_report(self) -> Report {
+        self.report
+    }
+
+    fn now(&self) -> u64 {
+        self.fixture.env.ledger().timestamp()
+    }
+
+    /// Apply one step, returning the BLND sold, assets reinvested and swap
+    /// fees paid if it compounded
+    fn apply(&mut self, step: &Step) -> (i128, i128, i128) {
+        let fixture = &self.fixture;
+        match *step {
+            Step::Deposit { user, assets } => {
+                let account = &self.users[user];
+                fixture.usdc_client.mint(account, &assets);
+                fixture
+                    .vault_client
+                    .deposit(&assets, account, account, account);
+                self.deposited[user] += assets;
+            }
+            Step::Withdraw { user, assets } => {
+                let account = &self.users[user];
+                fixture
+                    .vault_client
+                    .withdraw(&assets, account, account, account);
+                self.withdrawn[user] += assets;
+            }
+            Step::RedeemAll { user } => {
+                let account = &self.users[user];
+                let shares = fixture.vault_client.balance(account);
+                if shares > 0 {
+                    self.withdrawn[user] += fixture
+                        .vault_client
+                        .redeem(&shares, account, account, account);
+                }
+            }
+            Step::Borrow { assets } => {
+                let collateral = assets * COLLATERAL_RATIO;
+                fixture.blnd_client.mint(&self.borrower, &collateral);
+                let requests = vec![
+                    &fixture.env,
+                    pool::Request {
+                        request_type: REQUEST_TYPE_SUPPLY_COLLATERAL,
+                        address: fixture.blnd_token.clone(),
+                        amount: collateral,
+                    },
+                    pool::Request {
+                        request_type: REQUEST_TYPE_BORROW,
+                        address: fixture.usdc_token.clone(),
+                        amount: assets,
+                    },
+                ];
+                fixture.pool_client().submit(
+                    &self.borrower,
+                    &self.borrower,
+                    &self.borrower,
+                    &requests,
+                );
+            }
+            Step::Repay { assets } => {
+                fixture.usdc_client.mint(&self.borrower, &assets);
+                let requests = vec![
+                    &fixture.env,
+                    pool::Request {
+                        request_type: REQUEST_TYPE_REPAY,
+                        address: fixture.usdc_token.clone(),
+                        amount: assets,
+                    },
+                ];
+                fixture.pool_client().submit(
+                    &self.borrower,
+                    &self.borrower,
+                    &self.borrower,
+                    &requests,
+                );
+            }
+            Step::AccrueEmissions => {
+                fixture.accrue_emissions();
+            }
+            Step::Compound => return self.compound(),
+            Step::CometShock { bps } => self.comet_shock(bps),
+            Step::Advance { seconds } => fixture.advance_time(seconds),
+        }
+        (0, 0, 0)
+    }
+
+    /// Compound as the deployer. The BLND sold is read off Comet's balance
+    /// and valued at the price before the swap.
+    fn compound(&mut self) -> (i128, i128, i128) {
+        let fixture = &self.fixture;
+        let comet = CometPoolClient::new(&fixture.env, &fixture.comet_pool);
+        let price = self.comet_price();
+        let blnd_before = comet.get_balance(&fixture.blnd_token);
+        let assets = fixture.vault_client.compound(&fixture.deployer);
+        let blnd = comet.get_balance(&fixture.blnd_token) - blnd_before;
+        let fees = blnd * price / PRICE_SCALAR - assets;
+        self.total_swap_fees += fees;
+        (blnd, assets, fees)
+    }
+
+    /// USDC per BLND on Comet, ignoring the swap fee
+    pub fn comet_price(&self) -> i128 {
+        let fixture = &self.fixture;
+        let comet = CometPoolClient::new(&fixture.env, &fixture.comet_pool);
+        let blnd_balance = comet.get_balance(&fixture.blnd_token);
+        let usdc_balance = comet.get_balance(&fixture.usdc_token);
+        let blnd_weight = comet.get_normalized_weight(&fixture.blnd_token);
+        let usdc_weight = comet.get_normalized_weight(&fixture.usdc_token);
+        usdc_balance * blnd_weight * PRICE_SCALAR / (blnd_balance * usdc_weight)
+    }
+
+    /// Trade against Comet until BLND's price has moved by `bps`. Selling
+    /// `a` of a token with balance `b` moves the price by a factor of
+    /// `(b / (b + a)) ^ (1 + w_in / w_out)`, each trade solves that for the
+    /// remaining move and the swap fee is made up by the next one.
+    fn comet_shock(&self, bps: i32) {
+        let fixture = &self.fixture;
+        let comet = CometPoolClient::new(&fixture.env, &fixture.comet_pool);
+        let start = self.comet_price();
+        let target = start * (10_000 + bps as i128) / 10_000;
+        let (token_in, token_out) = if bps < 0 {
+            (&fixture.blnd_token, &fixture.usdc_token)
+        } else {
+            (&fixture.usdc_token, &fixture.blnd_token)
+        };
+        for _ in 0..MAX_SHOCK_SWAPS {
+            let price = self.comet_price();
+            if (bps <= 0 && price <= target) || (bps >= 0 && price >= target) {
+                return;
+            }
+            let remaining = if bps < 0 {
+                price as f64 / target as f64
+            } else {
+                target as f64 / price as f64
+            };
+            let balance = comet.get_balance(token_in);
+            let weight_ratio = comet.get_normalized_weight(token_in) as f64
+                / comet.get_normalized_weight(token_out) as f64;
+            let amount = (balance as f64 * (remaining.powf(1.0 / (1.0 + weight_ratio)) - 1.0))
+                .ceil() as i128;
+            let amount = amount.clamp(1, balance / MAX_SHOCK_SWAP_DIVISOR);
+            MockTokenClient::new(&fixture.env, token_in).mint(&self.trader, &amount);
+            comet.swap_exact_amount_in(token_in, &amount, token_out, &0, &i128::MAX, &self.trader);
+        }
+    }
+
+    fn record_vault(
+        &mut self,
+        step: usize,
+        action: &'static str,
+        blnd: i128,
+        assets: i128,
+        fees: i128,
+    ) {
+        let fixture = &self.fixture;
+        let sample = VaultSample {
+            step,
+            action,
+            timestamp: self.now(),
+            total_assets: fixture.vault_client.total_assets(),
+            total_supply: fixture.vault_client.total_supply(),
+            share_price: fixture.vault_client.convert_to_assets(&PRICE_SCALAR),
+            b_rate: fixture
+                .pool_client()
+                .get_reserve(&fixture.usdc_token)
+                .data
+                .b_rate,
+            comet_price: self.comet_price(),
+            blnd_compounded: blnd,
+            assets_compounded: assets,
+            swap_fees: fees,
+            total_swap_fees: self.total_swap_fees,
+        };
+        self.report.vault.push(sample);
+    }
+
+    fn record_users(&mut self, step: usize) {
+        let timestamp = self.now();
+        for (user, account) in self.users.iter().enumerate() {
+            if self.deposited[user] == 0 {
+                continue;
+            }
+            let position = self.fixture.vault_client.position_of(account);
+            let earnings = self.fixture.vault_client.earnings_of(account);
+            self.report.users.push(UserSample {
+                step,
+                timestamp,
+                user,
+                shares: position.shares,
+                value: position.value,
+                deposited: self.deposited[user],
+                withdrawn: self.withdrawn[user],
+                realized: earnings.realized,
+                unrealized: earnings.unrealized,
+                return_bps: earnings.total * 10_000 / self.deposited[user],
+            });
+        }
+    }
+}
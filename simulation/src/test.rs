@@ -0,0 +1,168 @@
+use super::*;
+
+fn assert_near(actual: i128, expected: i128) {
+    assert!(
+        (actual - expected).abs() <= 2,
+        "expected {} got {}",
+        expected,
+        actual
+    );
+}
+
+fn lending_scenario() -> Scenario {
+    let mut scenario = Scenario::new(2);
+    scenario
+        .deposit(0, 10_000_0000000)
+        .deposit(1, 5_000_0000000)
+        .borrow(7_500_0000000);
+    for _ in 0..30 {
+        scenario.advance(DAY_IN_SECONDS);
+    }
+    scenario
+}
+
+#[test]
+fn test_borrow_interest_grows_share_price() {
+    let report = run(&lending_scenario());
+
+    let advances: Vec<&VaultSample> = report
+        .vault
+        .iter()
+        .filter(|s| s.action == "advance")
+        .collect();
+    assert_eq!(advances.len(), 30);
+    assert_eq!(
+        advances.last().unwrap().timestamp,
+        START_TIMESTAMP + 30 * DAY_IN_SECONDS
+    );
+    for pair in advances.windows(2) {
+        assert!(pair[1].b_rate > pair[0].b_rate);
+        assert!(pair[1].share_price >= pair[0].share_price);
+    }
+    assert!(report.last().share_price > report.vault[0].share_price);
+    // No compounds, so nothing was paid to Comet
+    assert_eq!(report.last().total_swap_fees, 0);
+
+    // Both users hold from the same price and earn the same rate
+    assert_eq!(report.users.len(), 60);
+    let first = report.user(0).last().unwrap();
+    let second = report.user(1).last().unwrap();
+    assert!(first.return_bps > 0);
+    assert!((first.return_bps - second.return_bps).abs() <= 1);
+    assert_eq!(first.unrealized, first.value - first.deposited);
+    assert_eq!(first.realized, 0);
+}
+
+#[test]
+fn test_scheduled_compounds_reinvest_emissions() {
+    let mut scenario = Scenario::new(1);
+    scenario
+        .compound_every(WEEK_IN_SECONDS)
+        .deposit(0, 1_000_0000000)
+        .accrue_emissions();
+    for _ in 0..14 {
+        scenario.advance(DAY_IN_SECONDS);
+    }
+    let report = run(&scenario);
+
+    // One compound once emissions moved the clock past the first week, then
+    // one per week
+    let compounds: Vec<&VaultSample> = report
+        .vault
+        .iter()
+        .filter(|s| s.action == "compound")
+        .collect();
+    assert_eq!(compounds.len(), 3);
+    assert_eq!(
+        compounds[2].timestamp - compounds[1].timestamp,
+        WEEK_IN_SECONDS
+    );
+    assert!(compounds[1].blnd_compounded > 0);
+    assert!(compounds[1].assets_compounded > 0);
+    assert!(compounds.iter().all(|s| s.swap_fees >= 0));
+    assert_eq!(
+        report.last().total_swap_fees,
+        compounds.iter().map(|s| s.swap_fees).sum::<i128>()
+    );
+    assert!(report.last().share_price > report.vault[0].share_price);
+    assert!(report.user(0).last().unwrap().return_bps > 0);
+}
+
+#[test]
+fn test_comet_shocks_move_blnd_price() {
+    let mut simulation = Simulation::new(0);
+    let start = simulation.comet_price();
+    let mut scenario = Scenario::new(0);
+    scenario.comet_shock(-3_000).comet_shock(2_000);
+    simulation.run(&scenario);
+
+    let report = simulation.report();
+    let dropped = report.vault[0].comet_price;
+    let target = start * 7_000 / 10_000;
+    assert!(dropped <= target && dropped >= target * 99 / 100);
+    let recovered = report.vault[1].comet_price;
+    let target = dropped * 12_000 / 10_000;
+    assert!(recovered >= target && recovered <= target * 101 / 100);
+}
+
+#[test]
+fn test_redeeming_realizes_returns() {
+    let mut scenario = lending_scenario();
+    scenario
+        .redeem_all(1)
+        .withdraw(0, 1_000_0000000)
+        .advance(DAY_IN_SECONDS);
+    let report = run(&scenario);
+
+    let redeemed = report.user(1).last().unwrap();
+    assert_eq!(redeemed.shares, 0);
+    assert_eq!(redeemed.value, 0);
+    assert!(redeemed.withdrawn > redeemed.deposited);
+    assert_near(redeemed.realized, redeemed.withdrawn - redeemed.deposited);
+    assert_eq!(redeemed.unrealized, 0);
+
+    let partial = report.user(0).last().unwrap();
+    assert_eq!(partial.withdrawn, 1_000_0000000);
+    assert!(partial.realized > 0);
+    assert!(partial.unrealized > 0);
+}
+
+#[test]
+fn test_runs_are_deterministic() {
+    let mut scenario = lending_scenario();
+    scenario
+        .compound_every(WEEK_IN_SECONDS)
+        .comet_shock(-1_000)
+        .redeem_all(0)
+        .advance(DAY_IN_SECONDS);
+
+    let csv = |report: &Report| {
+        let mut vault = Vec::new();
+        report.write_vault_csv(&mut vault).unwrap();
+        let mut users = Vec::new();
+        report.write_users_csv(&mut users).unwrap();
+        (
+            String::from_utf8(vault).unwrap(),
+            String::from_utf8(users).unwrap(),
+        )
+    };
+    let (vault, users) = csv(&run(&scenario));
+    assert_eq!(csv(&run(&scenario)), (vault.clone(), users.clone()));
+
+    let mut vault_lines = vault.lines();
+    assert_eq!(
+        vault_lines.next().unwrap(),
+        "step,action,timestamp,total_assets,total_supply,share_price,b_rate,comet_price,\
+         blnd_compounded,assets_compounded,swap_fees,total_swap_fees"
+    );
+    assert!(vault_lines
+        .next()
+        .unwrap()
+        .starts_with("0,deposit,1700000000,"));
+    let mut user_lines = users.lines();
+    assert_eq!(
+        user_lines.next().unwrap(),
+        "step,timestamp,user,shares,value,deposited,withdrawn,realized,unrealized,return_bps"
+    );
+    assert_eq!(user_lines.count(), 62);
+}
@@ -0,0 +1,274 @@
+#![no_std]
+
+//! Blend supply strategy for the Blend Vault.
+//!
+//! Supplies the vault's asset as collateral to a single Blend pool reserve and
+//! implements the vault's `Strategy` interface: `asset`, `deposit`, `withdraw`,
+//! `total_assets`, `harvest` and `max_withdrawable`. The vault registers it
+//! with `add_strategy(strategy, false, max_debt)`. Its `harvest` claims the
+//! reserve's BLND supply emissions, the reward `compound` expects.
+
+use soroban_sdk::{
+    contract, contractclient, contractevent, contractimpl, contracttype, token, Address, Env, Map,
+    Vec,
+};
+
+#[contract]
+pub struct BlendStrategy;
+
+// EVENTS
+
+#[contractevent]
+pub struct StrategyDepositEvent {
+    pub amount: i128,
+}
+
+#[contractevent]
+pub struct StrategyWithdrawEvent {
+    pub to: Address,
+    pub amount: i128,
+}
+
+#[contractevent]
+pub struct HarvestEvent {
+    pub claimed: i128,
+}
+
+// STORAGE KEYS
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum DataKey {
+    Initialized,
+    Vault,
+    Asset,
+    BlendPool,
+    ReserveIndex,
+    RewardTokenId,
+}
+
+// BLEND TYPES
+#[contracttype]
+#[derive(Clone)]
+pub struct Request {
+    pub request_type: u32,
+    pub address: Address,
+    pub amount: i128,
+}
+
+#[contracttype]
+pub struct Positions {
+    pub collateral: Map<u32, i128>,
+    pub liabilities: Map<u32, i128>,
+    pub supply: Map<u32, i128>,
+}
+
+#[contracttype]
+#[derive(Clone, Debug)]
+pub struct ReserveConfig {
+    pub index: u32,
+    pub decimals: u32,
+    pub c_factor: u32,
+    pub l_factor: u32,
+    pub util: u32,
+    pub max_util: u32,
+    pub r_base: u32,
+    pub r_one: u32,
+    pub r_two: u32,
+    pub r_three: u32,
+    pub reactivity: u32,
+    pub supply_cap: i128,
+    pub enabled: bool,
+}
+
+#[contracttype]
+#[derive(Clone, Debug)]
+pub struct ReserveData {
+    pub d_rate: i128,
+    pub b_rate: i128,
+    pub ir_mod: i128,
+    pub b_supply: i128,
+    pub d_supply: i128,
+    pub backstop_credit: i128,
+    pub last_time: u64,
+}
+
+#[contracttype]
+#[derive(Clone, Debug)]
+pub struct Reserve {
+    pub asset: Address,
+    pub config: ReserveConfig,
+    pub data: ReserveData,
+    pub scalar: i128,
+}
+
+const REQUEST_TYPE_SUPPLY_COLLATERAL: u32 = 2;
+const REQUEST_TYPE_WITHDRAW_COLLATERAL: u32 = 3;
+const BLEND_RATE_SCALAR: i128 = 1_000_000_000_000;
+
+#[contractclient(name = "BlendPoolClient")]
+pub trait BlendPoolInterface {
+    fn submit_with_allowance(
+        env: Env,
+        from: Address,
+        spender: Address,
+        to: Address,
+        requests: Vec<Request>,
+    ) -> Positions;
+    fn get_positions(env: Env, address: Address) -> Positions;
+    fn claim(env: Env, from: Address, reserve_token_ids: Vec<u32>, to: Address) -> i128;
+    fn get_reserve(env: Env, asset: Address) -> Reserve;
+}
+
+#[contractimpl]
+impl BlendStrategy {
+    /// Initialize the strategy after deployment, this function can only be called once.
+    /// The reserve index and the claimable reward token id of the asset are
+    /// read from `blend_pool`, the same way the vault discovers them.
+    /// ### Arguments
+    /// * `vault` - The vault allowed to move funds through the strategy
+    /// * `asset` - The underlying asset address
+    /// * `blend_pool` - The Blend pool contract address
+    pub fn initialize(e: &Env, vault: Address, asset: Address, blend_pool: Address) {
+        if e.storage().instance().has(&DataKey::Initialized) {
+            panic!("Contract is already initialized");
+        }
+        let reserve_index = BlendPoolClient::new(e, &blend_pool)
+            .get_reserve(&asset)
+            .config
+            .index;
+        // Blend's claimable token id for a reserve's supply emissions
+        let reward_token_id = reserve_index * 2 + 1;
+        e.storage().instance().set(&DataKey::Vault, &vault);
+        e.storage().instance().set(&DataKey::Asset, &asset);
+        e.storage().instance().set(&DataKey::BlendPool, &blend_pool);
+        e.storage()
+            .instance()
+            .set(&DataKey::ReserveIndex, &reserve_index);
+        e.storage()
+            .instance()
+            .set(&DataKey::RewardTokenId, &reward_token_id);
+        e.storage().instance().set(&DataKey::Initialized, &true);
+    }
+
+    pub fn vault(e: &Env) -> Address {
+        e.storage()
+            .instance()
+            .get(&DataKey::Vault)
+            .expect("Vault not initialized")
+    }
+
+    pub fn blend_pool(e: &Env) -> Address {
+        e.storage()
+            .instance()
+            .get(&DataKey::BlendPool)
+            .expect("Blend pool not initialized")
+    }
+
+    pub fn asset(e: &Env) -> Address {
+        e.storage()
+            .instance()
+            .get(&DataKey::Asset)
+            .expect("Asset not initialized")
+    }
+
+    /// Supply `amount` of the asset held by the strategy to Blend
+    pub fn deposit(e: &Env, amount: i128) {
+        Self::vault(e).require_auth();
+        if amount <= 0 {
+            panic!("Invalid amount");
+        }
+        let strategy_address = e.current_contract_address();
+        let pool_address = Self::blend_pool(e);
+        let asset = Self::asset(e);
+        let expiration_ledger = e.ledger().sequence() + 1000;
+        token::TokenClient::new(e, &asset).approve(
+            &strategy_address,
+            &pool_address,
+            &amount,
+            &expiration_ledger,
+        );
+        let mut requests: Vec<Request> = Vec::new(e);
+        requests.push_back(Request {
+            request_type: REQUEST_TYPE_SUPPLY_COLLATERAL,
+            address: asset,
+            amount,
+        });
+        BlendPoolClient::new(e, &pool_address).submit_with_allowance(
+            &strategy_address,
+            &strategy_address,
+            &strategy_address,
+            &requests,
+        );
+        StrategyDepositEvent { amount }.publish(e);
+    }
+
+    /// Withdraw `amount` of the asset from Blend straight to `to`
+    pub fn withdraw(e: &Env, amount: i128, to: Address) -> i128 {
+        Self::vault(e).require_auth();
+        if amount <= 0 {
+            return 0;
+        }
+        let strategy_address = e.current_contract_address();
+        let mut requests: Vec<Request> = Vec::new(e);
+        requests.push_back(Request {
+            request_type: REQUEST_TYPE_WITHDRAW_COLLATERAL,
+            address: Self::asset(e),
+            amount,
+        });
+        BlendPoolClient::new(e, &Self::blend_pool(e)).submit_with_allowance(
+            &strategy_address,
+            &strategy_address,
+            &to,
+            &requests,
+        );
+        StrategyWithdrawEvent { to, amount }.publish(e);
+        amount
+    }
+
+    /// Value of the strategy's Blend collateral in the asset
+    pub fn total_assets(e: &Env) -> i128 {
+        let pool_client = BlendPoolClient::new(e, &Self::blend_pool(e));
+        let reserve_index: u32 = e
+            .storage()
+            .instance()
+            .get(&DataKey::ReserveIndex)
+            .expect("Reserve index not initialized");
+        let positions = pool_client.get_positions(&e.current_contract_address());
+        let collateral_b_tokens = positions.collateral.get(reserve_index).unwrap_or(0);
+        if collateral_b_tokens == 0 {
+            return 0;
+        }
+        let reserve = pool_client.get_reserve(&Self::asset(e));
+        let pool_assets = collateral_b_tokens
+            .checked_mul(reserve.data.b_rate)
+            .unwrap_or_else(|| panic!("Blend collateral overflow"));
+        pool_assets / BLEND_RATE_SCALAR
+    }
+
+    /// Claim the reserve's BLND supply emissions to the vault, returning the
+    /// amount of BLND claimed
+    pub fn harvest(e: &Env) -> i128 {
+        let vault = Self::vault(e);
+        vault.require_auth();
+        let reward_token_id: u32 = e
+            .storage()
+            .instance()
+            .get(&DataKey::RewardTokenId)
+            .expect("Reward token id not initialized");
+        let mut reserve_ids: Vec<u32> = Vec::new(e);
+        reserve_ids.push_back(reward_token_id);
+        let claimed = BlendPoolClient::new(e, &Self::blend_pool(e)).claim(
+            &e.current_contract_address(),
+            &reserve_ids,
+            &vault,
+        );
+        HarvestEvent { claimed }.publish(e);
+        claimed
+    }
+
+    /// Collateral that the Blend pool can pay out right now
+    pub fn max_withdrawable(e: &Env) -> i128 {
+        let liquidity = token::TokenClient::new(e, &Self::asset(e)).balance(&Self::blend_pool(e));
+        Self::total_assets(e).min(liquidity)
+    }
+}